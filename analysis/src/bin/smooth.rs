@@ -0,0 +1,196 @@
+use analysis::{Sample, Smoothing, current_thresholds, smooth};
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::Parser;
+use dotenvy::dotenv;
+use logic::{ActivityState, HiddenState};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::env;
+
+/// Offline HMM/Viterbi smoothing of the stored states, per device
+#[derive(Parser, Debug)]
+#[command(name = "smooth")]
+struct Args {
+    /// Hours of sedentary_log to smooth, by measurement time, up to now
+    #[arg(long, default_value_t = 24)]
+    hours: i64,
+
+    /// Only this device (all devices when left out)
+    #[arg(short, long)]
+    device: Option<String>,
+
+    /// Add a non-wear state below sedentary
+    #[arg(long)]
+    non_wear: bool,
+
+    /// Print the results without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    let args = Args::parse();
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let pool = db::get_db_pool(&database_url)
+        .await
+        .expect("Failed to connect to database");
+
+    if let Err(e) = run(&pool, &args).await {
+        eprintln!("Smoothing failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(pool: &PgPool, args: &Args) -> Result<(), sqlx::Error> {
+    let window_end = Utc::now();
+    let window_start = window_end - TimeDelta::hours(args.hours);
+    println!("Smoothing the last {}h...", args.hours);
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, device_id AS "device_id!", state, acceleration_val,
+               COALESCE(measured_at, created_at) AS "at!"
+        FROM sedentary_log
+        WHERE COALESCE(measured_at, created_at) > $1
+          AND COALESCE(measured_at, created_at) <= $2
+          AND device_id IS NOT NULL
+          AND ($3::TEXT IS NULL OR device_id = $3)
+        ORDER BY device_id, 5, id
+        "#,
+        window_start,
+        window_end,
+        args.device
+    )
+    .fetch_all(pool)
+    .await?;
+    if rows.is_empty() {
+        println!("No data found for the last {}h.", args.hours);
+        return Ok(());
+    }
+
+    let mut devices: BTreeMap<String, Vec<Sample>> = BTreeMap::new();
+    for row in rows {
+        let (Some(acc), Ok(state)) = (row.acceleration_val, row.state.parse::<ActivityState>())
+        else {
+            continue;
+        };
+        devices.entry(row.device_id).or_default().push(Sample {
+            id: row.id,
+            at: row.at,
+            acc,
+            state,
+        });
+    }
+
+    for (device, samples) in &devices {
+        let thresholds = current_thresholds(pool, device).await?;
+        let Some(smoothing) = smooth(samples, &thresholds, args.non_wear) else {
+            println!("{}: too few records ({})", device, samples.len());
+            continue;
+        };
+        print_summary(device, samples, &smoothing);
+        if !args.dry_run {
+            let id = store(
+                pool,
+                device,
+                samples,
+                &smoothing,
+                (window_start, window_end),
+            )
+            .await?;
+            println!("  Stored as run {}", id);
+        }
+    }
+    Ok(())
+}
+
+fn print_summary(device: &str, samples: &[Sample], smoothing: &Smoothing) {
+    let report = smoothing.report;
+    println!(
+        "{}: {} records in {} segment(s), {} iterations{}, log-likelihood {:.1}",
+        device,
+        samples.len(),
+        smoothing.segments,
+        report.iterations,
+        if report.converged {
+            ""
+        } else {
+            " (not converged)"
+        },
+        report.log_likelihood
+    );
+    for state in &smoothing.model.states {
+        let raw = samples
+            .iter()
+            .filter(|s| HiddenState::from(s.state) == *state)
+            .count();
+        let cleaned = smoothing.states.iter().filter(|s| *s == state).count();
+        println!("  {:<9} {:>7} raw, {:>7} cleaned", state, raw, cleaned);
+    }
+    println!(
+        "  {} record(s) relabelled ({:.1}%)",
+        smoothing.relabelled,
+        smoothing.relabelled as f64 / samples.len() as f64 * 100.0
+    );
+}
+
+// Run parameters and the cleaned state of every record, in one transaction
+async fn store(
+    pool: &PgPool,
+    device: &str,
+    samples: &[Sample],
+    smoothing: &Smoothing,
+    (window_start, window_end): (DateTime<Utc>, DateTime<Utc>),
+) -> Result<i64, sqlx::Error> {
+    let model = &smoothing.model;
+    let states: Vec<String> = model.states.iter().map(|s| s.to_string()).collect();
+    let transitions: Vec<f64> = model.transitions.concat();
+
+    let mut tx = pool.begin().await?;
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO hmm_runs
+            (device_id, window_start, window_end, samples, segments, states, initial,
+             transitions, means, stds, iterations, converged, log_likelihood, relabelled)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        RETURNING id
+        "#,
+        device,
+        window_start,
+        window_end,
+        samples.len() as i32,
+        smoothing.segments as i32,
+        &states,
+        &model.initial,
+        &transitions,
+        &model.means,
+        &model.stds,
+        smoothing.report.iterations as i32,
+        smoothing.report.converged,
+        smoothing.report.log_likelihood,
+        smoothing.relabelled as i32
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let ids: Vec<i32> = samples.iter().map(|s| s.id).collect();
+    let cleaned: Vec<String> = smoothing.states.iter().map(|s| s.to_string()).collect();
+    sqlx::query!(
+        r#"
+        UPDATE sedentary_log AS log
+        SET smoothed_state = cleaned.state, hmm_run_id = $3
+        FROM UNNEST($1::INTEGER[], $2::TEXT[]) AS cleaned (id, state)
+        WHERE log.id = cleaned.id
+        "#,
+        &ids,
+        &cleaned,
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(id)
+}
@@ -0,0 +1,282 @@
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use logic::{
+    ActivityState, Clustering, FitReport, GaussianHmm, HiddenState, Thresholds, jenks_breaks,
+    kmeans_1d, log_acc,
+};
+use sqlx::PgExecutor;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+// ANALYSIS CONFIGURATION
+pub const MIN_SAMPLES: usize = 100; // Fewer records give no suggestion
+const CLUSTERS: usize = 3; // Sedentary, fidget, active
+pub const MAX_GAP_SEC: i64 = 30; // Longer gaps split the history and count as no time (the server's TIMER_MAX_GAP_SEC default)
+const HMM_MAX_ITER: usize = 100;
+const HMM_TOLERANCE: f64 = 1e-6; // Log-likelihood gain per record below which Baum-Welch stops
+
+/// How the smoothed acceleration is split into three groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Method {
+    #[default]
+    KMeans, // Breaks halfway between the cluster centers
+    Jenks, // Breaks in the widest gaps (natural breaks)
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::KMeans => "kmeans",
+            Method::Jenks => "jenks",
+        }
+    }
+
+    fn cluster(&self, values: &[f64]) -> Option<Clustering> {
+        match self {
+            Method::KMeans => kmeans_1d(values, CLUSTERS),
+            Method::Jenks => jenks_breaks(values, CLUSTERS),
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "kmeans" => Ok(Method::KMeans),
+            "jenks" => Ok(Method::Jenks),
+            other => Err(format!(
+                "unknown method '{}' (expected kmeans or jenks)",
+                other
+            )),
+        }
+    }
+}
+
+/// Records per state, as the server classified them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateCounts {
+    pub sedentary: u64,
+    pub fidget: u64,
+    pub active: u64,
+}
+
+impl StateCounts {
+    pub fn add(&mut self, state: ActivityState) {
+        match state {
+            ActivityState::Sedentary => self.sedentary += 1,
+            ActivityState::Fidget => self.fidget += 1,
+            ActivityState::Active => self.active += 1,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.sedentary + self.fidget + self.active
+    }
+}
+
+/// Time spent per state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StateTime {
+    pub sedentary: TimeDelta,
+    pub fidget: TimeDelta,
+    pub active: TimeDelta,
+}
+
+impl StateTime {
+    pub fn add(&mut self, state: ActivityState, time: TimeDelta) {
+        match state {
+            ActivityState::Sedentary => self.sedentary += time,
+            ActivityState::Fidget => self.fidget += time,
+            ActivityState::Active => self.active += time,
+        }
+    }
+}
+
+/// Time per state and UTC day of one device's records, given in time order.
+/// A record lasts until the next one, whatever the sample rate; gaps longer
+/// than MAX_GAP_SEC are unknown time. Every day with a record is listed.
+pub fn daily_time(
+    records: impl IntoIterator<Item = (DateTime<Utc>, ActivityState)>,
+) -> BTreeMap<NaiveDate, StateTime> {
+    let max_gap = TimeDelta::seconds(MAX_GAP_SEC);
+    let mut days: BTreeMap<NaiveDate, StateTime> = BTreeMap::new();
+    let mut previous: Option<(DateTime<Utc>, ActivityState)> = None;
+    for (at, state) in records {
+        days.entry(at.date_naive()).or_default();
+        if let Some((since, held)) = previous {
+            let gap = at - since;
+            if gap <= max_gap {
+                days.entry(since.date_naive()).or_default().add(held, gap);
+            }
+        }
+        previous = Some((at, state));
+    }
+    days
+}
+
+/// One row of activity_summary
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailySummary {
+    pub sedentary_minutes: f32,
+    pub active_minutes: f32, // Fidgeting counts as active
+    pub dominant_state: ActivityState,
+    pub activity_score: i32, // Share of active minutes, 0-100
+}
+
+fn minutes(time: TimeDelta) -> f32 {
+    (time.num_milliseconds() as f32 / 60_000.0 * 100.0).round() / 100.0
+}
+
+impl DailySummary {
+    pub fn from_time(time: &StateTime) -> Self {
+        let sedentary_minutes = minutes(time.sedentary);
+        let active_minutes = minutes(time.active + time.fidget);
+        let total = sedentary_minutes + active_minutes;
+        let activity_score = if total > 0.0 {
+            (active_minutes / total * 100.0) as i32
+        } else {
+            0
+        };
+        let dominant_state = if active_minutes > sedentary_minutes {
+            ActivityState::Active
+        } else {
+            ActivityState::Sedentary
+        };
+        DailySummary {
+            sedentary_minutes,
+            active_minutes,
+            dominant_state,
+            activity_score,
+        }
+    }
+}
+
+/// Thresholds at the breaks between the three clusters of a device's smoothed acceleration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub method: Method,
+    pub centers: Vec<f32>, // Lowest first
+    pub thresholds: Thresholds,
+}
+
+// None with too few records, or when the data doesn't separate into three usable groups
+pub fn suggest(values: &[f32], method: Method) -> Option<Suggestion> {
+    if values.len() < MIN_SAMPLES {
+        return None;
+    }
+    let values: Vec<f64> = values.iter().map(|v| *v as f64).collect();
+    let clustering = method.cluster(&values)?;
+
+    let thresholds = Thresholds {
+        fidget: clustering.breaks[0] as f32,
+        active: clustering.breaks[1] as f32,
+    };
+    if !(thresholds.fidget > 0.0 && thresholds.active > thresholds.fidget) {
+        return None;
+    }
+    Some(Suggestion {
+        method,
+        centers: clustering
+            .clusters
+            .iter()
+            .map(|cluster| cluster.center as f32)
+            .collect(),
+        thresholds,
+    })
+}
+
+// Thresholds the device runs with (its own settings, else the defaults)
+pub async fn current_thresholds(
+    executor: impl PgExecutor<'_>,
+    device: &str,
+) -> Result<Thresholds, sqlx::Error> {
+    let row = sqlx::query!(
+        "SELECT thresh_fidget, thresh_active FROM device_settings WHERE device_id = $1",
+        device
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(row.map_or_else(Thresholds::default, |row| Thresholds {
+        fidget: row.thresh_fidget,
+        active: row.thresh_active,
+    }))
+}
+
+/// One stored record as the offline smoothing sees it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub id: i32,
+    pub at: DateTime<Utc>,    // Measured time (arrival time for old records)
+    pub acc: f32,             // Smoothed acceleration
+    pub state: ActivityState, // Real-time label
+}
+
+// Runs of time-ordered records without a gap longer than max_gap
+pub fn segments(samples: &[Sample], max_gap: TimeDelta) -> Vec<&[Sample]> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..samples.len() {
+        if samples[i].at - samples[i - 1].at > max_gap {
+            runs.push(&samples[start..i]);
+            start = i;
+        }
+    }
+    if start < samples.len() {
+        runs.push(&samples[start..]);
+    }
+    runs
+}
+
+/// HMM fitted to one device's history and the cleaned states, in record order
+#[derive(Debug, Clone)]
+pub struct Smoothing {
+    pub model: GaussianHmm,
+    pub report: FitReport,
+    pub segments: usize,
+    pub states: Vec<HiddenState>,
+    pub relabelled: usize, // Records whose cleaned state differs from the real-time label
+}
+
+// Fits the model to the whole history (Baum-Welch), then decodes each
+// stretch between data gaps (Viterbi). None with too few records.
+pub fn smooth(samples: &[Sample], thresholds: &Thresholds, non_wear: bool) -> Option<Smoothing> {
+    if samples.len() < MIN_SAMPLES {
+        return None;
+    }
+    let runs = segments(samples, TimeDelta::seconds(MAX_GAP_SEC));
+    let sequences: Vec<Vec<f64>> = runs
+        .iter()
+        .map(|run| run.iter().map(|sample| log_acc(sample.acc)).collect())
+        .collect();
+
+    let mut model = GaussianHmm::from_thresholds(thresholds, non_wear);
+    let report = model.fit(&sequences, HMM_MAX_ITER, HMM_TOLERANCE);
+    let states: Vec<HiddenState> = sequences
+        .iter()
+        .flat_map(|sequence| model.viterbi(sequence))
+        .collect();
+    let relabelled = samples
+        .iter()
+        .zip(&states)
+        .filter(|(sample, state)| HiddenState::from(sample.state) != **state)
+        .count();
+
+    Some(Smoothing {
+        model,
+        report,
+        segments: runs.len(),
+        states,
+        relabelled,
+    })
+}
+
+#[cfg(test)]
+mod tests;
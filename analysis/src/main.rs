@@ -0,0 +1,229 @@
+use analysis::{
+    DailySummary, Method, StateCounts, Suggestion, current_thresholds, daily_time, suggest,
+};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Utc};
+use clap::Parser;
+use dotenvy::dotenv;
+use logic::{ActivityState, Thresholds};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::BTreeMap;
+use std::env;
+
+/// Nightly analysis: daily summaries and suggested thresholds per device
+#[derive(Parser, Debug)]
+#[command(name = "nightly")]
+struct Args {
+    /// Hours of sedentary_log to analyse, up to now
+    #[arg(long, default_value_t = 24)]
+    hours: i64,
+
+    /// Clustering used for the suggestions: kmeans or jenks
+    #[arg(short, long, default_value = "kmeans")]
+    method: Method,
+
+    /// Print the results without writing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+// Records of one device
+#[derive(Default)]
+struct DeviceData {
+    values: Vec<f32>, // Smoothed acceleration in the analysed window
+    counts: StateCounts,
+    records: Vec<(DateTime<Utc>, ActivityState)>, // From the start of its first day, for the summaries
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    let args = Args::parse();
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let pool = db::get_db_pool(&database_url)
+        .await
+        .expect("Failed to connect to database");
+
+    if let Err(e) = run(&pool, &args).await {
+        eprintln!("Nightly analysis failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(pool: &PgPool, args: &Args) -> Result<(), sqlx::Error> {
+    let window_end = Utc::now();
+    let window_start = window_end - TimeDelta::hours(args.hours);
+    println!(
+        "Starting nightly analysis of the last {}h ({})...",
+        args.hours, args.method
+    );
+
+    // Whole UTC days the window touches, by measurement time, so late and
+    // replayed records land on the day they were measured
+    let first_day = window_start.date_naive().and_time(NaiveTime::MIN).and_utc();
+    let rows = sqlx::query!(
+        r#"
+        SELECT device_id AS "device_id!", state, acceleration_val,
+               COALESCE(measured_at, created_at) AS "at!"
+        FROM sedentary_log
+        WHERE device_id IS NOT NULL
+          AND COALESCE(measured_at, created_at) >= $1
+          AND COALESCE(measured_at, created_at) <= $2
+        ORDER BY device_id, 4, id
+        "#,
+        first_day,
+        window_end
+    )
+    .fetch_all(pool)
+    .await?;
+    if rows.is_empty() {
+        println!("No data found for the last {}h.", args.hours);
+        return Ok(());
+    }
+    println!("Loaded {} records.", rows.len());
+
+    // Records from before device ids are left out
+    let mut devices: BTreeMap<String, DeviceData> = BTreeMap::new();
+    for row in rows {
+        let Ok(state) = row.state.parse::<ActivityState>() else {
+            continue;
+        };
+        let data = devices.entry(row.device_id).or_default();
+        data.records.push((row.at, state));
+        if row.at > window_start {
+            data.counts.add(state);
+            data.values.extend(row.acceleration_val);
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    for (device, data) in &devices {
+        let counts = data.counts;
+        if counts.total() == 0 {
+            continue; // Only records before the window, for its first day
+        }
+        println!(
+            "{}: {} sedentary, {} fidget, {} active",
+            device, counts.sedentary, counts.fidget, counts.active
+        );
+        let Some(suggestion) = suggest(&data.values, args.method) else {
+            println!("  No suggestion ({} records)", data.values.len());
+            continue;
+        };
+        let current = current_thresholds(&mut *tx, device).await?;
+        println!(
+            "  Clusters {:?}: fidget {:.4} (now {:.4}), active {:.4} (now {:.4})",
+            suggestion.centers,
+            suggestion.thresholds.fidget,
+            current.fidget,
+            suggestion.thresholds.active,
+            current.active
+        );
+        if !args.dry_run {
+            let id = store_suggestion(
+                &mut tx,
+                device,
+                data,
+                &suggestion,
+                &current,
+                (window_start, window_end),
+            )
+            .await?;
+            println!(
+                "  Stored as suggestion {} (POST /api/suggestions/{}/apply)",
+                id, id
+            );
+        }
+    }
+
+    for (device, data) in devices {
+        for (day, time) in daily_time(data.records) {
+            let summary = DailySummary::from_time(&time);
+            println!(
+                "{} {}: score {}/100 ({} min sedentary, {} min active)",
+                device,
+                day,
+                summary.activity_score,
+                summary.sedentary_minutes,
+                summary.active_minutes
+            );
+            if !args.dry_run {
+                store_summary(&mut tx, &device, day, &summary).await?;
+            }
+        }
+    }
+    tx.commit().await?;
+    println!(
+        "{}",
+        if args.dry_run {
+            "Dry run, nothing saved."
+        } else {
+            "Analysis saved!"
+        }
+    );
+    Ok(())
+}
+
+async fn store_suggestion(
+    tx: &mut Transaction<'_, Postgres>,
+    device: &str,
+    data: &DeviceData,
+    suggestion: &Suggestion,
+    current: &Thresholds,
+    (window_start, window_end): (DateTime<Utc>, DateTime<Utc>),
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO threshold_suggestions
+            (device_id, method, window_start, window_end, samples,
+             sedentary_count, fidget_count, active_count, centers,
+             current_fidget, current_active, suggested_fidget, suggested_active)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        RETURNING id
+        "#,
+        device,
+        suggestion.method.as_str(),
+        window_start,
+        window_end,
+        data.values.len() as i32,
+        data.counts.sedentary as i32,
+        data.counts.fidget as i32,
+        data.counts.active as i32,
+        &suggestion.centers,
+        current.fidget,
+        current.active,
+        suggestion.thresholds.fidget,
+        suggestion.thresholds.active
+    )
+    .fetch_one(&mut **tx)
+    .await
+}
+
+async fn store_summary(
+    tx: &mut Transaction<'_, Postgres>,
+    device: &str,
+    date: NaiveDate,
+    summary: &DailySummary,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO activity_summary
+            (date, device_id, sedentary_minutes, active_minutes, dominant_state, activity_score)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (date, device_id) DO UPDATE SET
+            sedentary_minutes = EXCLUDED.sedentary_minutes,
+            active_minutes = EXCLUDED.active_minutes,
+            dominant_state = EXCLUDED.dominant_state,
+            activity_score = EXCLUDED.activity_score
+        "#,
+        date,
+        device,
+        summary.sedentary_minutes,
+        summary.active_minutes,
+        summary.dominant_state.as_str(),
+        summary.activity_score
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use super::*;
+
+// Smoothed acceleration of a still, a fidgeting and a walking stretch
+fn three_bands() -> Vec<f32> {
+    let mut values = Vec::new();
+    values.extend((0..600).map(|i| 0.003 + (i % 4) as f32 * 0.001));
+    values.extend((0..200).map(|i| 0.026 + (i % 5) as f32 * 0.002));
+    values.extend((0..100).map(|i| 0.10 + (i % 3) as f32 * 0.02));
+    values
+}
+
+// Method Tests
+
+#[test]
+fn test_method_from_str() {
+    assert_eq!("kmeans".parse::<Method>(), Ok(Method::KMeans));
+    assert_eq!(" Jenks ".parse::<Method>(), Ok(Method::Jenks));
+    assert!("dbscan".parse::<Method>().is_err());
+    assert_eq!(Method::default().to_string(), "kmeans");
+}
+
+// Suggestion Tests
+
+#[test]
+fn test_suggest_splits_between_bands() {
+    for method in [Method::KMeans, Method::Jenks] {
+        let suggestion = suggest(&three_bands(), method).unwrap();
+        let Thresholds { fidget, active } = suggestion.thresholds;
+        assert!(
+            fidget > 0.006 && fidget < 0.026,
+            "{}: fidget {}",
+            method,
+            fidget
+        );
+        assert!(
+            active > 0.034 && active < 0.10,
+            "{}: active {}",
+            method,
+            active
+        );
+        assert_eq!(suggestion.centers.len(), 3);
+        assert_eq!(suggestion.method, method);
+    }
+}
+
+#[test]
+fn test_kmeans_breaks_halfway_between_centers() {
+    // Same rule as the old Python job: (c0 + c1) / 2
+    let suggestion = suggest(&three_bands(), Method::KMeans).unwrap();
+    let c = &suggestion.centers;
+    assert!((suggestion.thresholds.fidget - (c[0] + c[1]) / 2.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_suggest_needs_enough_records() {
+    let values = vec![0.01; MIN_SAMPLES - 1];
+    assert_eq!(suggest(&values, Method::KMeans), None);
+}
+
+#[test]
+fn test_suggest_skips_data_without_three_groups() {
+    // A desk nobody sat at: nothing to learn from
+    let values = vec![0.0; 1000];
+    assert_eq!(suggest(&values, Method::KMeans), None);
+    assert_eq!(suggest(&values, Method::Jenks), None);
+}
+
+// Summary Tests
+
+#[test]
+fn test_state_counts() {
+    let mut counts = StateCounts::default();
+    counts.add(ActivityState::Sedentary);
+    counts.add(ActivityState::Fidget);
+    counts.add(ActivityState::Fidget);
+    assert_eq!(counts.fidget, 2);
+    assert_eq!(counts.total(), 3);
+}
+
+// Records every `step_ms` from `start`, in the given state
+fn records(
+    start: DateTime<Utc>,
+    step_ms: i64,
+    count: i64,
+    state: ActivityState,
+) -> Vec<(DateTime<Utc>, ActivityState)> {
+    (0..count)
+        .map(|i| (start + TimeDelta::milliseconds(i * step_ms), state))
+        .collect()
+}
+
+#[test]
+fn test_daily_time_follows_the_sample_rate() {
+    let start: DateTime<Utc> = "2026-10-17T10:00:00Z".parse().unwrap();
+    // Two minutes at 5Hz, then one at 10Hz
+    let mut stream = records(start, 200, 600, ActivityState::Sedentary);
+    stream.extend(records(
+        start + TimeDelta::minutes(2),
+        100,
+        601,
+        ActivityState::Fidget,
+    ));
+
+    let days = daily_time(stream);
+    let day = days[&start.date_naive()];
+    assert_eq!(day.sedentary, TimeDelta::minutes(2));
+    assert_eq!(day.fidget, TimeDelta::minutes(1));
+    assert_eq!(day.active, TimeDelta::zero());
+}
+
+#[test]
+fn test_daily_time_skips_gaps_and_splits_days() {
+    let start: DateTime<Utc> = "2026-10-17T23:59:00Z".parse().unwrap();
+    let mut stream = records(start, 1000, 61, ActivityState::Active);
+    // Device off for an hour: unknown time, not activity
+    stream.extend(records(
+        start + TimeDelta::hours(1),
+        1000,
+        31,
+        ActivityState::Sedentary,
+    ));
+
+    let days = daily_time(stream);
+    assert_eq!(days.len(), 2);
+    assert_eq!(days[&start.date_naive()].active, TimeDelta::minutes(1));
+    let next = days[&(start + TimeDelta::hours(1)).date_naive()];
+    assert_eq!(next.active, TimeDelta::zero());
+    assert_eq!(next.sedentary, TimeDelta::seconds(30));
+}
+
+#[test]
+fn test_daily_summary_from_time() {
+    let time = StateTime {
+        sedentary: TimeDelta::minutes(3),
+        fidget: TimeDelta::seconds(30),
+        active: TimeDelta::seconds(30),
+    };
+
+    let summary = DailySummary::from_time(&time);
+    assert_eq!(summary.sedentary_minutes, 3.0);
+    assert_eq!(summary.active_minutes, 1.0);
+    assert_eq!(summary.activity_score, 25);
+    assert_eq!(summary.dominant_state, ActivityState::Sedentary);
+}
+
+#[test]
+fn test_daily_summary_without_records() {
+    let summary = DailySummary::from_time(&StateTime::default());
+    assert_eq!(summary.activity_score, 0);
+    assert_eq!(summary.dominant_state, ActivityState::Sedentary);
+}
+
+// Smoothing Tests
+
+fn sample(id: i32, second: i64, acc: f32, state: ActivityState) -> Sample {
+    Sample {
+        id,
+        at: "2026-10-17T09:00:00Z".parse::<DateTime<Utc>>().unwrap() + TimeDelta::seconds(second),
+        acc,
+        state,
+    }
+}
+
+#[test]
+fn test_segments_split_at_gaps() {
+    let samples = vec![
+        sample(1, 0, 0.01, ActivityState::Sedentary),
+        sample(2, 1, 0.01, ActivityState::Sedentary),
+        sample(3, 1 + MAX_GAP_SEC, 0.01, ActivityState::Sedentary),
+        sample(4, 2 + 2 * MAX_GAP_SEC, 0.01, ActivityState::Sedentary),
+        sample(5, 3 + 2 * MAX_GAP_SEC, 0.01, ActivityState::Sedentary),
+    ];
+
+    let runs = segments(&samples, TimeDelta::seconds(MAX_GAP_SEC));
+    let ids: Vec<Vec<i32>> = runs
+        .iter()
+        .map(|run| run.iter().map(|s| s.id).collect())
+        .collect();
+    // A gap of exactly MAX_GAP_SEC still counts as continuous
+    assert_eq!(ids, vec![vec![1, 2, 3], vec![4, 5]]);
+    assert!(segments(&[], TimeDelta::seconds(MAX_GAP_SEC)).is_empty());
+}
+
+#[test]
+fn test_smooth_relabels_flicker() {
+    // Sitting with a smoothed value hovering at the fidget threshold now and then
+    let samples: Vec<Sample> = (0..600)
+        .map(|i| {
+            let (acc, state) = if i % 50 == 25 {
+                (0.021, ActivityState::Fidget)
+            } else {
+                (0.012 + (i % 5) as f32 * 0.0015, ActivityState::Sedentary)
+            };
+            sample(i, i as i64 / 10, acc, state)
+        })
+        .collect();
+
+    let smoothing = smooth(&samples, &Thresholds::default(), false).unwrap();
+    assert_eq!(smoothing.states.len(), 600);
+    assert_eq!(smoothing.segments, 1);
+    assert_eq!(smoothing.relabelled, 12);
+    assert!(
+        smoothing
+            .states
+            .iter()
+            .all(|s| *s == HiddenState::Sedentary)
+    );
+}
+
+#[test]
+fn test_smooth_needs_enough_records() {
+    let samples: Vec<Sample> = (0..MIN_SAMPLES as i32 - 1)
+        .map(|i| sample(i, i as i64, 0.01, ActivityState::Sedentary))
+        .collect();
+    assert!(smooth(&samples, &Thresholds::default(), false).is_none());
+}
@@ -0,0 +1,105 @@
+// Integration tests for the analysis crate
+// Simulated days go through the server's smoothing and the nightly suggestion / HMM smoothing
+
+use analysis::{Method, Sample, smooth, suggest};
+use chrono::{DateTime, TimeDelta, Utc};
+use logic::{ActivityState, HiddenState, Thresholds, log_acc};
+use simulator::{SimConfig, Simulator, parse_scenario};
+
+// Smoothed acceleration the server would store (mean of the last 10 samples)
+fn smoothed(script: &str) -> Vec<f32> {
+    let phases = parse_scenario(script).unwrap();
+    let mut window: Vec<f32> = Vec::new();
+    Simulator::new(phases, SimConfig::default())
+        .map(|reading| {
+            window.push(reading.acc);
+            if window.len() > 10 {
+                window.remove(0);
+            }
+            window.iter().sum::<f32>() / window.len() as f32
+        })
+        .collect()
+}
+
+#[test]
+fn test_suggestions_separate_simulated_behaviours() {
+    let values = smoothed("20 min sedentary then 10 min fidget then 5 min walk");
+
+    for method in [Method::KMeans, Method::Jenks] {
+        let suggestion = suggest(&values, method).unwrap();
+        let thresholds = suggestion.thresholds;
+        // Fidgeting in the simulator sits between 0.022 and 0.036, walking far above
+        assert!(
+            thresholds.fidget < 0.022,
+            "{}: fidget {}",
+            method,
+            thresholds.fidget
+        );
+        assert!(
+            thresholds.active > 0.036 && thresholds.active < 0.12,
+            "{}: active {}",
+            method,
+            thresholds.active
+        );
+    }
+}
+
+#[test]
+fn test_no_suggestion_for_a_short_day() {
+    let values = smoothed("5s sit");
+    assert_eq!(suggest(&values, Method::KMeans), None);
+}
+
+// Real-time labels of the default threshold classifier on the smoothed values
+fn labelled(values: &[f32]) -> Vec<Sample> {
+    let start: DateTime<Utc> = "2026-10-17T09:00:00Z".parse().unwrap();
+    let thresholds = Thresholds::default();
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, acc)| Sample {
+            id: i as i32,
+            at: start + TimeDelta::milliseconds(i as i64 * 100),
+            acc: *acc,
+            state: if *acc > thresholds.active {
+                ActivityState::Active
+            } else if *acc > thresholds.fidget {
+                ActivityState::Fidget
+            } else {
+                ActivityState::Sedentary
+            },
+        })
+        .collect()
+}
+
+fn changes<T: PartialEq>(states: &[T]) -> usize {
+    states.windows(2).filter(|w| w[0] != w[1]).count()
+}
+
+#[test]
+fn test_smoothing_cleans_simulated_timeline() {
+    let samples = labelled(&smoothed(
+        "10 min sedentary then 5 min fidget then 5 min walk then 10 min sedentary",
+    ));
+    let smoothing = smooth(&samples, &Thresholds::default(), false).unwrap();
+
+    let raw: Vec<ActivityState> = samples.iter().map(|s| s.state).collect();
+    // No more state changes than the real-time labels, and still every behaviour
+    assert!(
+        changes(&smoothing.states) <= changes(&raw),
+        "{} cleaned vs {} raw changes",
+        changes(&smoothing.states),
+        changes(&raw)
+    );
+    assert!(changes(&smoothing.states) >= 3);
+    // The fidget level is learned from the data (the simulator fidgets between 0.022 and 0.036)
+    let fidget = smoothing.model.means[1];
+    assert!(
+        fidget > log_acc(0.022) && fidget < log_acc(0.036),
+        "fidget mean {}",
+        fidget
+    );
+    assert_eq!(smoothing.states[7500], HiddenState::Fidget);
+    assert_eq!(smoothing.states[10500], HiddenState::Active);
+    assert_eq!(*smoothing.states.last().unwrap(), HiddenState::Sedentary);
+}
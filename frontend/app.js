@@ -1,9 +1,9 @@
 
 const CONFIG = {
     maxDataPoints: 100,
-    thresholdFidget: 0.020,   // Matches Arduino THRESHOLD_FIDGET
-    thresholdActive: 0.040,   // Matches Arduino THRESHOLD_ACTIVE  
-    alertTimeSeconds: 1200,   // 20 minutes - matches Arduino ALERT_TIME_SEC
+    thresholdFidget: 0.020,   // Until /api/classifier answers
+    thresholdActive: 0.040,   // Until /api/classifier answers
+    alertTimeSeconds: 1200,   // 20 minutes unless the device has its own limit
 };
 
 
@@ -14,6 +14,9 @@ const state = {
     activeReadings: 0,
     longestInactive: 0,
     sedentaryTimer: 0,       
+    unknownSeconds: 0,       // Data gaps in the current timer span
+    faults: {},              // Active sensor faults: fault -> detail
+    features: null,          // Hjorth features (hjorth classifier only)
     currentState: 'SEDENTARY', // "ACTIVE", "FIDGET", or "SEDENTARY"
     alertCount: 0,
     alerts: [],
@@ -22,13 +25,19 @@ const state = {
 
 const elements = {
     connectionText: document.getElementById('connectionText'),
-    statusDot: document.querySelector('.status-dot'),
+    statusDot: document.querySelector('#connectionStatus .status-dot'),
+    deviceText: document.getElementById('deviceText'),
+    deviceDot: document.getElementById('deviceDot'),
     statusIndicator: document.getElementById('statusIndicator'),
     statusIcon: document.getElementById('statusIcon'),
     activityStateText: document.getElementById('activityStateText'),
     timerLabel: document.getElementById('timerLabel'),
     timerValue: document.getElementById('timerValue'),
     confidenceValue: document.getElementById('confidenceValue'),
+    featuresDisplay: document.getElementById('featuresDisplay'),
+    featuresValue: document.getElementById('featuresValue'),
+    calibrationDisplay: document.getElementById('calibrationDisplay'),
+    calibrationValue: document.getElementById('calibrationValue'),
     totalReadings: document.getElementById('totalReadings'),
     activePercentage: document.getElementById('activePercentage'),
     longestInactive: document.getElementById('longestInactive'),
@@ -145,9 +154,13 @@ const pie = d3.pie()
     .value(d => d.value)
     .sort(null);
 
+// Dashboard for one device with /?device=desk-3 (every device when unset)
+const deviceFilter = new URLSearchParams(window.location.search).get('device');
+
 // WebSocket Connection
 function connectWebSocket() {
-    const ws = new WebSocket(`ws://${window.location.host}/ws`);
+    const query = deviceFilter ? `?device=${encodeURIComponent(deviceFilter)}` : '';
+    const ws = new WebSocket(`ws://${window.location.host}/ws${query}`);
 
     ws.onopen = () => {
         elements.connectionText.textContent = 'Connected';
@@ -198,10 +211,34 @@ function processData(rawData) {
         console.log('Arduino error:', data.error);
         return;
     }
+
+    // Serial link status from the server (not a sensor reading)
+    if (data.event === 'device_status') {
+        updateDeviceStatus(data);
+        return;
+    }
+
+    // Sensor fault raised or cleared
+    if (data.event === 'device_health') {
+        updateDeviceHealth(data);
+        return;
+    }
+
+    // Calibration session step or result
+    if (data.event === 'calibration') {
+        updateCalibration(data);
+        return;
+    }
+
+    // Other server events (link stats, handshakes, data quality) aren't readings
+    if (data.event) {
+        return;
+    }
     
     
     const activityState = data.state || 'SEDENTARY';  // "ACTIVE", "FIDGET", or "SEDENTARY"
     const timerSeconds = data.timer || 0;             // Sedentary timer from Rust
+    const unknownSeconds = data.unknown_sec || 0;     // Data gaps inside the timer span
     const accelValue = data.val || 0;                 // Smoothed acceleration delta
     const alertTriggered = data.alert || false;       // Alert flag from backend
     
@@ -209,7 +246,10 @@ function processData(rawData) {
 
     state.totalReadings++;
     state.currentState = activityState;
+    const previousTimer = state.sedentaryTimer;
     state.sedentaryTimer = timerSeconds;
+    state.unknownSeconds = unknownSeconds;
+    state.features = data.features || null;
     
     // Update data for acceleration chart
     state.accelData.push(accelValue);
@@ -227,8 +267,9 @@ function processData(rawData) {
         state.longestInactive = timerSeconds;
     }
     
-    // Trigger alert when backend signals it once per threshold crossing
-    if (alertTriggered && timerSeconds % 60 === 0) {
+    // Trigger alert when backend signals it, once per minute crossed
+    // (the timer follows elapsed time, so it can skip seconds)
+    if (alertTriggered && Math.floor(timerSeconds / 60) !== Math.floor(previousTimer / 60)) {
         triggerAlert(timerSeconds);
     }
 
@@ -250,6 +291,57 @@ function processData(rawData) {
 }
 
 
+// Tells the user what to do during a calibration, then shows the outcome
+function updateCalibration(event) {
+    elements.calibrationDisplay.hidden = false;
+    switch (event.status) {
+        case 'running': {
+            const until = new Date(event.phase_ends_at).toLocaleTimeString();
+            elements.calibrationValue.textContent = `${event.instruction} until ${until}`;
+            break;
+        }
+        case 'completed':
+            elements.calibrationValue.textContent = event.detail
+                ? `done (${event.detail})`
+                : 'done, personal thresholds applied';
+            // New thresholds and alert limit for the chart
+            loadThresholds();
+            break;
+        default:
+            elements.calibrationValue.textContent = event.detail
+                ? `${event.status}: ${event.detail}`
+                : event.status;
+    }
+}
+
+function updateDeviceStatus(status) {
+    elements.deviceDot.classList.toggle('connected', status.connected);
+    elements.deviceDot.classList.toggle('disconnected', !status.connected);
+
+    if (status.connected) {
+        elements.deviceText.textContent = `Device: ${status.port}`;
+        elements.deviceText.title = '';
+    } else {
+        const retry = status.retry_in_sec != null ? ` (retry in ${status.retry_in_sec}s)` : '';
+        elements.deviceText.textContent = `Device disconnected${retry}`;
+        elements.deviceText.title = status.detail || '';
+    }
+}
+
+
+function updateDeviceHealth(alert) {
+    if (alert.active) {
+        state.faults[alert.fault] = alert.detail;
+    } else {
+        delete state.faults[alert.fault];
+    }
+
+    const faults = Object.keys(state.faults);
+    elements.deviceDot.classList.toggle('warning', faults.length > 0);
+    elements.deviceText.title = faults.map(f => `${f}: ${state.faults[f]}`).join('\n');
+}
+
+
 function updateUI() {
     // Activity status - 3-state model
     const indicator = elements.statusIndicator;
@@ -280,6 +372,9 @@ function updateUI() {
     const minutes = Math.floor(duration / 60);
     const seconds = duration % 60;
     elements.timerValue.textContent = `${minutes.toString().padStart(2, '0')}:${seconds.toString().padStart(2, '0')}`;
+    if (state.unknownSeconds > 0) {
+        elements.timerValue.textContent += ` (+${Math.ceil(state.unknownSeconds / 60)}m no data)`;
+    }
 
     // Confidence (based on data stability)
     const recentData = state.accelData.slice(-10);
@@ -288,6 +383,15 @@ function updateUI() {
     const confidence = Math.max(0, Math.min(100, 100 - variance * 50));
     elements.confidenceValue.textContent = confidence.toFixed(0);
 
+    // Features behind the verdict, when the server classifies on them
+    elements.featuresDisplay.hidden = !state.features;
+    if (state.features) {
+        const f = state.features;
+        elements.featuresValue.textContent =
+            `activity ${f.hjorth_activity.toExponential(1)}, mobility ${f.hjorth_mobility.toFixed(2)}, ` +
+            `complexity ${f.hjorth_complexity.toFixed(2)}${f.stationarity_passed ? '' : ' (changing)'}`;
+    }
+
     // Stats
     elements.totalReadings.textContent = state.totalReadings;
     const activePercent = state.totalReadings > 0 
@@ -402,7 +506,30 @@ function updateAlertList() {
 }
 
 
+// Threshold lines follow the server's classifier, or the device's own settings
+async function loadThresholds() {
+    try {
+        const url = deviceFilter
+            ? `/api/devices/${encodeURIComponent(deviceFilter)}/settings`
+            : '/api/classifier';
+        const response = await fetch(url);
+        const { thresholds, alert_limit_sec } = await response.json();
+        if (alert_limit_sec) CONFIG.alertTimeSeconds = alert_limit_sec;
+        CONFIG.thresholdFidget = thresholds.fidget;
+        CONFIG.thresholdActive = thresholds.active;
+        accelSvg.select('.fidget-line')
+            .attr('y1', accelY(thresholds.fidget))
+            .attr('y2', accelY(thresholds.fidget));
+        accelSvg.select('.active-line')
+            .attr('y1', accelY(thresholds.active))
+            .attr('y2', accelY(thresholds.active));
+    } catch (e) {
+        console.error('Classifier settings unavailable:', e);
+    }
+}
+
 function init() {
+    loadThresholds();
     connectWebSocket();
     // Timer is now driven by Arduino's inactiveSeconds - no local interval needed
 }
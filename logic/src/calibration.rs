@@ -0,0 +1,251 @@
+use crate::classifier::Thresholds;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// CALIBRATION LIMITS
+pub const MIN_PHASE_SAMPLES: usize = 50; // Fewer samples in any phase fail the session
+pub const SMOOTHING_CANDIDATES: &[usize] = &[3, 5, 10, 15, 20, 30]; // Windows tried, smallest first
+const SEPARATION_QUANTILE: f32 = 0.9; // Share of each phase that has to fall on its side of a threshold
+const PIR_NOISE_RATIO: f32 = 0.1; // PIR high for more of the still phase than this is worth a warning
+const PIR_MISS_RATIO: f32 = 0.5; // PIR high for less of the walk phase than this is worth a warning
+
+// The guided steps, in the order the user goes through them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CalibrationPhase {
+    Still,
+    Fidget,
+    Walk,
+}
+
+impl CalibrationPhase {
+    pub const ALL: [CalibrationPhase; 3] = [
+        CalibrationPhase::Still,
+        CalibrationPhase::Fidget,
+        CalibrationPhase::Walk,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CalibrationPhase::Still => "still",
+            CalibrationPhase::Fidget => "fidget",
+            CalibrationPhase::Walk => "walk",
+        }
+    }
+
+    // What the user is asked to do
+    pub fn instruction(&self) -> &'static str {
+        match self {
+            CalibrationPhase::Still => "Sit still",
+            CalibrationPhase::Fidget => "Stay seated and fidget as you normally would",
+            CalibrationPhase::Walk => "Get up and walk around",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl fmt::Display for CalibrationPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CalibrationPhase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "still" => Ok(CalibrationPhase::Still),
+            "fidget" => Ok(CalibrationPhase::Fidget),
+            "walk" => Ok(CalibrationPhase::Walk),
+            other => Err(format!("unknown calibration phase '{}'", other)),
+        }
+    }
+}
+
+// Acceleration and PIR statistics of one phase (raw deltas, before smoothing)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PhaseStats {
+    pub phase: CalibrationPhase,
+    pub samples: usize,
+    pub acc_mean: f32,
+    pub acc_std: f32,
+    pub acc_p05: f32,
+    pub acc_p95: f32,
+    pub pir_ratio: f32, // Share of samples with the PIR high
+}
+
+// Personal classification settings derived from a session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationProfile {
+    pub thresholds: Thresholds,
+    pub smoothing_window: usize, // Samples in the smoothing buffer
+    pub phases: Vec<PhaseStats>,
+    pub warnings: Vec<String>, // Things worth telling the user, the profile is still usable
+}
+
+// Readings of one phase
+#[derive(Debug, Clone, Default)]
+struct PhaseRecording {
+    acc: Vec<f32>,
+    pir_high: usize,
+}
+
+// Collects the readings of a session, phase by phase
+#[derive(Debug, Clone, Default)]
+pub struct CalibrationRecorder {
+    phases: [PhaseRecording; 3],
+}
+
+// Value below which the given share of sorted values falls
+fn quantile(sorted: &[f32], q: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let index = ((sorted.len() - 1) as f32 * q).round() as usize;
+    sorted[index]
+}
+
+// Trailing mean as the pipeline computes it, once the window is full
+fn smoothed(acc: &[f32], window: usize) -> Vec<f32> {
+    let mut values: Vec<f32> = acc
+        .windows(window)
+        .map(|w| w.iter().sum::<f32>() / window as f32)
+        .collect();
+    values.sort_by(f32::total_cmp);
+    values
+}
+
+// Thresholds for one smoothing window and the worst gap between neighbouring phases
+fn separate(recorder: &CalibrationRecorder, window: usize) -> Option<(Thresholds, f32)> {
+    let [still, fidget, walk] = &recorder.phases;
+    let (still, fidget, walk) = (
+        smoothed(&still.acc, window),
+        smoothed(&fidget.acc, window),
+        smoothed(&walk.acc, window),
+    );
+    if [&still, &fidget, &walk].iter().any(|s| s.is_empty()) {
+        return None;
+    }
+
+    let still_high = quantile(&still, SEPARATION_QUANTILE);
+    let fidget_low = quantile(&fidget, 1.0 - SEPARATION_QUANTILE);
+    let fidget_high = quantile(&fidget, SEPARATION_QUANTILE);
+    let walk_low = quantile(&walk, 1.0 - SEPARATION_QUANTILE);
+
+    let thresholds = Thresholds {
+        fidget: (still_high + fidget_low) / 2.0,
+        active: (fidget_high + walk_low) / 2.0,
+    };
+    let margin = (fidget_low - still_high).min(walk_low - fidget_high);
+    Some((thresholds, margin))
+}
+
+impl CalibrationRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // One reading during the given phase; out-of-range readings have no delta
+    // and are left out, PIR included, so pir_ratio shares the samples' denominator
+    pub fn record(&mut self, phase: CalibrationPhase, acc: Option<f32>, pir: bool) {
+        let recording = &mut self.phases[phase.index()];
+        if let Some(acc) = acc.filter(|acc| acc.is_finite()) {
+            recording.acc.push(acc);
+            if pir {
+                recording.pir_high += 1;
+            }
+        }
+    }
+
+    pub fn samples(&self, phase: CalibrationPhase) -> usize {
+        self.phases[phase.index()].acc.len()
+    }
+
+    pub fn stats(&self, phase: CalibrationPhase) -> PhaseStats {
+        let recording = &self.phases[phase.index()];
+        let mut sorted = recording.acc.clone();
+        sorted.sort_by(f32::total_cmp);
+        let n = sorted.len().max(1) as f32;
+        let mean = sorted.iter().sum::<f32>() / n;
+        let variance = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n;
+        PhaseStats {
+            phase,
+            samples: sorted.len(),
+            acc_mean: mean,
+            acc_std: variance.sqrt(),
+            acc_p05: quantile(&sorted, 0.05),
+            acc_p95: quantile(&sorted, 0.95),
+            pir_ratio: recording.pir_high as f32 / n,
+        }
+    }
+
+    // Thresholds halfway between neighbouring phases at the smallest smoothing
+    // window that keeps the phases apart. Err says why the session can't be used.
+    pub fn profile(&self) -> Result<CalibrationProfile, String> {
+        for phase in CalibrationPhase::ALL {
+            let samples = self.samples(phase);
+            if samples < MIN_PHASE_SAMPLES {
+                return Err(format!(
+                    "only {} samples in the {} phase (need {})",
+                    samples, phase, MIN_PHASE_SAMPLES
+                ));
+            }
+        }
+
+        let mut fits = false; // Some window had enough samples
+        let mut found = None;
+        for &window in SMOOTHING_CANDIDATES {
+            let Some((thresholds, margin)) = separate(self, window) else {
+                continue;
+            };
+            fits = true;
+            if margin > 0.0 && thresholds.fidget > 0.0 {
+                found = Some((window, thresholds));
+                break;
+            }
+        }
+        let Some((smoothing_window, thresholds)) = found else {
+            return Err(if fits {
+                "still, fidget and walk phases overlap; was each step followed?".to_string()
+            } else {
+                "phases too short for any smoothing window".to_string()
+            });
+        };
+
+        let phases: Vec<PhaseStats> = CalibrationPhase::ALL
+            .iter()
+            .map(|phase| self.stats(*phase))
+            .collect();
+        let mut warnings = Vec::new();
+        let still_pir = phases[CalibrationPhase::Still.index()].pir_ratio;
+        if still_pir > PIR_NOISE_RATIO {
+            warnings.push(format!(
+                "PIR was high for {:.0}% of the still phase and will keep the state ACTIVE (CLASSIFIER=acceleration ignores it)",
+                still_pir * 100.0
+            ));
+        }
+        let walk_pir = phases[CalibrationPhase::Walk.index()].pir_ratio;
+        if walk_pir < PIR_MISS_RATIO {
+            warnings.push(format!(
+                "PIR was high for only {:.0}% of the walk phase, walking is detected by acceleration alone",
+                walk_pir * 100.0
+            ));
+        }
+
+        Ok(CalibrationProfile {
+            thresholds,
+            smoothing_window,
+            phases,
+            warnings,
+        })
+    }
+}
+
+#[cfg(test)]
+#[path = "calibration_tests.rs"]
+mod tests;
@@ -0,0 +1,133 @@
+use super::*;
+
+const STILL: CalibrationPhase = CalibrationPhase::Still;
+const FIDGET: CalibrationPhase = CalibrationPhase::Fidget;
+const WALK: CalibrationPhase = CalibrationPhase::Walk;
+
+// Records 100 deltas in a phase, cycling through the given pattern
+fn record(recorder: &mut CalibrationRecorder, phase: CalibrationPhase, pattern: &[f32], pir: bool) {
+    for i in 0..100 {
+        recorder.record(phase, Some(pattern[i % pattern.len()]), pir);
+    }
+}
+
+// A board with a higher noise floor than the defaults assume
+fn noisy_board() -> CalibrationRecorder {
+    let mut recorder = CalibrationRecorder::new();
+    record(&mut recorder, STILL, &[0.020, 0.024, 0.022, 0.026], false);
+    record(&mut recorder, FIDGET, &[0.045, 0.055, 0.050, 0.060], false);
+    record(&mut recorder, WALK, &[0.15, 0.20, 0.18, 0.22], true);
+    recorder
+}
+
+//Phase Stats Tests
+
+#[test]
+fn test_stats_describe_raw_deltas() {
+    let mut recorder = CalibrationRecorder::new();
+    record(&mut recorder, STILL, &[0.01, 0.03], false);
+    // Out of range, left out of the PIR share like the deltas
+    recorder.record(STILL, None, true);
+
+    let stats = recorder.stats(STILL);
+    assert_eq!(stats.samples, 100);
+    assert!((stats.acc_mean - 0.02).abs() < 1e-6);
+    assert!((stats.acc_std - 0.01).abs() < 1e-6);
+    assert_eq!(stats.acc_p05, 0.01);
+    assert_eq!(stats.acc_p95, 0.03);
+    assert_eq!(stats.pir_ratio, 0.0);
+
+    record(&mut recorder, STILL, &[0.02], true);
+    let stats = recorder.stats(STILL);
+    assert_eq!(stats.samples, 200);
+    assert!((stats.pir_ratio - 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn test_stats_of_empty_phase() {
+    let stats = CalibrationRecorder::new().stats(WALK);
+    assert_eq!(stats.samples, 0);
+    assert_eq!(stats.acc_mean, 0.0);
+    assert_eq!(stats.pir_ratio, 0.0);
+}
+
+#[test]
+fn test_phase_names_round_trip() {
+    for phase in CalibrationPhase::ALL {
+        assert_eq!(phase.as_str().parse::<CalibrationPhase>(), Ok(phase));
+    }
+    assert!("sleep".parse::<CalibrationPhase>().is_err());
+}
+
+//Profile Tests
+
+#[test]
+fn test_profile_separates_phases() {
+    let profile = noisy_board().profile().unwrap();
+
+    // Above the still phase and below the fidget phase, likewise for walking
+    assert!(profile.thresholds.fidget > 0.026 && profile.thresholds.fidget < 0.045);
+    assert!(profile.thresholds.active > 0.060 && profile.thresholds.active < 0.15);
+    // Well separated phases need no more than the smallest window
+    assert_eq!(profile.smoothing_window, SMOOTHING_CANDIDATES[0]);
+    assert_eq!(profile.phases.len(), 3);
+    assert!(profile.warnings.is_empty());
+}
+
+#[test]
+fn test_profile_widens_smoothing_for_bursty_fidgeting() {
+    // Fidgeting comes in bursts with still stretches in between
+    let mut recorder = CalibrationRecorder::new();
+    record(&mut recorder, STILL, &[0.004, 0.006], false);
+    record(
+        &mut recorder,
+        FIDGET,
+        &[0.002, 0.004, 0.003, 0.002, 0.004, 0.003, 0.06, 0.05],
+        false,
+    );
+    record(&mut recorder, WALK, &[0.12, 0.15], true);
+
+    let profile = recorder.profile().unwrap();
+    // Only a window longer than the stretches keeps the fidget phase above the still one
+    assert_eq!(profile.smoothing_window, 10);
+    assert!(profile.thresholds.fidget > 0.006);
+    assert!(profile.thresholds.active > profile.thresholds.fidget);
+}
+
+#[test]
+fn test_profile_rejects_short_phase() {
+    let mut short = CalibrationRecorder::new();
+    for phase in [STILL, FIDGET] {
+        for _ in 0..MIN_PHASE_SAMPLES {
+            short.record(phase, Some(0.01), false);
+        }
+    }
+    short.record(WALK, Some(0.2), true);
+
+    let error = short.profile().unwrap_err();
+    assert!(error.contains("walk"), "{}", error);
+}
+
+#[test]
+fn test_profile_rejects_overlapping_phases() {
+    // The user never got up
+    let mut recorder = CalibrationRecorder::new();
+    record(&mut recorder, STILL, &[0.01, 0.02], false);
+    record(&mut recorder, FIDGET, &[0.01, 0.02], false);
+    record(&mut recorder, WALK, &[0.01, 0.02], false);
+
+    assert!(recorder.profile().unwrap_err().contains("overlap"));
+}
+
+#[test]
+fn test_profile_warns_about_pir() {
+    let mut recorder = CalibrationRecorder::new();
+    record(&mut recorder, STILL, &[0.005], true);
+    record(&mut recorder, FIDGET, &[0.03], false);
+    record(&mut recorder, WALK, &[0.1], false);
+
+    let profile = recorder.profile().unwrap();
+    assert_eq!(profile.warnings.len(), 2);
+    assert!(profile.warnings[0].contains("still phase"));
+    assert!(profile.warnings[1].contains("walk phase"));
+}
@@ -0,0 +1,270 @@
+use crate::{SignalFeatures, SignalWindow, calculate_hjorth_params};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// CLASSIFICATION DEFAULTS
+pub const THRESH_FIDGET: f32 = 0.020; // Smoothed acceleration delta threshold for fidgeting
+pub const THRESH_ACTIVE: f32 = 0.040; // Smoothed acceleration delta threshold for active
+pub const SMOOTHING_WINDOW: usize = 10; // Samples in the smoothing buffer (1s at the default 10Hz)
+pub const HJORTH_WINDOW: usize = 50; // Samples the Hjorth features are computed over (5s at 10Hz)
+pub const HJORTH_HOP: usize = 10; // New samples between two feature computations
+
+// Activity state of one reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ActivityState {
+    Sedentary,
+    Fidget,
+    Active,
+}
+
+impl ActivityState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityState::Sedentary => "SEDENTARY",
+            ActivityState::Fidget => "FIDGET",
+            ActivityState::Active => "ACTIVE",
+        }
+    }
+}
+
+impl fmt::Display for ActivityState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ActivityState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SEDENTARY" => Ok(ActivityState::Sedentary),
+            "FIDGET" => Ok(ActivityState::Fidget),
+            "ACTIVE" => Ok(ActivityState::Active),
+            other => Err(format!("unknown activity state '{}'", other)),
+        }
+    }
+}
+
+// Smoothed acceleration limits between the states
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Thresholds {
+    pub fidget: f32, // Above this is FIDGET
+    pub active: f32, // Above this is ACTIVE
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Thresholds {
+            fidget: THRESH_FIDGET,
+            active: THRESH_ACTIVE,
+        }
+    }
+}
+
+// What a classifier sees of one reading
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Observation {
+    pub pir: bool,         // Motion sensor high (already false when the PIR is stuck)
+    pub smoothed_acc: f32, // Mean acceleration delta over the smoothing window
+    pub acc: Option<f32>,  // This sample's acceleration delta (None when out of range)
+}
+
+// A classifier's state for one reading and the movement level it compared
+// against the thresholds, which the state machine also leaves the state on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Verdict {
+    pub state: ActivityState,
+    pub level: f32,
+}
+
+impl Verdict {
+    fn by_level(level: f32, thresholds: &Thresholds) -> Self {
+        Verdict {
+            state: by_acceleration(level, thresholds),
+            level,
+        }
+    }
+}
+
+// Turns readings into activity states. Implementations may keep state
+// between readings, one instance per device.
+pub trait Classifier: fmt::Debug + Send {
+    // Name used to select it (CLASSIFIER)
+    fn name(&self) -> &'static str;
+
+    fn thresholds(&self) -> Thresholds;
+
+    // Takes effect from the next reading
+    fn set_thresholds(&mut self, thresholds: Thresholds);
+
+    fn classify(&mut self, observation: &Observation) -> Verdict;
+
+    // Signal features behind the latest verdict, for classifiers that compute them
+    fn features(&self) -> Option<SignalFeatures> {
+        None
+    }
+}
+
+// Default rule: PIR or strong movement is ACTIVE, light movement FIDGET
+#[derive(Debug, Clone, Default)]
+pub struct ThresholdClassifier {
+    pub thresholds: Thresholds,
+}
+
+impl Classifier for ThresholdClassifier {
+    fn name(&self) -> &'static str {
+        "threshold"
+    }
+
+    fn thresholds(&self) -> Thresholds {
+        self.thresholds
+    }
+
+    fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    fn classify(&mut self, observation: &Observation) -> Verdict {
+        let verdict = Verdict::by_level(observation.smoothed_acc, &self.thresholds);
+        if observation.pir {
+            Verdict {
+                state: ActivityState::Active,
+                ..verdict
+            }
+        } else {
+            verdict
+        }
+    }
+}
+
+// Same thresholds without the PIR, for boards without one or placed where
+// other people walk past
+#[derive(Debug, Clone, Default)]
+pub struct AccelerationClassifier {
+    pub thresholds: Thresholds,
+}
+
+impl Classifier for AccelerationClassifier {
+    fn name(&self) -> &'static str {
+        "acceleration"
+    }
+
+    fn thresholds(&self) -> Thresholds {
+        self.thresholds
+    }
+
+    fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    fn classify(&mut self, observation: &Observation) -> Verdict {
+        Verdict::by_level(observation.smoothed_acc, &self.thresholds)
+    }
+}
+
+// Classifies on Hjorth features of a sliding window of acceleration deltas,
+// recomputed every HJORTH_HOP samples. The movement level is the window's mean
+// plus its standard deviation (sqrt of the Hjorth activity), so short bursts
+// of fidgeting count even when they barely move the mean. A window that fails
+// the stationarity check spans a change, so the latest hop alone decides.
+#[derive(Debug, Clone, Default)]
+pub struct HjorthClassifier {
+    pub thresholds: Thresholds,
+    window: SignalWindow,
+    since_hop: usize,
+    features: Option<SignalFeatures>,
+    level: Option<f32>, // Movement level of the latest hop
+}
+
+impl HjorthClassifier {
+    pub fn new(thresholds: Thresholds) -> Self {
+        HjorthClassifier {
+            thresholds,
+            ..Default::default()
+        }
+    }
+
+    fn hop(&mut self) {
+        let data = &self.window.data_points;
+        let features = calculate_hjorth_params(data);
+        let recent = if features.stationarity_passed {
+            features
+        } else {
+            calculate_hjorth_params(&data[data.len().saturating_sub(HJORTH_HOP)..])
+        };
+        self.level = Some((recent.mean + recent.hjorth_activity.sqrt()) as f32);
+        self.features = Some(features);
+    }
+}
+
+impl Classifier for HjorthClassifier {
+    fn name(&self) -> &'static str {
+        "hjorth"
+    }
+
+    fn thresholds(&self) -> Thresholds {
+        self.thresholds
+    }
+
+    fn set_thresholds(&mut self, thresholds: Thresholds) {
+        self.thresholds = thresholds;
+    }
+
+    fn classify(&mut self, observation: &Observation) -> Verdict {
+        if let Some(acc) = observation.acc {
+            self.window.add(acc as f64);
+            self.window.keep_last(HJORTH_WINDOW);
+            self.since_hop += 1;
+            if self.since_hop >= HJORTH_HOP {
+                self.since_hop = 0;
+                self.hop();
+            }
+        }
+
+        // Smoothed acceleration until the first hop
+        let level = self.level.unwrap_or(observation.smoothed_acc);
+        let verdict = Verdict::by_level(level, &self.thresholds);
+        if observation.pir {
+            Verdict {
+                state: ActivityState::Active,
+                ..verdict
+            }
+        } else {
+            verdict
+        }
+    }
+
+    fn features(&self) -> Option<SignalFeatures> {
+        self.features
+    }
+}
+
+fn by_acceleration(smoothed_acc: f32, thresholds: &Thresholds) -> ActivityState {
+    if smoothed_acc > thresholds.active {
+        ActivityState::Active
+    } else if smoothed_acc > thresholds.fidget {
+        ActivityState::Fidget
+    } else {
+        ActivityState::Sedentary
+    }
+}
+
+// Names accepted by classifier_by_name, the default first
+pub const CLASSIFIERS: &[&str] = &["threshold", "acceleration", "hjorth"];
+
+// Builds a classifier by name with the given thresholds
+pub fn classifier_by_name(name: &str, thresholds: Thresholds) -> Option<Box<dyn Classifier>> {
+    match name {
+        "threshold" => Some(Box::new(ThresholdClassifier { thresholds })),
+        "acceleration" => Some(Box::new(AccelerationClassifier { thresholds })),
+        "hjorth" => Some(Box::new(HjorthClassifier::new(thresholds))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+#[path = "classifier_tests.rs"]
+mod tests;
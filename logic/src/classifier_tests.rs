@@ -0,0 +1,202 @@
+use super::*;
+
+fn observe(pir: bool, smoothed_acc: f32) -> Observation {
+    Observation {
+        pir,
+        smoothed_acc,
+        acc: Some(smoothed_acc),
+    }
+}
+
+// Feeds raw deltas (smoothed acceleration left at 0) and returns the last verdict
+fn feed(classifier: &mut HjorthClassifier, deltas: impl IntoIterator<Item = f32>) -> ActivityState {
+    let mut state = ActivityState::Sedentary;
+    for acc in deltas {
+        state = classifier
+            .classify(&Observation {
+                pir: false,
+                smoothed_acc: 0.0,
+                acc: Some(acc),
+            })
+            .state;
+    }
+    state
+}
+
+//ActivityState Tests
+
+#[test]
+fn test_activity_state_wire_format() {
+    assert_eq!(
+        serde_json::to_string(&ActivityState::Sedentary).unwrap(),
+        "\"SEDENTARY\""
+    );
+    assert_eq!(
+        serde_json::from_str::<ActivityState>("\"FIDGET\"").unwrap(),
+        ActivityState::Fidget
+    );
+    assert_eq!(ActivityState::Active.to_string(), "ACTIVE");
+}
+
+#[test]
+fn test_activity_state_from_str() {
+    for state in [
+        ActivityState::Sedentary,
+        ActivityState::Fidget,
+        ActivityState::Active,
+    ] {
+        assert_eq!(state.as_str().parse::<ActivityState>(), Ok(state));
+    }
+    assert!("active".parse::<ActivityState>().is_err());
+}
+
+//Classifier Tests
+
+#[test]
+fn test_threshold_classifier() {
+    let mut classifier = ThresholdClassifier::default();
+
+    assert_eq!(
+        classifier.classify(&observe(true, 0.0)).state,
+        ActivityState::Active
+    );
+    assert_eq!(
+        classifier.classify(&observe(false, 0.05)).state,
+        ActivityState::Active
+    );
+    assert_eq!(
+        classifier.classify(&observe(false, 0.03)).state,
+        ActivityState::Fidget
+    );
+    assert_eq!(
+        classifier.classify(&observe(false, 0.01)).state,
+        ActivityState::Sedentary
+    );
+    // Limits are exclusive
+    assert_eq!(
+        classifier.classify(&observe(false, THRESH_FIDGET)).state,
+        ActivityState::Sedentary
+    );
+}
+
+#[test]
+fn test_acceleration_classifier_ignores_pir() {
+    let mut classifier = AccelerationClassifier::default();
+
+    assert_eq!(
+        classifier.classify(&observe(true, 0.0)).state,
+        ActivityState::Sedentary
+    );
+    assert_eq!(
+        classifier.classify(&observe(true, 0.03)).state,
+        ActivityState::Fidget
+    );
+}
+
+#[test]
+fn test_custom_thresholds() {
+    let thresholds = Thresholds {
+        fidget: 0.1,
+        active: 0.2,
+    };
+    let mut classifier = ThresholdClassifier { thresholds };
+
+    assert_eq!(
+        classifier.classify(&observe(false, 0.05)).state,
+        ActivityState::Sedentary
+    );
+    assert_eq!(classifier.thresholds(), thresholds);
+}
+
+#[test]
+fn test_classifier_by_name() {
+    for name in CLASSIFIERS {
+        let classifier = classifier_by_name(name, Thresholds::default()).unwrap();
+        assert_eq!(classifier.name(), *name);
+    }
+    assert!(classifier_by_name("magic", Thresholds::default()).is_none());
+}
+
+//Hjorth Classifier Tests
+
+#[test]
+fn test_hjorth_uses_smoothed_acceleration_until_first_hop() {
+    let mut classifier = HjorthClassifier::default();
+    assert_eq!(
+        classifier.classify(&observe(false, 0.05)).state,
+        ActivityState::Active
+    );
+    assert_eq!(classifier.features(), None);
+}
+
+#[test]
+fn test_hjorth_computes_features_each_hop() {
+    let mut classifier = HjorthClassifier::default();
+    feed(&mut classifier, vec![0.0; HJORTH_HOP - 1]);
+    assert_eq!(classifier.features(), None);
+
+    let state = feed(&mut classifier, [0.0]);
+    assert_eq!(state, ActivityState::Sedentary);
+    assert_eq!(classifier.features().unwrap().hjorth_activity, 0.0);
+
+    // The stationarity check needs a few hops of data
+    feed(&mut classifier, vec![0.0; HJORTH_WINDOW]);
+    assert!(classifier.features().unwrap().stationarity_passed);
+}
+
+#[test]
+fn test_hjorth_counts_bursts_the_mean_misses() {
+    // One jolt in ten samples: mean 0.008, below the fidget threshold
+    let mut classifier = HjorthClassifier::default();
+    let burst = [0.0, 0.0, 0.0, 0.0, 0.08, 0.0, 0.0, 0.0, 0.0, 0.0];
+    let state = feed(
+        &mut classifier,
+        burst.iter().copied().cycle().take(HJORTH_WINDOW),
+    );
+    assert_eq!(state, ActivityState::Fidget);
+}
+
+#[test]
+fn test_hjorth_sustained_movement_is_active() {
+    let mut classifier = HjorthClassifier::default();
+    let state = feed(
+        &mut classifier,
+        [0.05, 0.07].into_iter().cycle().take(HJORTH_WINDOW),
+    );
+    assert_eq!(state, ActivityState::Active);
+    assert!(classifier.features().unwrap().hjorth_mobility > 0.0);
+}
+
+#[test]
+fn test_hjorth_latest_hop_decides_after_a_change() {
+    // Strong movement that stopped: the window as a whole is far from stationary
+    let mut classifier = HjorthClassifier::default();
+    feed(
+        &mut classifier,
+        [0.0, 2.0]
+            .into_iter()
+            .cycle()
+            .take(HJORTH_WINDOW - HJORTH_HOP),
+    );
+    let state = feed(&mut classifier, vec![0.0; HJORTH_HOP]);
+    assert!(!classifier.features().unwrap().stationarity_passed);
+    assert_eq!(state, ActivityState::Sedentary);
+}
+
+#[test]
+fn test_hjorth_pir_is_active_and_skips_out_of_range() {
+    let mut classifier = HjorthClassifier::default();
+    let observation = Observation {
+        pir: true,
+        smoothed_acc: 0.0,
+        acc: None,
+    };
+    for _ in 0..HJORTH_HOP {
+        assert_eq!(
+            classifier.classify(&observation).state,
+            ActivityState::Active
+        );
+    }
+    // Nothing entered the window
+    assert_eq!(classifier.features(), None);
+}
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+
+// CLUSTERING LIMITS
+const KMEANS_MAX_ITER: usize = 100;
+const JENKS_MAX_POINTS: usize = 1000; // Jenks is quadratic, larger inputs are sampled at quantiles
+
+// One group of values, lowest first in a Clustering
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Cluster {
+    pub center: f64, // Mean of the members
+    pub min: f64,
+    pub max: f64,
+    pub count: usize,
+}
+
+// 1-D clustering result: k clusters in ascending order and the k-1 values separating them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Clustering {
+    pub clusters: Vec<Cluster>,
+    pub breaks: Vec<f64>,
+}
+
+fn sorted(values: &[f64]) -> Vec<f64> {
+    let mut data: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    data.sort_by(f64::total_cmp);
+    data
+}
+
+// Clusters of sorted data split at the given breaks (a value equal to a break goes below it)
+fn split(data: &[f64], breaks: &[f64]) -> Vec<Cluster> {
+    let mut start = 0;
+    let mut clusters = Vec::with_capacity(breaks.len() + 1);
+    for end in breaks
+        .iter()
+        .map(|b| data.partition_point(|v| v <= b))
+        .chain([data.len()])
+    {
+        let members = &data[start..end.max(start)];
+        let center = if members.is_empty() {
+            f64::NAN
+        } else {
+            members.iter().sum::<f64>() / members.len() as f64
+        };
+        clusters.push(Cluster {
+            center,
+            min: members.first().copied().unwrap_or(f64::NAN),
+            max: members.last().copied().unwrap_or(f64::NAN),
+            count: members.len(),
+        });
+        start = end.max(start);
+    }
+    clusters
+}
+
+// 1. KMeans (Lloyd's algorithm)
+// In one dimension every cluster is a contiguous run of the sorted values, so each
+// iteration only has to find the midpoints between neighbouring centers. Starts
+// from the quantiles, so the result is deterministic.
+pub fn kmeans_1d(values: &[f64], k: usize) -> Option<Clustering> {
+    let data = sorted(values);
+    if k == 0 || data.len() < k {
+        return None;
+    }
+
+    let n = data.len();
+    let mut centers: Vec<f64> = (0..k)
+        .map(|i| data[((2 * i + 1) * n / (2 * k)).min(n - 1)])
+        .collect();
+    let mut breaks = Vec::new();
+    for _ in 0..KMEANS_MAX_ITER {
+        breaks = centers.windows(2).map(|c| (c[0] + c[1]) / 2.0).collect();
+        let updated: Vec<f64> = split(&data, &breaks)
+            .iter()
+            .zip(&centers)
+            // An empty cluster keeps its center
+            .map(|(cluster, old)| {
+                if cluster.count == 0 {
+                    *old
+                } else {
+                    cluster.center
+                }
+            })
+            .collect();
+        if updated == centers {
+            break;
+        }
+        centers = updated;
+    }
+
+    Some(Clustering {
+        clusters: split(&data, &breaks),
+        breaks,
+    })
+}
+
+// 2. Jenks Natural Breaks (Fisher's exact optimisation)
+// Minimises the summed squared deviation within the classes. Breaks lie halfway
+// between the last value of one class and the first value of the next.
+pub fn jenks_breaks(values: &[f64], k: usize) -> Option<Clustering> {
+    let data = sorted(values);
+    if k == 0 || data.len() < k {
+        return None;
+    }
+
+    // Quantile sample keeps the quadratic part bounded
+    let sample: Vec<f64> = if data.len() > JENKS_MAX_POINTS {
+        (0..JENKS_MAX_POINTS)
+            .map(|i| data[i * (data.len() - 1) / (JENKS_MAX_POINTS - 1)])
+            .collect()
+    } else {
+        data.clone()
+    };
+    let n = sample.len();
+
+    // Prefix sums give the squared deviation of any run in O(1)
+    let mut sum = vec![0.0; n + 1];
+    let mut sum_sq = vec![0.0; n + 1];
+    for (i, v) in sample.iter().enumerate() {
+        sum[i + 1] = sum[i] + v;
+        sum_sq[i + 1] = sum_sq[i] + v * v;
+    }
+    let cost = |from: usize, to: usize| {
+        let count = (to - from) as f64;
+        let s = sum[to] - sum[from];
+        (sum_sq[to] - sum_sq[from]) - s * s / count
+    };
+
+    // best[c][i]: lowest cost of splitting the first i values into c + 1 classes
+    let mut best = vec![vec![f64::INFINITY; n + 1]; k];
+    let mut start = vec![vec![0usize; n + 1]; k];
+    for (i, slot) in best[0].iter_mut().enumerate().skip(1) {
+        *slot = cost(0, i);
+    }
+    for c in 1..k {
+        for i in (c + 1)..=n {
+            for j in c..i {
+                let total = best[c - 1][j] + cost(j, i);
+                if total < best[c][i] {
+                    best[c][i] = total;
+                    start[c][i] = j;
+                }
+            }
+        }
+    }
+
+    // Walk back from the last class to find where each one starts
+    let mut starts = vec![0usize; k];
+    let mut end = n;
+    for c in (1..k).rev() {
+        starts[c] = start[c][end];
+        end = starts[c];
+    }
+    let breaks: Vec<f64> = starts[1..]
+        .iter()
+        .map(|&s| (sample[s - 1] + sample[s]) / 2.0)
+        .collect();
+
+    Some(Clustering {
+        clusters: split(&data, &breaks),
+        breaks,
+    })
+}
+
+#[cfg(test)]
+#[path = "clustering_tests.rs"]
+mod tests;
@@ -0,0 +1,79 @@
+use super::*;
+
+// Three well separated groups: still, fidgeting, walking
+fn three_groups() -> Vec<f64> {
+    let mut values = Vec::new();
+    for i in 0..60 {
+        values.push(0.004 + (i % 5) as f64 * 0.001);
+    }
+    for i in 0..30 {
+        values.push(0.028 + (i % 4) as f64 * 0.002);
+    }
+    for i in 0..10 {
+        values.push(0.10 + (i % 3) as f64 * 0.02);
+    }
+    values
+}
+
+//KMeans Tests
+
+#[test]
+fn test_kmeans_finds_separated_groups() {
+    let result = kmeans_1d(&three_groups(), 3).unwrap();
+
+    let counts: Vec<usize> = result.clusters.iter().map(|c| c.count).collect();
+    assert_eq!(counts, vec![60, 30, 10]);
+    assert!((result.clusters[0].center - 0.006).abs() < 1e-9);
+    assert!((result.clusters[1].center - 0.031).abs() < 1e-3);
+    // Breaks halfway between the centers
+    let halfway = (result.clusters[0].center + result.clusters[1].center) / 2.0;
+    assert!((result.breaks[0] - halfway).abs() < 1e-12);
+    assert!(result.breaks[0] > 0.008 && result.breaks[0] < 0.028);
+    assert!(result.breaks[1] > 0.034 && result.breaks[1] < 0.10);
+}
+
+#[test]
+fn test_kmeans_ignores_order_and_non_finite() {
+    let mut values = three_groups();
+    values.reverse();
+    values.push(f64::NAN);
+    assert_eq!(kmeans_1d(&values, 3), kmeans_1d(&three_groups(), 3));
+}
+
+#[test]
+fn test_kmeans_needs_k_values() {
+    assert_eq!(kmeans_1d(&[0.1, 0.2], 3), None);
+    assert_eq!(kmeans_1d(&[0.1, 0.2], 0), None);
+}
+
+//Jenks Tests
+
+#[test]
+fn test_jenks_finds_separated_groups() {
+    let result = jenks_breaks(&three_groups(), 3).unwrap();
+
+    let counts: Vec<usize> = result.clusters.iter().map(|c| c.count).collect();
+    assert_eq!(counts, vec![60, 30, 10]);
+    // Breaks halfway across the gaps
+    assert!((result.breaks[0] - (0.008 + 0.028) / 2.0).abs() < 1e-9);
+    assert!((result.breaks[1] - (0.034 + 0.10) / 2.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_jenks_samples_large_inputs() {
+    // 30,000 values still split at the same gaps
+    let values: Vec<f64> = three_groups().into_iter().cycle().take(30_000).collect();
+    let result = jenks_breaks(&values, 3).unwrap();
+
+    let counts: Vec<usize> = result.clusters.iter().map(|c| c.count).collect();
+    assert_eq!(counts, vec![18_000, 9_000, 3_000]);
+    assert!(result.breaks[0] > 0.008 && result.breaks[0] < 0.028);
+    assert!(result.breaks[1] > 0.034 && result.breaks[1] < 0.10);
+}
+
+#[test]
+fn test_jenks_constant_data_has_empty_classes() {
+    let result = jenks_breaks(&[0.0; 20], 3).unwrap();
+    assert_eq!(result.clusters[0].count, 20);
+    assert_eq!(result.breaks, vec![0.0, 0.0]);
+}
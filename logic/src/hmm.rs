@@ -0,0 +1,361 @@
+use crate::classifier::{ActivityState, Thresholds};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+// HMM CONFIGURATION
+pub const ACC_FLOOR: f32 = 1e-4; // Added before the log, so a flat signal stays finite
+const INITIAL_STAY: f64 = 0.99; // Starting chance of keeping the state between two samples (10Hz)
+const INITIAL_STD: f64 = 0.5; // Starting spread of every state, in ln units
+const MIN_STD: f64 = 0.05; // Keeps a state from collapsing onto identical values
+const MIN_STAY: f64 = 0.95; // Chance of keeping a state never learned below this (~2s dwell at 10Hz)
+
+// Hidden states of the offline model, quietest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HiddenState {
+    NonWear, // Sensor not worn: flatter than sitting still
+    Sedentary,
+    Fidget,
+    Active,
+}
+
+impl HiddenState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HiddenState::NonWear => "NON_WEAR",
+            HiddenState::Sedentary => "SEDENTARY",
+            HiddenState::Fidget => "FIDGET",
+            HiddenState::Active => "ACTIVE",
+        }
+    }
+}
+
+impl From<ActivityState> for HiddenState {
+    fn from(state: ActivityState) -> Self {
+        match state {
+            ActivityState::Sedentary => HiddenState::Sedentary,
+            ActivityState::Fidget => HiddenState::Fidget,
+            ActivityState::Active => HiddenState::Active,
+        }
+    }
+}
+
+impl fmt::Display for HiddenState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HiddenState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "NON_WEAR" => Ok(HiddenState::NonWear),
+            other => other.parse::<ActivityState>().map(HiddenState::from),
+        }
+    }
+}
+
+// Emission value of one sample: the log makes the quiet states as wide as the active one
+pub fn log_acc(acc: f32) -> f64 {
+    (acc.max(0.0) + ACC_FLOOR).ln() as f64
+}
+
+// Outcome of a Baum-Welch fit
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FitReport {
+    pub iterations: usize,
+    pub log_likelihood: f64,
+    pub converged: bool,
+}
+
+// Sums of one E-step over all sequences
+struct Expectations {
+    initial: Vec<f64>,
+    transitions: Vec<Vec<f64>>,
+    occupancy: Vec<f64>,   // Sum of gamma per state
+    from_weight: Vec<f64>, // Sum of gamma per state, without the last sample of each sequence
+    sum_x: Vec<f64>,
+    sum_x2: Vec<f64>,
+    log_likelihood: f64,
+}
+
+impl Expectations {
+    fn new(k: usize) -> Self {
+        Expectations {
+            initial: vec![0.0; k],
+            transitions: vec![vec![0.0; k]; k],
+            occupancy: vec![0.0; k],
+            from_weight: vec![0.0; k],
+            sum_x: vec![0.0; k],
+            sum_x2: vec![0.0; k],
+            log_likelihood: 0.0,
+        }
+    }
+}
+
+// Hidden Markov model with one Gaussian emission per state. Observations are
+// log_acc values; each mean stays inside its state's band of the thresholds,
+// so the labels keep their meaning through training.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GaussianHmm {
+    pub states: Vec<HiddenState>,
+    pub initial: Vec<f64>, // Chance of each state at the start of a sequence
+    pub transitions: Vec<Vec<f64>>, // transitions[from][to]
+    pub means: Vec<f64>,
+    pub stds: Vec<f64>,
+    pub bounds: Vec<(f64, f64)>, // Lowest and highest mean of each state
+}
+
+impl GaussianHmm {
+    // Starting point from the classification thresholds, optionally with a non-wear state
+    pub fn from_thresholds(thresholds: &Thresholds, non_wear: bool) -> Self {
+        let fidget = log_acc(thresholds.fidget);
+        let active = log_acc(thresholds.active);
+        let mut levels = Vec::new();
+        let mut states = Vec::new();
+        let mut bounds = Vec::new();
+        if non_wear {
+            let flat = log_acc(thresholds.fidget / 10.0);
+            states.push(HiddenState::NonWear);
+            levels.push(thresholds.fidget / 20.0);
+            bounds.extend([(f64::NEG_INFINITY, flat), (flat, fidget)]);
+        } else {
+            bounds.push((f64::NEG_INFINITY, fidget));
+        }
+        states.extend([
+            HiddenState::Sedentary,
+            HiddenState::Fidget,
+            HiddenState::Active,
+        ]);
+        levels.extend([
+            thresholds.fidget / 2.0,
+            (thresholds.fidget + thresholds.active) / 2.0,
+            thresholds.active * 2.0,
+        ]);
+        bounds.extend([(fidget, active), (active, f64::INFINITY)]);
+
+        let k = states.len();
+        let leave = (1.0 - INITIAL_STAY) / (k - 1) as f64;
+        GaussianHmm {
+            initial: vec![1.0 / k as f64; k],
+            transitions: (0..k)
+                .map(|from| {
+                    (0..k)
+                        .map(|to| if from == to { INITIAL_STAY } else { leave })
+                        .collect()
+                })
+                .collect(),
+            means: levels.iter().map(|level| log_acc(*level)).collect(),
+            stds: vec![INITIAL_STD; k],
+            states,
+            bounds,
+        }
+    }
+
+    fn log_emission(&self, state: usize, x: f64) -> f64 {
+        let z = (x - self.means[state]) / self.stds[state];
+        -0.5 * z * z - self.stds[state].ln() - 0.5 * (2.0 * std::f64::consts::PI).ln()
+    }
+
+    // Emission likelihoods scaled so the best state of each sample is 1, and the
+    // log of that scale
+    fn emissions(&self, x: f64) -> (Vec<f64>, f64) {
+        let logs: Vec<f64> = (0..self.states.len())
+            .map(|state| self.log_emission(state, x))
+            .collect();
+        let max = logs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (logs.iter().map(|l| (l - max).exp()).collect(), max)
+    }
+
+    // Scaled forward-backward over one sequence, adding to the sums
+    fn expect(&self, observations: &[f64], sums: &mut Expectations) {
+        let k = self.states.len();
+        let n = observations.len();
+        if n == 0 {
+            return;
+        }
+
+        let mut emissions = Vec::with_capacity(n);
+        let mut alpha = vec![vec![0.0; k]; n];
+        let mut scale = vec![0.0; n];
+        for (t, x) in observations.iter().enumerate() {
+            let (b, shift) = self.emissions(*x);
+            for to in 0..k {
+                let prior = if t == 0 {
+                    self.initial[to]
+                } else {
+                    (0..k)
+                        .map(|from| alpha[t - 1][from] * self.transitions[from][to])
+                        .sum()
+                };
+                alpha[t][to] = prior * b[to];
+            }
+            scale[t] = alpha[t].iter().sum::<f64>().max(f64::MIN_POSITIVE);
+            alpha[t].iter_mut().for_each(|a| *a /= scale[t]);
+            sums.log_likelihood += scale[t].ln() + shift;
+            emissions.push(b);
+        }
+
+        // Backward pass, accumulating gamma and xi on the way
+        let mut beta = vec![1.0; k];
+        for t in (0..n).rev() {
+            let gamma: Vec<f64> = (0..k).map(|s| alpha[t][s] * beta[s]).collect();
+            let total = gamma.iter().sum::<f64>().max(f64::MIN_POSITIVE);
+            for (s, g) in gamma.iter().map(|g| g / total).enumerate() {
+                sums.occupancy[s] += g;
+                sums.sum_x[s] += g * observations[t];
+                sums.sum_x2[s] += g * observations[t] * observations[t];
+                if t == 0 {
+                    sums.initial[s] += g;
+                }
+                if t + 1 < n {
+                    sums.from_weight[s] += g;
+                }
+            }
+            if t == 0 {
+                break;
+            }
+
+            let b = &emissions[t];
+            for (from, a) in alpha[t - 1].iter().enumerate() {
+                for to in 0..k {
+                    sums.transitions[from][to] +=
+                        a * self.transitions[from][to] * b[to] * beta[to] / scale[t];
+                }
+            }
+            beta = (0..k)
+                .map(|from| {
+                    (0..k)
+                        .map(|to| self.transitions[from][to] * b[to] * beta[to])
+                        .sum::<f64>()
+                        / scale[t]
+                })
+                .collect();
+        }
+    }
+
+    // Log-likelihood of the sequences under the current parameters
+    pub fn log_likelihood(&self, sequences: &[Vec<f64>]) -> f64 {
+        let mut sums = Expectations::new(self.states.len());
+        for sequence in sequences {
+            self.expect(sequence, &mut sums);
+        }
+        sums.log_likelihood
+    }
+
+    // Baum-Welch: re-estimates all parameters until the log-likelihood per sample
+    // improves by less than `tolerance`
+    pub fn fit(&mut self, sequences: &[Vec<f64>], max_iter: usize, tolerance: f64) -> FitReport {
+        let k = self.states.len();
+        let samples: usize = sequences.iter().map(Vec::len).sum();
+        let mut previous = f64::NEG_INFINITY;
+        let mut report = FitReport {
+            iterations: 0,
+            log_likelihood: previous,
+            converged: false,
+        };
+        if samples == 0 {
+            return report;
+        }
+
+        for iteration in 1..=max_iter {
+            let mut sums = Expectations::new(k);
+            for sequence in sequences {
+                self.expect(sequence, &mut sums);
+            }
+            report.iterations = iteration;
+            report.log_likelihood = sums.log_likelihood;
+            if (sums.log_likelihood - previous) / (samples as f64) < tolerance {
+                report.converged = true;
+                break;
+            }
+            previous = sums.log_likelihood;
+
+            let starts: f64 = sums.initial.iter().sum();
+            for s in 0..k {
+                self.initial[s] = sums.initial[s] / starts;
+                if sums.from_weight[s] > 0.0 {
+                    let row: f64 = sums.transitions[s].iter().sum();
+                    for to in 0..k {
+                        self.transitions[s][to] = sums.transitions[s][to] / row;
+                    }
+                    self.keep_stay(s);
+                }
+                // A state nothing was assigned to keeps its emission
+                if sums.occupancy[s] > f64::EPSILON {
+                    let (low, high) = self.bounds[s];
+                    let mean = (sums.sum_x[s] / sums.occupancy[s]).clamp(low, high);
+                    // Spread around the clamped mean
+                    let variance = sums.sum_x2[s] / sums.occupancy[s]
+                        - 2.0 * mean * sums.sum_x[s] / sums.occupancy[s]
+                        + mean * mean;
+                    self.means[s] = mean;
+                    self.stds[s] = variance.max(0.0).sqrt().max(MIN_STD);
+                }
+            }
+        }
+        report
+    }
+
+    // Holds a state's self-transition at MIN_STAY or above, so a single sample
+    // can't be its own state
+    fn keep_stay(&mut self, state: usize) {
+        let row = &mut self.transitions[state];
+        let leave = 1.0 - row[state];
+        if row[state] >= MIN_STAY || leave <= 0.0 {
+            return;
+        }
+        for (to, p) in row.iter_mut().enumerate() {
+            *p = if to == state {
+                MIN_STAY
+            } else {
+                *p / leave * (1.0 - MIN_STAY)
+            };
+        }
+    }
+
+    // Viterbi: the single most likely state sequence
+    pub fn viterbi(&self, observations: &[f64]) -> Vec<HiddenState> {
+        let k = self.states.len();
+        let n = observations.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let log_transitions: Vec<Vec<f64>> = self
+            .transitions
+            .iter()
+            .map(|row| row.iter().map(|p| p.ln()).collect())
+            .collect();
+
+        let mut score: Vec<f64> = (0..k)
+            .map(|s| self.initial[s].ln() + self.log_emission(s, observations[0]))
+            .collect();
+        let mut back = vec![vec![0u8; k]; n];
+        for t in 1..n {
+            score = (0..k)
+                .map(|to| {
+                    let (from, best) = (0..k)
+                        .map(|from| (from, score[from] + log_transitions[from][to]))
+                        .fold((0, f64::NEG_INFINITY), |a, b| if b.1 > a.1 { b } else { a });
+                    back[t][to] = from as u8;
+                    best + self.log_emission(to, observations[t])
+                })
+                .collect();
+        }
+
+        let mut state = (0..k).fold(0, |best, s| if score[s] > score[best] { s } else { best });
+        let mut path = vec![self.states[state]; n];
+        for t in (1..n).rev() {
+            state = back[t][state] as usize;
+            path[t - 1] = self.states[state];
+        }
+        path
+    }
+}
+
+#[cfg(test)]
+#[path = "hmm_tests.rs"]
+mod tests;
@@ -0,0 +1,176 @@
+use super::*;
+
+// Deterministic wobble around a level, like a sensor's noise
+fn around(level: f32, n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| log_acc(level * (0.8 + 0.1 * (i % 5) as f32)))
+        .collect()
+}
+
+// Still, then fidgeting with two blips past the active threshold, then walking
+fn day() -> Vec<f64> {
+    let mut values = around(0.005, 300);
+    values.extend(around(0.03, 150));
+    values.extend(around(0.06, 2));
+    values.extend(around(0.03, 150));
+    values.extend(around(0.06, 2));
+    values.extend(around(0.03, 100));
+    values.extend(around(0.15, 300));
+    values
+}
+
+fn count(path: &[HiddenState], state: HiddenState) -> usize {
+    path.iter().filter(|s| **s == state).count()
+}
+
+//Model Tests
+
+#[test]
+fn test_initial_model_is_ordered_and_normalised() {
+    let hmm = GaussianHmm::from_thresholds(&Thresholds::default(), true);
+    assert_eq!(
+        hmm.states,
+        vec![
+            HiddenState::NonWear,
+            HiddenState::Sedentary,
+            HiddenState::Fidget,
+            HiddenState::Active
+        ]
+    );
+    assert!(hmm.means.windows(2).all(|m| m[0] < m[1]));
+    for row in &hmm.transitions {
+        assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+    assert!((hmm.initial.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_hidden_state_names() {
+    assert_eq!("NON_WEAR".parse::<HiddenState>(), Ok(HiddenState::NonWear));
+    assert_eq!("FIDGET".parse::<HiddenState>(), Ok(HiddenState::Fidget));
+    assert!("STILL".parse::<HiddenState>().is_err());
+    assert_eq!(
+        HiddenState::from(ActivityState::Active).to_string(),
+        "ACTIVE"
+    );
+}
+
+#[test]
+fn test_log_acc_is_finite_for_flat_signal() {
+    assert!(log_acc(0.0).is_finite());
+    assert!(log_acc(-1.0).is_finite());
+    assert!(log_acc(0.0) < log_acc(0.001));
+}
+
+//Viterbi Tests
+
+#[test]
+fn test_viterbi_follows_clear_levels() {
+    let hmm = GaussianHmm::from_thresholds(&Thresholds::default(), false);
+    let mut values = around(0.005, 50);
+    values.extend(around(0.03, 50));
+    values.extend(around(0.15, 50));
+
+    let path = hmm.viterbi(&values);
+    assert_eq!(path.len(), 150);
+    assert_eq!(path[10], HiddenState::Sedentary);
+    assert_eq!(path[75], HiddenState::Fidget);
+    assert_eq!(path[140], HiddenState::Active);
+    assert!(hmm.viterbi(&[]).is_empty());
+}
+
+#[test]
+fn test_viterbi_removes_short_blips() {
+    let hmm = GaussianHmm::from_thresholds(&Thresholds::default(), false);
+    let mut values = around(0.005, 100);
+    values.push(log_acc(0.03));
+    values.extend(around(0.005, 100));
+
+    // A single fidget-level sample is not worth two state changes
+    let path = hmm.viterbi(&values);
+    assert_eq!(count(&path, HiddenState::Sedentary), 201);
+}
+
+//Baum-Welch Tests
+
+#[test]
+fn test_fit_improves_likelihood_and_converges() {
+    let sequences = vec![day()];
+    let mut hmm = GaussianHmm::from_thresholds(&Thresholds::default(), false);
+    let before = hmm.log_likelihood(&sequences);
+
+    let report = hmm.fit(&sequences, 100, 1e-6);
+    assert!(report.converged);
+    assert!(report.log_likelihood > before);
+    for row in &hmm.transitions {
+        assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+    // Long stays are learned from the data
+    assert!(hmm.transitions[0][0] > 0.99);
+}
+
+#[test]
+fn test_fit_learns_levels_and_smooths_blips() {
+    let sequences = vec![day()];
+    let mut hmm = GaussianHmm::from_thresholds(&Thresholds::default(), false);
+    hmm.fit(&sequences, 100, 1e-6);
+
+    // Means move to the data (still at ~0.005, walking at ~0.15)
+    assert!((hmm.means[0] - log_acc(0.005)).abs() < 0.2);
+    assert!((hmm.means[2] - log_acc(0.15)).abs() < 0.2);
+
+    // The blips would flip the real-time label to ACTIVE, the cleaned one stays FIDGET
+    let path = hmm.viterbi(&sequences[0]);
+    assert_eq!(count(&path, HiddenState::Sedentary), 300);
+    assert_eq!(count(&path, HiddenState::Fidget), 404);
+    assert_eq!(count(&path, HiddenState::Active), 300);
+}
+
+#[test]
+fn test_fit_finds_non_wear() {
+    // Board left on the desk: flat apart from quantisation noise
+    let mut values: Vec<f64> = (0..300)
+        .map(|i| log_acc(if i % 7 == 0 { 0.0002 } else { 0.0 }))
+        .collect();
+    values.extend(around(0.005, 300));
+    values.extend(around(0.15, 100));
+    let sequences = vec![values];
+
+    let mut hmm = GaussianHmm::from_thresholds(&Thresholds::default(), true);
+    hmm.fit(&sequences, 100, 1e-6);
+    let path = hmm.viterbi(&sequences[0]);
+
+    assert_eq!(count(&path[..300], HiddenState::NonWear), 300);
+    assert_eq!(count(&path[300..600], HiddenState::Sedentary), 300);
+    assert_eq!(count(&path[600..], HiddenState::Active), 100);
+}
+
+#[test]
+fn test_fit_keeps_one_behaviour_in_its_state() {
+    // A whole sitting: the unused states must not split its noise between them
+    let sequences = vec![around(0.005, 500)];
+    let mut hmm = GaussianHmm::from_thresholds(&Thresholds::default(), false);
+    hmm.fit(&sequences, 100, 1e-6);
+
+    let thresholds = Thresholds::default();
+    assert!(hmm.means[1] >= log_acc(thresholds.fidget));
+    assert!(hmm.means[2] >= log_acc(thresholds.active));
+    let path = hmm.viterbi(&sequences[0]);
+    assert_eq!(count(&path, HiddenState::Sedentary), 500);
+}
+
+#[test]
+fn test_fit_over_several_sequences() {
+    let sequences = vec![
+        around(0.005, 200),
+        Vec::new(),
+        around(0.15, 1),
+        around(0.03, 200),
+    ];
+    let mut hmm = GaussianHmm::from_thresholds(&Thresholds::default(), false);
+
+    let report = hmm.fit(&sequences, 50, 1e-6);
+    assert!(report.log_likelihood.is_finite());
+    assert!(hmm.means.windows(2).all(|m| m[0] <= m[1]));
+    assert!(hmm.stds.iter().all(|s| *s >= MIN_STD));
+}
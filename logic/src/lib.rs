@@ -1,5 +1,24 @@
 use serde::{Deserialize, Serialize};
 
+pub mod classifier;
+pub use classifier::{
+    AccelerationClassifier, ActivityState, CLASSIFIERS, Classifier, HjorthClassifier, Observation,
+    SMOOTHING_WINDOW, THRESH_ACTIVE, THRESH_FIDGET, ThresholdClassifier, Thresholds, Verdict,
+    classifier_by_name,
+};
+
+pub mod calibration;
+pub use calibration::{CalibrationPhase, CalibrationProfile, CalibrationRecorder, PhaseStats};
+
+pub mod clustering;
+pub use clustering::{Cluster, Clustering, jenks_breaks, kmeans_1d};
+
+pub mod hmm;
+pub use hmm::{FitReport, GaussianHmm, HiddenState, log_acc};
+
+pub mod state_machine;
+pub use state_machine::{StateMachine, StateMachineConfig};
+
 // Holds a window of accelerometer data
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SignalWindow {
@@ -21,10 +40,17 @@ impl SignalWindow {
             self.data_points.remove(0);
         }
     }
+
+    // Drop all but the newest n values (sliding window)
+    pub fn keep_last(&mut self, n: usize) {
+        if self.data_points.len() > n {
+            self.data_points.drain(..self.data_points.len() - n);
+        }
+    }
 }
 
 // Scientific Features extracted from the signal
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct SignalFeatures {
     pub mean: f64,
     pub variance: f64,
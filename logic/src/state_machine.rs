@@ -0,0 +1,127 @@
+use crate::classifier::{ActivityState, Thresholds};
+use serde::{Deserialize, Serialize};
+
+// STATE MACHINE DEFAULTS
+pub const EXIT_RATIO: f32 = 0.8; // A state is left below 80% of the threshold that entered it
+pub const MIN_DWELL_MS: i64 = 2000; // Time outside the current state before a change counts
+
+// Hysteresis and dwell parameters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StateMachineConfig {
+    pub exit_ratio: f32, // Exit threshold as a fraction of the enter threshold (1 = no hysteresis)
+    pub min_dwell_ms: i64, // 0 = changes count at once
+}
+
+impl Default for StateMachineConfig {
+    fn default() -> Self {
+        StateMachineConfig {
+            exit_ratio: EXIT_RATIO,
+            min_dwell_ms: MIN_DWELL_MS,
+        }
+    }
+}
+
+impl StateMachineConfig {
+    // Every change counts at once, as with a bare classifier
+    pub fn immediate() -> Self {
+        StateMachineConfig {
+            exit_ratio: 1.0,
+            min_dwell_ms: 0,
+        }
+    }
+
+    // Levels the classifier's level has to fall below to leave FIDGET / ACTIVE
+    pub fn exit_thresholds(&self, enter: &Thresholds) -> Thresholds {
+        Thresholds {
+            fidget: enter.fidget * self.exit_ratio,
+            active: enter.active * self.exit_ratio,
+        }
+    }
+}
+
+// Debounces the per-sample classifier output. A state is entered at its
+// threshold but only left once the signal drops below the lower exit
+// threshold, and a change only counts after the signal stayed out of the
+// current state for min_dwell_ms. One instance per device.
+#[derive(Debug, Clone)]
+pub struct StateMachine {
+    pub config: StateMachineConfig,
+    current: Option<ActivityState>,
+    away_since: Option<i64>, // Device time (ms) the signal first left the current state
+}
+
+impl StateMachine {
+    pub fn new(config: StateMachineConfig) -> Self {
+        StateMachine {
+            config,
+            current: None,
+            away_since: None,
+        }
+    }
+
+    pub fn state(&self) -> Option<ActivityState> {
+        self.current
+    }
+
+    // Settled state after one reading. `proposed` is the classifier's verdict
+    // for this sample alone, `level` the value it decided on (Verdict::level),
+    // `at_ms` the device time of the sample.
+    pub fn update(
+        &mut self,
+        proposed: ActivityState,
+        level: f32,
+        thresholds: &Thresholds,
+        at_ms: i64,
+    ) -> ActivityState {
+        let Some(current) = self.current else {
+            // Nothing to debounce against yet
+            self.current = Some(proposed);
+            return proposed;
+        };
+
+        let target = if self.holds(current, proposed, level, thresholds) {
+            current
+        } else {
+            proposed
+        };
+        if target == current {
+            self.away_since = None;
+            return current;
+        }
+
+        let since = *self.away_since.get_or_insert(at_ms);
+        if at_ms - since >= self.config.min_dwell_ms {
+            self.current = Some(target);
+            self.away_since = None;
+            target
+        } else {
+            current
+        }
+    }
+
+    // Stays in a state while the signal is still above its exit threshold
+    fn holds(
+        &self,
+        current: ActivityState,
+        proposed: ActivityState,
+        level: f32,
+        thresholds: &Thresholds,
+    ) -> bool {
+        let exit = self.config.exit_thresholds(thresholds);
+        match (current, proposed) {
+            (ActivityState::Active, _) => level > exit.active,
+            (ActivityState::Fidget, ActivityState::Sedentary) => level > exit.fidget,
+            _ => false,
+        }
+    }
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self::new(StateMachineConfig::default())
+    }
+}
+
+#[cfg(test)]
+#[path = "state_machine_tests.rs"]
+mod tests;
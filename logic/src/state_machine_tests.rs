@@ -0,0 +1,174 @@
+use super::*;
+use crate::classifier::{Classifier, HjorthClassifier, Observation};
+
+const SEDENTARY: ActivityState = ActivityState::Sedentary;
+const FIDGET: ActivityState = ActivityState::Fidget;
+const ACTIVE: ActivityState = ActivityState::Active;
+
+fn machine(exit_ratio: f32, min_dwell_ms: i64) -> StateMachine {
+    StateMachine::new(StateMachineConfig {
+        exit_ratio,
+        min_dwell_ms,
+    })
+}
+
+// Feeds (proposed, smoothed_acc) pairs 100ms apart and returns the settled states
+fn run(machine: &mut StateMachine, samples: &[(ActivityState, f32)]) -> Vec<ActivityState> {
+    let thresholds = Thresholds::default();
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, (proposed, acc))| machine.update(*proposed, *acc, &thresholds, i as i64 * 100))
+        .collect()
+}
+
+//Hysteresis Tests
+
+#[test]
+fn test_first_sample_is_taken_as_is() {
+    let mut sm = StateMachine::default();
+    assert_eq!(sm.state(), None);
+    assert_eq!(run(&mut sm, &[(ACTIVE, 0.05)]), vec![ACTIVE]);
+    assert_eq!(sm.state(), Some(ACTIVE));
+}
+
+#[test]
+fn test_exit_thresholds_scale_enter_thresholds() {
+    let exit = StateMachineConfig::default().exit_thresholds(&Thresholds::default());
+    assert!((exit.fidget - 0.016).abs() < 1e-6);
+    assert!((exit.active - 0.032).abs() < 1e-6);
+}
+
+#[test]
+fn test_active_held_until_below_exit_threshold() {
+    let mut sm = machine(0.8, 0);
+    let states = run(
+        &mut sm,
+        &[
+            (ACTIVE, 0.041),
+            (FIDGET, 0.039), // Still above 0.032
+            (ACTIVE, 0.041),
+            (FIDGET, 0.035),
+            (FIDGET, 0.030), // Below the exit threshold
+        ],
+    );
+    assert_eq!(states, vec![ACTIVE, ACTIVE, ACTIVE, ACTIVE, FIDGET]);
+}
+
+#[test]
+fn test_fidget_held_until_below_exit_threshold() {
+    let mut sm = machine(0.8, 0);
+    let states = run(
+        &mut sm,
+        &[(FIDGET, 0.021), (SEDENTARY, 0.018), (SEDENTARY, 0.010)],
+    );
+    assert_eq!(states, vec![FIDGET, FIDGET, SEDENTARY]);
+}
+
+#[test]
+fn test_pir_activity_ends_with_the_pir() {
+    // Active from the PIR with no movement has nothing to hold it
+    let mut sm = machine(0.8, 0);
+    let states = run(&mut sm, &[(ACTIVE, 0.0), (SEDENTARY, 0.0)]);
+    assert_eq!(states, vec![ACTIVE, SEDENTARY]);
+}
+
+//Dwell Tests
+
+#[test]
+fn test_short_blips_do_not_change_state() {
+    let mut sm = machine(1.0, 500);
+    let mut samples = vec![(SEDENTARY, 0.0)];
+    // Flickering several times a second never stays away for 500ms
+    for _ in 0..10 {
+        samples.push((ACTIVE, 0.041));
+        samples.push((ACTIVE, 0.041));
+        samples.push((SEDENTARY, 0.0));
+    }
+    let states = run(&mut sm, &samples);
+    assert!(states.iter().all(|state| *state == SEDENTARY));
+}
+
+#[test]
+fn test_change_counts_after_dwell() {
+    let mut sm = machine(1.0, 300);
+    let states = run(
+        &mut sm,
+        &[
+            (SEDENTARY, 0.0),
+            (ACTIVE, 0.05), // away since 100ms
+            (ACTIVE, 0.05),
+            (ACTIVE, 0.05),
+            (ACTIVE, 0.05), // 300ms away
+        ],
+    );
+    assert_eq!(
+        states,
+        vec![SEDENTARY, SEDENTARY, SEDENTARY, SEDENTARY, ACTIVE]
+    );
+}
+
+#[test]
+fn test_dwell_counts_time_away_not_one_target() {
+    // Movement wavering between FIDGET and ACTIVE still leaves SEDENTARY
+    let mut sm = machine(1.0, 200);
+    let states = run(
+        &mut sm,
+        &[
+            (SEDENTARY, 0.0),
+            (FIDGET, 0.03),
+            (ACTIVE, 0.05),
+            (FIDGET, 0.03),
+        ],
+    );
+    assert_eq!(states, vec![SEDENTARY, SEDENTARY, SEDENTARY, FIDGET]);
+}
+
+#[test]
+fn test_immediate_config_follows_classifier() {
+    let mut sm = StateMachine::new(StateMachineConfig::immediate());
+    let samples = [
+        (SEDENTARY, 0.0),
+        (ACTIVE, 0.041),
+        (FIDGET, 0.039),
+        (SEDENTARY, 0.0),
+    ];
+    let expected: Vec<ActivityState> = samples.iter().map(|(state, _)| *state).collect();
+    assert_eq!(run(&mut sm, &samples), expected);
+}
+
+// Classifier level tests
+
+#[test]
+fn test_hjorth_state_held_on_its_own_level() {
+    // Bursty fidgeting: the smoothed mean stays low, the Hjorth level is what counts
+    let mut classifier = HjorthClassifier::default();
+    let mut sm = machine(0.8, 0);
+    let thresholds = Thresholds::default();
+    let burst = |peak: f32| (0..10).map(move |i| if i == 4 { peak } else { 0.0 });
+    let deltas = burst(0.08)
+        .cycle()
+        .take(50)
+        .chain(burst(0.045).cycle().take(50));
+
+    let mut last = None;
+    for (i, acc) in deltas.enumerate() {
+        let verdict = classifier.classify(&Observation {
+            pir: false,
+            smoothed_acc: 0.005,
+            acc: Some(acc),
+        });
+        let state = sm.update(verdict.state, verdict.level, &thresholds, i as i64 * 100);
+        last = Some((verdict, state));
+    }
+
+    // Weaker bursts fall below the fidget threshold but not below its exit threshold
+    let (verdict, state) = last.unwrap();
+    assert_eq!(verdict.state, SEDENTARY);
+    assert!(
+        verdict.level > 0.016 && verdict.level < 0.02,
+        "{}",
+        verdict.level
+    );
+    assert_eq!(state, FIDGET);
+}
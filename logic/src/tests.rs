@@ -41,6 +41,19 @@ fn test_signal_window_buffer_limit() {
     assert_eq!(window.data_points[1999], 2099.0);
 }
 
+#[test]
+fn test_signal_window_keep_last() {
+    let mut window = SignalWindow::new();
+    for i in 0..10 {
+        window.add(i as f64);
+    }
+    window.keep_last(3);
+    assert_eq!(window.data_points, vec![7.0, 8.0, 9.0]);
+    // Shorter windows are left alone
+    window.keep_last(5);
+    assert_eq!(window.data_points.len(), 3);
+}
+
 //Stationarity Tests
 
 #[test]
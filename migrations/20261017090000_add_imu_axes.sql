@@ -0,0 +1,9 @@
+-- Raw IMU axes from the extended reading format (NULL for legacy "acc"-only readings)
+ALTER TABLE sedentary_log
+    ADD COLUMN IF NOT EXISTS magnitude REAL,
+    ADD COLUMN IF NOT EXISTS ax REAL,
+    ADD COLUMN IF NOT EXISTS ay REAL,
+    ADD COLUMN IF NOT EXISTS az REAL,
+    ADD COLUMN IF NOT EXISTS gx REAL,
+    ADD COLUMN IF NOT EXISTS gy REAL,
+    ADD COLUMN IF NOT EXISTS gz REAL;
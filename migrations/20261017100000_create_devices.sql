@@ -0,0 +1,13 @@
+-- Latest hello/handshake per device, so we can see what is deployed
+CREATE TABLE IF NOT EXISTS devices (
+    device_id VARCHAR(64) PRIMARY KEY,
+    firmware VARCHAR(32) NOT NULL,
+    protocol INTEGER NOT NULL,
+    sample_rate_hz INTEGER NOT NULL,
+    sensors TEXT[] NOT NULL DEFAULT '{}',
+    port TEXT NOT NULL,
+    accepted BOOLEAN NOT NULL,
+    detail TEXT NOT NULL DEFAULT '',
+    first_seen TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,7 @@
+-- Device time resolved to a full date, when the server got the reading, and the
+-- reading time corrected onto the server clock (created_at stays the insert time)
+ALTER TABLE sedentary_log
+    ADD COLUMN IF NOT EXISTS device_time TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS received_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS measured_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS clock_offset_ms BIGINT;
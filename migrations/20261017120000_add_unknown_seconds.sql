@@ -0,0 +1,3 @@
+-- Data gaps (longer than TIMER_MAX_GAP_SEC) inside the current timer span
+ALTER TABLE sedentary_log
+    ADD COLUMN IF NOT EXISTS unknown_seconds INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,16 @@
+-- Gaps and sample rate reports per source, so "still" can be told apart from "offline"
+CREATE TABLE IF NOT EXISTS data_quality (
+    id SERIAL PRIMARY KEY,
+    source TEXT NOT NULL,
+    kind VARCHAR(20) NOT NULL,          -- gap_start, gap_end, sample_rate
+    started_at TIMESTAMPTZ NOT NULL,
+    ended_at TIMESTAMPTZ,
+    duration_sec REAL,
+    expected_hz REAL NOT NULL,
+    effective_hz REAL,
+    irregular_sec INTEGER,
+    degraded BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS data_quality_started_at ON data_quality (started_at);
@@ -0,0 +1,15 @@
+-- Sensor faults flagged per reading, plus the alerts raised and cleared per source
+ALTER TABLE sedentary_log ADD COLUMN IF NOT EXISTS faults TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE TABLE IF NOT EXISTS device_health (
+    id SERIAL PRIMARY KEY,
+    source TEXT NOT NULL,
+    fault VARCHAR(32) NOT NULL,         -- stuck_accelerometer, stuck_pir, out_of_range, non_monotonic
+    active BOOLEAN NOT NULL,            -- Raised (true) or cleared (false)
+    since TIMESTAMPTZ NOT NULL,
+    until TIMESTAMPTZ,
+    detail TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS device_health_since ON device_health (since);
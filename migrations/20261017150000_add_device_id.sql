@@ -0,0 +1,4 @@
+-- Which device each reading came from, so one server can monitor several users
+ALTER TABLE sedentary_log ADD COLUMN IF NOT EXISTS device_id TEXT;
+
+CREATE INDEX IF NOT EXISTS sedentary_log_device_created ON sedentary_log (device_id, created_at);
@@ -0,0 +1,12 @@
+-- Raw payloads that weren't valid readings, kept for firmware debugging (newest 10,000)
+CREATE TABLE IF NOT EXISTS quarantine (
+    id BIGSERIAL PRIMARY KEY,
+    device_id TEXT NOT NULL,
+    source TEXT NOT NULL,
+    kind VARCHAR(16) NOT NULL,          -- rejected, partial
+    reason TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS quarantine_device ON quarantine (device_id, id);
@@ -0,0 +1,25 @@
+-- Classification settings per device (one device per user's desk), applied live
+CREATE TABLE IF NOT EXISTS device_settings (
+    device_id TEXT PRIMARY KEY,
+    thresh_fidget REAL NOT NULL,
+    thresh_active REAL NOT NULL,
+    alert_limit_sec INTEGER NOT NULL,
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Every change with who made it and the values before and after
+CREATE TABLE IF NOT EXISTS settings_audit (
+    id BIGSERIAL PRIMARY KEY,
+    device_id TEXT NOT NULL,
+    changed_by TEXT NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    old_fidget REAL NOT NULL,
+    old_active REAL NOT NULL,
+    old_alert_limit_sec INTEGER NOT NULL,
+    thresh_fidget REAL NOT NULL,
+    thresh_active REAL NOT NULL,
+    alert_limit_sec INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS settings_audit_device ON settings_audit (device_id, id);
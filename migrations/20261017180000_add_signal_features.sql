@@ -0,0 +1,5 @@
+-- Hjorth features behind each record, filled when the hjorth classifier is active
+ALTER TABLE sedentary_log ADD COLUMN IF NOT EXISTS hjorth_activity DOUBLE PRECISION;
+ALTER TABLE sedentary_log ADD COLUMN IF NOT EXISTS hjorth_mobility DOUBLE PRECISION;
+ALTER TABLE sedentary_log ADD COLUMN IF NOT EXISTS hjorth_complexity DOUBLE PRECISION;
+ALTER TABLE sedentary_log ADD COLUMN IF NOT EXISTS stationary BOOLEAN;
@@ -0,0 +1,32 @@
+-- Thresholds suggested by the nightly analysis, reviewed and applied through the API
+CREATE TABLE IF NOT EXISTS threshold_suggestions (
+    id BIGSERIAL PRIMARY KEY,
+    device_id TEXT NOT NULL,
+    method VARCHAR(16) NOT NULL,               -- kmeans or jenks
+    window_start TIMESTAMPTZ NOT NULL,
+    window_end TIMESTAMPTZ NOT NULL,
+    samples INTEGER NOT NULL,
+    sedentary_count INTEGER NOT NULL,          -- Records per state the server classified
+    fidget_count INTEGER NOT NULL,
+    active_count INTEGER NOT NULL,
+    centers REAL[] NOT NULL,                   -- Cluster centers, lowest first
+    current_fidget REAL NOT NULL,              -- Thresholds in use when the job ran
+    current_active REAL NOT NULL,
+    suggested_fidget REAL NOT NULL,
+    suggested_active REAL NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending', -- pending, applied or dismissed
+    reviewed_by TEXT,
+    reviewed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS threshold_suggestions_device ON threshold_suggestions (device_id, id);
+
+-- Daily summary written by the same job
+CREATE TABLE IF NOT EXISTS activity_summary (
+    date DATE PRIMARY KEY,
+    sedentary_minutes REAL NOT NULL,
+    active_minutes REAL NOT NULL,
+    dominant_state VARCHAR(20) NOT NULL,
+    activity_score INTEGER NOT NULL
+);
@@ -0,0 +1,39 @@
+-- Smoothing window from a calibration (NULL = one second of samples at the device's rate)
+ALTER TABLE device_settings ADD COLUMN IF NOT EXISTS smoothing_window INTEGER;
+ALTER TABLE settings_audit
+    ADD COLUMN IF NOT EXISTS old_smoothing_window INTEGER,
+    ADD COLUMN IF NOT EXISTS smoothing_window INTEGER;
+
+-- Guided calibration sessions (sit still, fidget, walk) and the profile they produced
+CREATE TABLE IF NOT EXISTS calibration_sessions (
+    id BIGSERIAL PRIMARY KEY,
+    device_id TEXT NOT NULL,
+    started_by TEXT NOT NULL,
+    phase_sec INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'running', -- running, completed, failed or cancelled
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ,
+    thresh_fidget REAL,
+    thresh_active REAL,
+    smoothing_window INTEGER,
+    warnings TEXT[] NOT NULL DEFAULT '{}',
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS calibration_sessions_device ON calibration_sessions (device_id, id);
+-- One running session per device
+CREATE UNIQUE INDEX IF NOT EXISTS calibration_sessions_running
+    ON calibration_sessions (device_id) WHERE status = 'running';
+
+-- Acceleration delta and PIR statistics of each phase
+CREATE TABLE IF NOT EXISTS calibration_phases (
+    session_id BIGINT NOT NULL REFERENCES calibration_sessions (id) ON DELETE CASCADE,
+    phase TEXT NOT NULL,
+    samples INTEGER NOT NULL,
+    acc_mean REAL NOT NULL,
+    acc_std REAL NOT NULL,
+    acc_p05 REAL NOT NULL,
+    acc_p95 REAL NOT NULL,
+    pir_ratio REAL NOT NULL,
+    PRIMARY KEY (session_id, phase)
+);
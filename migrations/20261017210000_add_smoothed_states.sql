@@ -0,0 +1,26 @@
+-- Offline HMM/Viterbi pass over stored history (cargo run -p analysis --bin smooth)
+CREATE TABLE IF NOT EXISTS hmm_runs (
+    id BIGSERIAL PRIMARY KEY,
+    device_id TEXT NOT NULL,
+    window_start TIMESTAMPTZ NOT NULL,
+    window_end TIMESTAMPTZ NOT NULL,
+    samples INTEGER NOT NULL,
+    segments INTEGER NOT NULL, -- Stretches between data gaps, each decoded on its own
+    states TEXT[] NOT NULL, -- Hidden states, quietest first
+    initial DOUBLE PRECISION[] NOT NULL,
+    transitions DOUBLE PRECISION[] NOT NULL, -- Row-major, from x to
+    means DOUBLE PRECISION[] NOT NULL, -- Emission mean per state, ln(acc + 1e-4)
+    stds DOUBLE PRECISION[] NOT NULL,
+    iterations INTEGER NOT NULL,
+    converged BOOLEAN NOT NULL,
+    log_likelihood DOUBLE PRECISION NOT NULL,
+    relabelled INTEGER NOT NULL, -- Records whose cleaned state differs from the real-time one
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS hmm_runs_device ON hmm_runs (device_id, id);
+
+-- Cleaned state next to the real-time label, from the latest run covering the record
+ALTER TABLE sedentary_log
+    ADD COLUMN IF NOT EXISTS smoothed_state VARCHAR(20),
+    ADD COLUMN IF NOT EXISTS hmm_run_id BIGINT REFERENCES hmm_runs (id) ON DELETE SET NULL;
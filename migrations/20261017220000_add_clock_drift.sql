@@ -0,0 +1,2 @@
+-- Device RTC rate error at each reading, once measured (positive = running fast)
+ALTER TABLE sedentary_log ADD COLUMN IF NOT EXISTS clock_drift_ppm DOUBLE PRECISION;
@@ -0,0 +1,13 @@
+-- Device of each quality and health row, next to the transport in source, so they
+-- join sedentary_log.device_id
+ALTER TABLE data_quality ADD COLUMN IF NOT EXISTS device_id TEXT;
+ALTER TABLE device_health ADD COLUMN IF NOT EXISTS device_id TEXT;
+
+-- Older rows were keyed by tcp://<id>, udp://<id> or the bare device id; serial ports stay unknown
+UPDATE data_quality SET device_id = regexp_replace(source, '^(tcp|udp)://', '')
+WHERE device_id IS NULL AND source NOT LIKE '/%';
+UPDATE device_health SET device_id = regexp_replace(source, '^(tcp|udp)://', '')
+WHERE device_id IS NULL AND source NOT LIKE '/%';
+
+CREATE INDEX IF NOT EXISTS data_quality_device_started ON data_quality (device_id, started_at);
+CREATE INDEX IF NOT EXISTS device_health_device_since ON device_health (device_id, since);
@@ -0,0 +1,6 @@
+-- One summary per device and day. Rows written before this summed every
+-- device and keep an empty device_id
+ALTER TABLE activity_summary ADD COLUMN IF NOT EXISTS device_id TEXT NOT NULL DEFAULT '';
+ALTER TABLE activity_summary ALTER COLUMN device_id DROP DEFAULT;
+ALTER TABLE activity_summary DROP CONSTRAINT IF EXISTS activity_summary_pkey;
+ALTER TABLE activity_summary ADD PRIMARY KEY (date, device_id);
@@ -0,0 +1,5 @@
+-- Names come from the request body behind one shared token; nothing checks them
+COMMENT ON COLUMN device_settings.updated_by IS 'Self-reported by the caller (changed_by), not verified';
+COMMENT ON COLUMN settings_audit.changed_by IS 'Self-reported by the caller, not verified';
+COMMENT ON COLUMN threshold_suggestions.reviewed_by IS 'Self-reported by the caller (changed_by), not verified';
+COMMENT ON COLUMN calibration_sessions.started_by IS 'Self-reported by the caller (changed_by), not verified';
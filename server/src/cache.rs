@@ -0,0 +1,129 @@
+use crate::metrics::Metrics;
+use crate::pipeline::{history_key, DEVICE_STATUS, HISTORY_DEVICES};
+use redis::aio::MultiplexedConnection;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::time::Instant;
+
+// CACHE WRITER
+const CACHE_QUEUE: usize = 4096; // Pending writes; more are dropped (the cache is best effort)
+const MAX_BATCH: usize = 256; // Writes per pipelined round trip
+const RECONNECT_AFTER: Duration = Duration::from_secs(5); // Wait after a failed connect
+const HISTORY_LEN: isize = 100; // Readings kept per device
+
+/// One write to the Redis reconnection cache
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheWrite {
+    History { device_id: String, json: String }, // Latest readings of a device
+    Status { port: String, json: String },       // Latest connection status of a port
+}
+
+/// Queue in front of the Redis writer. Pushing never waits: a slow or missing
+/// Redis costs dropped cache entries, never stalled sensor reads.
+#[derive(Clone)]
+pub struct Cache {
+    tx: mpsc::Sender<CacheWrite>,
+    metrics: Metrics,
+}
+
+impl Cache {
+    /// Starts the writer task (needs the Tokio runtime)
+    pub fn spawn(redis: redis::Client, metrics: Metrics) -> Self {
+        let (tx, rx) = mpsc::channel(CACHE_QUEUE);
+        tokio::spawn(write_batches(redis, rx, metrics.clone()));
+        Cache { tx, metrics }
+    }
+
+    pub fn push(&self, write: CacheWrite) {
+        match self.tx.try_send(write) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => self.metrics.cache_dropped(1),
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+}
+
+/// Turns a batch into one pipeline: a single LPUSH + LTRIM per device
+/// (newest last, so it ends up at the head) and one HSET for the statuses
+pub fn batch_pipeline(batch: &[CacheWrite]) -> redis::Pipeline {
+    let mut histories: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    let mut statuses: BTreeMap<&str, &str> = BTreeMap::new();
+    for write in batch {
+        match write {
+            CacheWrite::History { device_id, json } => {
+                histories.entry(device_id).or_default().push(json)
+            }
+            CacheWrite::Status { port, json } => {
+                statuses.insert(port, json);
+            }
+        }
+    }
+
+    let mut pipe = redis::pipe();
+    for (device_id, readings) in &histories {
+        let key = history_key(device_id);
+        pipe.cmd("LPUSH").arg(&key).arg(readings).ignore();
+        pipe.cmd("LTRIM")
+            .arg(&key)
+            .arg(0)
+            .arg(HISTORY_LEN - 1)
+            .ignore();
+    }
+    if !histories.is_empty() {
+        pipe.cmd("SADD")
+            .arg(HISTORY_DEVICES)
+            .arg(histories.keys().collect::<Vec<_>>())
+            .ignore();
+    }
+    if !statuses.is_empty() {
+        let fields: Vec<(&str, &str)> = statuses.into_iter().collect();
+        pipe.cmd("HSET").arg(DEVICE_STATUS).arg(fields).ignore();
+    }
+    pipe
+}
+
+// Drains the queue in batches over one persistent connection, reconnecting after errors
+async fn write_batches(redis: redis::Client, mut rx: mpsc::Receiver<CacheWrite>, metrics: Metrics) {
+    let mut con: Option<MultiplexedConnection> = None;
+    let mut retry_at = Instant::now();
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+
+    while rx.recv_many(&mut batch, MAX_BATCH).await > 0 {
+        if con.is_none() && Instant::now() >= retry_at {
+            match redis.get_multiplexed_async_connection().await {
+                Ok(connection) => {
+                    println!("Redis cache connected");
+                    con = Some(connection);
+                }
+                Err(e) => {
+                    eprintln!("Redis cache unavailable: {}", e);
+                    retry_at = Instant::now() + RECONNECT_AFTER;
+                }
+            }
+        }
+
+        let written = match con.as_mut() {
+            Some(connection) => batch_pipeline(&batch)
+                .query_async::<_, ()>(connection)
+                .await
+                .map_err(|e| {
+                    eprintln!("Redis cache write failed: {}", e);
+                    metrics.cache_error();
+                    con = None;
+                })
+                .is_ok(),
+            None => false,
+        };
+        if written {
+            metrics.cache_written(batch.len() as u64);
+        } else {
+            metrics.cache_dropped(batch.len() as u64);
+        }
+        batch.clear();
+    }
+}
+
+#[cfg(test)]
+#[path = "cache_tests.rs"]
+mod tests;
@@ -0,0 +1,103 @@
+use super::*;
+
+fn history(device_id: &str, json: &str) -> CacheWrite {
+    CacheWrite::History {
+        device_id: device_id.to_string(),
+        json: json.to_string(),
+    }
+}
+
+fn status(port: &str, json: &str) -> CacheWrite {
+    CacheWrite::Status {
+        port: port.to_string(),
+        json: json.to_string(),
+    }
+}
+
+// Commands of a pipeline, as "NAME arg arg..."
+fn commands(pipe: &redis::Pipeline) -> Vec<String> {
+    pipe.cmd_iter()
+        .map(|cmd| {
+            cmd.args_iter()
+                .map(|arg| match arg {
+                    redis::Arg::Simple(bytes) => String::from_utf8_lossy(bytes).to_string(),
+                    redis::Arg::Cursor => "<cursor>".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+// batch_pipeline Tests
+
+#[test]
+fn test_batch_groups_writes_per_device() {
+    let batch = vec![
+        history("desk-3", "a"),
+        history("desk-4", "x"),
+        history("desk-3", "b"),
+    ];
+
+    assert_eq!(
+        commands(&batch_pipeline(&batch)),
+        vec![
+            "LPUSH sensor_history:desk-3 a b",
+            "LTRIM sensor_history:desk-3 0 99",
+            "LPUSH sensor_history:desk-4 x",
+            "LTRIM sensor_history:desk-4 0 99",
+            "SADD sensor_devices desk-3 desk-4",
+        ]
+    );
+}
+
+#[test]
+fn test_batch_keeps_latest_status_per_port() {
+    let batch = vec![
+        status("/dev/ttyACM0", "connected"),
+        status("tcp://desk-4", "connected"),
+        status("/dev/ttyACM0", "disconnected"),
+    ];
+
+    assert_eq!(
+        commands(&batch_pipeline(&batch)),
+        vec!["HSET device_statuses /dev/ttyACM0 disconnected tcp://desk-4 connected"]
+    );
+}
+
+// Queue Tests
+
+#[test]
+fn test_full_queue_drops_and_counts() {
+    let (tx, _rx) = mpsc::channel(1);
+    let metrics = Metrics::default();
+    let cache = Cache {
+        tx,
+        metrics: metrics.clone(),
+    };
+
+    cache.push(history("desk-3", "a"));
+    cache.push(history("desk-3", "b"));
+
+    assert_eq!(metrics.snapshot().cache_dropped, 1);
+}
+
+#[tokio::test]
+async fn test_writes_are_dropped_while_redis_is_down() {
+    // Nothing listens on port 1
+    let redis = redis::Client::open("redis://127.0.0.1:1/").unwrap();
+    let metrics = Metrics::default();
+    let cache = Cache::spawn(redis, metrics.clone());
+
+    cache.push(history("desk-3", "a"));
+    for _ in 0..50 {
+        if metrics.snapshot().cache_dropped > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.cache_dropped, 1);
+    assert_eq!(snapshot.cache_written, 0);
+}
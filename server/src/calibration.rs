@@ -0,0 +1,558 @@
+use crate::settings::{self, DeviceSettings, SettingsUpdate};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use logic::{CalibrationPhase, CalibrationProfile, CalibrationRecorder, PhaseStats};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// CALIBRATION CONFIGURATION
+const PHASE_SEC: u64 = 30; // Length of each phase unless the request says otherwise
+const MIN_PHASE_SEC: u64 = 10;
+const MAX_PHASE_SEC: u64 = 300;
+const SETTLE_SEC: u64 = 3; // Start of each phase not recorded, while the user changes what they do
+
+/// Body of POST /api/devices/:id/calibration
+#[derive(Debug, Deserialize)]
+pub struct CalibrationRequest {
+    pub phase_sec: Option<u64>,
+    pub changed_by: String, // Who runs it, self-reported; the resulting settings change is audited under this name
+}
+
+impl CalibrationRequest {
+    fn phase_sec(&self) -> Result<u64, (StatusCode, String)> {
+        let phase_sec = self.phase_sec.unwrap_or(PHASE_SEC);
+        if !(MIN_PHASE_SEC..=MAX_PHASE_SEC).contains(&phase_sec) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("phase_sec must be {}-{}", MIN_PHASE_SEC, MAX_PHASE_SEC),
+            ));
+        }
+        Ok(phase_sec)
+    }
+}
+
+// Session in progress for one device
+#[derive(Debug)]
+struct Recording {
+    id: i64,
+    phase: CalibrationPhase,
+    record_from: Instant, // End of the settle time of the current phase
+    recorder: CalibrationRecorder,
+}
+
+/// Running calibration sessions, one per device at most. Every pipeline
+/// hands its readings over; only devices being calibrated keep them.
+#[derive(Debug, Clone, Default)]
+pub struct Calibrations {
+    sessions: Arc<Mutex<HashMap<String, Recording>>>,
+}
+
+impl Calibrations {
+    /// One reading of a device (acc is None when out of range)
+    pub fn record(&self, device: &str, acc: Option<f32>, pir: bool) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(recording) = sessions.get_mut(device) {
+            if Instant::now() >= recording.record_from {
+                recording.recorder.record(recording.phase, acc, pir);
+            }
+        }
+    }
+
+    fn start(&self, device: &str, id: i64) {
+        self.sessions.lock().unwrap().insert(
+            device.to_string(),
+            Recording {
+                id,
+                phase: CalibrationPhase::Still,
+                record_from: Instant::now(),
+                recorder: CalibrationRecorder::new(),
+            },
+        );
+    }
+
+    // False once the session was cancelled
+    fn set_phase(
+        &self,
+        device: &str,
+        id: i64,
+        phase: CalibrationPhase,
+        record_from: Instant,
+    ) -> bool {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get_mut(device) {
+            Some(recording) if recording.id == id => {
+                recording.phase = phase;
+                recording.record_from = record_from;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Ends a session and hands back what it recorded
+    fn finish(&self, device: &str, id: i64) -> Option<CalibrationRecorder> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get(device) {
+            Some(recording) if recording.id == id => {
+                sessions.remove(device).map(|recording| recording.recorder)
+            }
+            _ => None,
+        }
+    }
+
+    // Id of the cancelled session, if one was running
+    fn cancel(&self, device: &str) -> Option<i64> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .remove(device)
+            .map(|recording| recording.id)
+    }
+
+    // Current phase and the statistics recorded so far
+    fn progress(&self, device: &str, id: i64) -> Option<(CalibrationPhase, Vec<PhaseStats>)> {
+        let sessions = self.sessions.lock().unwrap();
+        let recording = sessions.get(device).filter(|r| r.id == id)?;
+        let stats = CalibrationPhase::ALL
+            .iter()
+            .map(|phase| recording.recorder.stats(*phase))
+            .filter(|stats| stats.samples > 0)
+            .collect();
+        Some((recording.phase, stats))
+    }
+}
+
+/// Calibration progress for the dashboard
+/// Format: {"event":"calibration","device_id":"desk-3","session_id":7,"status":"running","phase":"fidget","instruction":"...","phase_ends_at":"..."}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CalibrationEvent {
+    pub event: String, // Always "calibration"
+    pub device_id: String,
+    pub session_id: i64,
+    pub status: String, // running, completed, failed or cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<CalibrationPhase>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instruction: Option<String>, // What the user should do now
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase_ends_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>, // Failure reason or warnings
+}
+
+impl CalibrationEvent {
+    fn new(device: &str, session_id: i64, status: &str) -> Self {
+        CalibrationEvent {
+            event: "calibration".to_string(),
+            device_id: device.to_string(),
+            session_id,
+            status: status.to_string(),
+            phase: None,
+            instruction: None,
+            phase_ends_at: None,
+            detail: None,
+        }
+    }
+
+    fn phase(
+        device: &str,
+        session_id: i64,
+        phase: CalibrationPhase,
+        ends_at: DateTime<Utc>,
+    ) -> Self {
+        CalibrationEvent {
+            phase: Some(phase),
+            instruction: Some(phase.instruction().to_string()),
+            phase_ends_at: Some(ends_at),
+            ..Self::new(device, session_id, "running")
+        }
+    }
+
+    fn failed(device: &str, session_id: i64, reason: String) -> Self {
+        CalibrationEvent {
+            detail: Some(reason),
+            ..Self::new(device, session_id, "failed")
+        }
+    }
+}
+
+/// One session as stored
+#[derive(Debug, Serialize)]
+pub struct CalibrationSession {
+    pub id: i64,
+    pub device_id: String,
+    pub started_by: String,
+    pub phase_sec: i32,
+    pub status: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub thresh_fidget: Option<f32>,
+    pub thresh_active: Option<f32>,
+    pub smoothing_window: Option<i32>,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Latest session of a device with its phase statistics (so far, while running)
+#[derive(Debug, Serialize)]
+pub struct CalibrationStatus {
+    #[serde(flatten)]
+    pub session: CalibrationSession,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<CalibrationPhase>, // Current phase while running
+    pub phases: Vec<PhaseStats>,
+}
+
+// The device's settings with a calibration profile applied (alert limit unchanged)
+fn calibrated(
+    old: &DeviceSettings,
+    profile: &CalibrationProfile,
+    changed_by: &str,
+) -> Result<DeviceSettings, String> {
+    old.updated(&SettingsUpdate {
+        fidget: Some(profile.thresholds.fidget),
+        active: Some(profile.thresholds.active),
+        alert_limit_sec: None,
+        smoothing_window: Some(Some(profile.smoothing_window)),
+        changed_by: changed_by.to_string(),
+    })
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+fn publish(state: &AppState, event: &CalibrationEvent) {
+    let _ = state.tx.send(serde_json::to_string(event).unwrap());
+}
+
+/// Sessions still running when the server stopped can't finish any more
+pub async fn abandon_running(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE calibration_sessions
+        SET status = 'failed', finished_at = NOW(), error = 'server restarted'
+        WHERE status = 'running'
+        "#
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+async fn store_phases(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+    phases: &[PhaseStats],
+) -> Result<(), sqlx::Error> {
+    for stats in phases {
+        sqlx::query!(
+            r#"
+            INSERT INTO calibration_phases
+                (session_id, phase, samples, acc_mean, acc_std, acc_p05, acc_p95, pir_ratio)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            id,
+            stats.phase.as_str(),
+            stats.samples as i32,
+            stats.acc_mean,
+            stats.acc_std,
+            stats.acc_p05,
+            stats.acc_p95,
+            stats.pir_ratio
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn close(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    status: &str,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE calibration_sessions
+        SET status = $2, error = $3, finished_at = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        status,
+        error
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+// Stores the outcome of a session; a usable profile becomes the device's settings.
+// Returns the event for the dashboard.
+async fn complete(
+    state: &AppState,
+    device: &str,
+    id: i64,
+    started_by: &str,
+    recorder: &CalibrationRecorder,
+) -> Result<CalibrationEvent, sqlx::Error> {
+    let live = state.sink.settings();
+    let _writing = live.lock_writes().await;
+    let mut tx = state.db.begin().await?;
+    let old = settings::current(&mut tx, device).await?;
+    let outcome = recorder
+        .profile()
+        .and_then(|profile| calibrated(&old, &profile, started_by).map(|new| (new, profile)));
+
+    let event = match outcome {
+        Ok((new, profile)) => {
+            store_phases(&mut tx, id, &profile.phases).await?;
+            sqlx::query!(
+                r#"
+                UPDATE calibration_sessions
+                SET thresh_fidget = $2, thresh_active = $3, smoothing_window = $4, warnings = $5
+                WHERE id = $1
+                "#,
+                id,
+                profile.thresholds.fidget,
+                profile.thresholds.active,
+                profile.smoothing_window as i32,
+                &profile.warnings
+            )
+            .execute(&mut *tx)
+            .await?;
+            close(&mut *tx, id, "completed", None).await?;
+            settings::store(&mut tx, device, &old, &new, started_by).await?;
+            tx.commit().await?;
+
+            live.set(device, new);
+            println!(
+                "Calibration {} of {} applied: fidget {}, active {}, smoothing {} samples",
+                id,
+                device,
+                profile.thresholds.fidget,
+                profile.thresholds.active,
+                profile.smoothing_window
+            );
+            CalibrationEvent {
+                detail: (!profile.warnings.is_empty()).then(|| profile.warnings.join("; ")),
+                ..CalibrationEvent::new(device, id, "completed")
+            }
+        }
+        Err(reason) => {
+            let phases: Vec<PhaseStats> = CalibrationPhase::ALL
+                .iter()
+                .map(|phase| recorder.stats(*phase))
+                .collect();
+            store_phases(&mut tx, id, &phases).await?;
+            close(&mut *tx, id, "failed", Some(&reason)).await?;
+            tx.commit().await?;
+
+            println!("Calibration {} of {} failed: {}", id, device, reason);
+            CalibrationEvent::failed(device, id, reason)
+        }
+    };
+    Ok(event)
+}
+
+// Walks the device through the phases, then derives and stores the profile
+async fn run_session(state: AppState, device: String, id: i64, phase_sec: u64, started_by: String) {
+    let calibrations = state.sink.calibrations();
+    for phase in CalibrationPhase::ALL {
+        let record_from = Instant::now() + Duration::from_secs(SETTLE_SEC);
+        if !calibrations.set_phase(&device, id, phase, record_from) {
+            return; // Cancelled
+        }
+        let ends_at = Utc::now() + TimeDelta::seconds(phase_sec as i64);
+        publish(
+            &state,
+            &CalibrationEvent::phase(&device, id, phase, ends_at),
+        );
+        tokio::time::sleep(Duration::from_secs(phase_sec)).await;
+    }
+
+    let Some(recorder) = calibrations.finish(&device, id) else {
+        return;
+    };
+    conclude(&state, &device, id, &started_by, &recorder).await;
+}
+
+// Publishes the outcome. When it can't be stored the session is marked failed
+// on its own, outside the rolled back transaction, so the device can start again.
+async fn conclude(
+    state: &AppState,
+    device: &str,
+    id: i64,
+    started_by: &str,
+    recorder: &CalibrationRecorder,
+) {
+    let event = match complete(state, device, id, started_by, recorder).await {
+        Ok(event) => event,
+        Err(e) => {
+            eprintln!("Failed to store calibration {} of {}: {}", id, device, e);
+            let reason = format!("could not be stored: {}", e);
+            if let Err(e) = close(&state.db, id, "failed", Some(&reason)).await {
+                eprintln!("Failed to close calibration {} of {}: {}", id, device, e);
+            }
+            CalibrationEvent::failed(device, id, reason)
+        }
+    };
+    publish(state, &event);
+}
+
+// POST /api/devices/:id/calibration
+pub async fn start_calibration(
+    State(state): State<AppState>,
+    Path(device): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<CalibrationRequest>,
+) -> Result<(StatusCode, Json<CalibrationEvent>), (StatusCode, String)> {
+    state.commands.authorize(&headers)?;
+    let started_by = settings::changed_by(&request.changed_by)?;
+    let phase_sec = request.phase_sec()?;
+
+    // The unique index allows one running session per device
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO calibration_sessions (device_id, started_by, phase_sec)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        RETURNING id
+        "#,
+        device,
+        started_by,
+        phase_sec as i32
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or((
+        StatusCode::CONFLICT,
+        format!("{} is already being calibrated", device),
+    ))?;
+
+    state.sink.calibrations().start(&device, id);
+    println!(
+        "Calibration {} of {} started by {} ({}s per phase)",
+        id, device, started_by, phase_sec
+    );
+    tokio::spawn(run_session(
+        state.clone(),
+        device.clone(),
+        id,
+        phase_sec,
+        started_by,
+    ));
+
+    let ends_at = Utc::now() + TimeDelta::seconds(phase_sec as i64);
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(CalibrationEvent::phase(
+            &device,
+            id,
+            CalibrationPhase::Still,
+            ends_at,
+        )),
+    ))
+}
+
+// GET /api/devices/:id/calibration
+pub async fn get_calibration(
+    State(state): State<AppState>,
+    Path(device): Path<String>,
+) -> Result<Json<CalibrationStatus>, (StatusCode, String)> {
+    let session = sqlx::query_as!(
+        CalibrationSession,
+        r#"
+        SELECT id, device_id, started_by, phase_sec, status, started_at, finished_at,
+               thresh_fidget, thresh_active, smoothing_window, warnings, error
+        FROM calibration_sessions
+        WHERE device_id = $1
+        ORDER BY id DESC
+        LIMIT 1
+        "#,
+        device
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(db_error)?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        format!("{} was never calibrated", device),
+    ))?;
+
+    if let Some((phase, phases)) = state.sink.calibrations().progress(&device, session.id) {
+        return Ok(Json(CalibrationStatus {
+            session,
+            phase: Some(phase),
+            phases,
+        }));
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT phase, samples, acc_mean, acc_std, acc_p05, acc_p95, pir_ratio
+        FROM calibration_phases
+        WHERE session_id = $1
+        "#,
+        session.id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+    let mut phases: Vec<PhaseStats> = rows
+        .into_iter()
+        .filter_map(|row| {
+            Some(PhaseStats {
+                phase: row.phase.parse().ok()?,
+                samples: row.samples as usize,
+                acc_mean: row.acc_mean,
+                acc_std: row.acc_std,
+                acc_p05: row.acc_p05,
+                acc_p95: row.acc_p95,
+                pir_ratio: row.pir_ratio,
+            })
+        })
+        .collect();
+    phases.sort_by_key(|stats| stats.phase as usize);
+
+    Ok(Json(CalibrationStatus {
+        session,
+        phase: None,
+        phases,
+    }))
+}
+
+// DELETE /api/devices/:id/calibration
+pub async fn cancel_calibration(
+    State(state): State<AppState>,
+    Path(device): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state.commands.authorize(&headers)?;
+    let id = state.sink.calibrations().cancel(&device).ok_or((
+        StatusCode::NOT_FOUND,
+        format!("{} is not being calibrated", device),
+    ))?;
+
+    close(&state.db, id, "cancelled", None)
+        .await
+        .map_err(db_error)?;
+
+    publish(&state, &CalibrationEvent::new(&device, id, "cancelled"));
+    println!("Calibration {} of {} cancelled", id, device);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+#[path = "calibration_tests.rs"]
+mod tests;
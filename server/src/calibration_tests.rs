@@ -0,0 +1,158 @@
+use super::*;
+use crate::commands::Commands;
+use crate::pipeline::Sink;
+use sqlx::postgres::PgPoolOptions;
+use tokio::sync::broadcast;
+
+fn request(phase_sec: Option<u64>) -> CalibrationRequest {
+    CalibrationRequest {
+        phase_sec,
+        changed_by: "nurse-1".to_string(),
+    }
+}
+
+// Request Tests
+
+#[test]
+fn test_request_phase_sec_defaults_and_limits() {
+    assert_eq!(request(None).phase_sec().unwrap(), PHASE_SEC);
+    assert_eq!(
+        request(Some(MIN_PHASE_SEC)).phase_sec().unwrap(),
+        MIN_PHASE_SEC
+    );
+    for phase_sec in [0, MIN_PHASE_SEC - 1, MAX_PHASE_SEC + 1] {
+        let error = request(Some(phase_sec)).phase_sec().unwrap_err();
+        assert_eq!(error.0, StatusCode::BAD_REQUEST);
+    }
+}
+
+// Recording Tests
+
+#[test]
+fn test_record_ignores_devices_not_calibrated() {
+    let calibrations = Calibrations::default();
+    calibrations.record("desk-3", Some(0.01), false);
+    assert!(calibrations.progress("desk-3", 1).is_none());
+
+    calibrations.start("desk-3", 1);
+    calibrations.record("desk-3", Some(0.01), false);
+    calibrations.record("desk-4", Some(0.50), true);
+
+    let (phase, stats) = calibrations.progress("desk-3", 1).unwrap();
+    assert_eq!(phase, CalibrationPhase::Still);
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].samples, 1);
+}
+
+#[test]
+fn test_record_skips_settle_time_and_follows_phase() {
+    let calibrations = Calibrations::default();
+    calibrations.start("desk-3", 1);
+
+    let later = Instant::now() + Duration::from_secs(60);
+    assert!(calibrations.set_phase("desk-3", 1, CalibrationPhase::Fidget, later));
+    calibrations.record("desk-3", Some(0.05), false);
+    assert!(calibrations.progress("desk-3", 1).unwrap().1.is_empty());
+
+    assert!(calibrations.set_phase("desk-3", 1, CalibrationPhase::Walk, Instant::now()));
+    calibrations.record("desk-3", Some(0.2), true);
+    let recorder = calibrations.finish("desk-3", 1).unwrap();
+    assert_eq!(recorder.samples(CalibrationPhase::Fidget), 0);
+    assert_eq!(recorder.samples(CalibrationPhase::Walk), 1);
+    // Finished sessions stop recording
+    assert!(calibrations.finish("desk-3", 1).is_none());
+}
+
+#[test]
+fn test_cancelled_session_stops_its_task() {
+    let calibrations = Calibrations::default();
+    calibrations.start("desk-3", 7);
+
+    assert_eq!(calibrations.cancel("desk-3"), Some(7));
+    assert_eq!(calibrations.cancel("desk-3"), None);
+    assert!(!calibrations.set_phase("desk-3", 7, CalibrationPhase::Fidget, Instant::now()));
+    assert!(calibrations.finish("desk-3", 7).is_none());
+
+    // A newer session isn't touched by the old task
+    calibrations.start("desk-3", 8);
+    assert!(!calibrations.set_phase("desk-3", 7, CalibrationPhase::Walk, Instant::now()));
+    assert!(calibrations.finish("desk-3", 7).is_none());
+    assert!(calibrations.progress("desk-3", 8).is_some());
+}
+
+// Profile Tests
+
+#[test]
+fn test_calibrated_keeps_alert_limit() {
+    let old = DeviceSettings {
+        alert_limit_sec: 900,
+        ..DeviceSettings::default()
+    };
+    let profile = CalibrationProfile {
+        thresholds: logic::Thresholds {
+            fidget: 0.03,
+            active: 0.08,
+        },
+        smoothing_window: 15,
+        phases: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let new = calibrated(&old, &profile, "nurse-1").unwrap();
+    assert_eq!(new.thresholds, profile.thresholds);
+    assert_eq!(new.smoothing_window, Some(15));
+    assert_eq!(new.alert_limit_sec, 900);
+}
+
+// Event Tests
+
+#[test]
+fn test_event_format() {
+    let ends_at = "2026-10-17T10:00:30Z".parse().unwrap();
+    let event = CalibrationEvent::phase("desk-3", 7, CalibrationPhase::Fidget, ends_at);
+    let json = serde_json::to_value(&event).unwrap();
+
+    assert_eq!(json["event"], "calibration");
+    assert_eq!(json["device_id"], "desk-3");
+    assert_eq!(json["status"], "running");
+    assert_eq!(json["phase"], "fidget");
+    assert_eq!(json["phase_ends_at"], "2026-10-17T10:00:30Z");
+    assert!(json.get("detail").is_none());
+
+    let cancelled = serde_json::to_value(CalibrationEvent::new("desk-3", 7, "cancelled")).unwrap();
+    assert!(cancelled.get("phase").is_none());
+}
+
+#[tokio::test]
+async fn test_unstored_outcome_is_published_as_failed() {
+    let (tx, mut rx) = broadcast::channel(16);
+    let redis = redis::Client::open("redis://127.0.0.1:1/").unwrap();
+    let sink = Sink::new(tx.clone(), redis);
+    // Nothing listens there, so storing the outcome fails
+    let db = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(200))
+        .connect_lazy("postgres://postgres@127.0.0.1:1/sedentary_tracker")
+        .unwrap();
+    let state = AppState {
+        db,
+        tx,
+        redis: redis::Client::open("redis://127.0.0.1:1/").unwrap(),
+        pipelines: Arc::default(),
+        sink,
+        commands: Commands::default(),
+    };
+
+    conclude(
+        &state,
+        "desk-3",
+        7,
+        "nurse-1",
+        &CalibrationRecorder::default(),
+    )
+    .await;
+
+    let event: CalibrationEvent = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(event.session_id, 7);
+    assert_eq!(event.status, "failed");
+    assert!(event.detail.unwrap().starts_with("could not be stored"));
+}
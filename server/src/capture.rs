@@ -0,0 +1,243 @@
+use crate::framing::{decode_frame, frame_from_capture};
+use crate::models::RawReading;
+use crate::pipeline::{Pipeline, Sink};
+use crate::source::{parse_line, SensorSource};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// One raw line exactly as it arrived, before any parsing.
+/// Capture files are NDJSON, one entry per line.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CaptureEntry {
+    pub at: DateTime<Utc>, // Wall-clock arrival time
+    pub source: String,    // Ingestion path, e.g. "serial:/dev/ttyACM0", "tcp://10.0.0.5:53122"
+    pub line: String,
+}
+
+/// Appends every raw line received by the ingestion path to a capture file
+/// (enabled via CAPTURE_FILE). Shared by all sources through the Sink.
+#[derive(Clone)]
+pub struct Capture {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl Capture {
+    pub fn from_env() -> Option<Self> {
+        let path = env::var("CAPTURE_FILE").ok()?;
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(file) => {
+                println!("Capturing raw sensor input to {}", path);
+                Some(Capture::from_writer(file))
+            }
+            Err(e) => {
+                eprintln!("Cannot open CAPTURE_FILE '{}': {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn from_writer(writer: impl Write + Send + 'static) -> Self {
+        Capture {
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+        }
+    }
+
+    pub fn record(&self, source: &str, line: &str) {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            return;
+        }
+
+        let entry = CaptureEntry {
+            at: Utc::now(),
+            source: source.to_string(),
+            line: line.to_string(),
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+
+        // Flushed per line so a crash doesn't lose the interesting part
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(e) = writeln!(writer, "{}", json).and_then(|_| writer.flush()) {
+            eprintln!("Capture write error: {}", e);
+        }
+    }
+}
+
+/// How fast a capture is played back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    Factor(f64), // 1.0 = original timing, 10.0 = ten times faster
+    Max,         // No waiting between lines
+}
+
+impl ReplaySpeed {
+    fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim().to_ascii_lowercase();
+        if raw == "max" {
+            return Some(ReplaySpeed::Max);
+        }
+        match raw.trim_end_matches('x').parse::<f64>() {
+            Ok(factor) if factor > 0.0 && factor.is_finite() => Some(ReplaySpeed::Factor(factor)),
+            _ => None,
+        }
+    }
+}
+
+/// Replay settings, read from the environment (REPLAY_FILE, REPLAY_SPEED)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayConfig {
+    pub path: String,
+    pub speed: ReplaySpeed,
+}
+
+impl ReplayConfig {
+    pub fn from_env() -> Option<Self> {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    fn from_lookup(get: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let path = get("REPLAY_FILE")?;
+
+        let speed = match get("REPLAY_SPEED") {
+            None => ReplaySpeed::Factor(1.0),
+            Some(raw) => ReplaySpeed::parse(&raw).unwrap_or_else(|| {
+                eprintln!("Invalid REPLAY_SPEED '{}', using 1x", raw);
+                ReplaySpeed::Factor(1.0)
+            }),
+        };
+
+        Some(ReplayConfig { path, speed })
+    }
+}
+
+/// Time from the start of the replay at which an entry is due
+fn replay_offset(first: DateTime<Utc>, at: DateTime<Utc>, speed: ReplaySpeed) -> Duration {
+    match speed {
+        ReplaySpeed::Max => Duration::ZERO,
+        ReplaySpeed::Factor(factor) => (at - first)
+            .to_std()
+            .unwrap_or(Duration::ZERO)
+            .div_f64(factor),
+    }
+}
+
+/// Plays a capture file back as a sensor source, keeping the original
+/// spacing between lines (scaled by the speed)
+pub struct ReplaySource<R> {
+    name: String,
+    reader: R,
+    speed: ReplaySpeed,
+    started: Option<(DateTime<Utc>, Instant)>,
+    line: String,
+}
+
+impl<R: BufRead> ReplaySource<R> {
+    pub fn new(name: &str, reader: R, speed: ReplaySpeed) -> Self {
+        ReplaySource {
+            name: name.to_string(),
+            reader,
+            speed,
+            started: None,
+            line: String::new(),
+        }
+    }
+
+    fn wait_for(&mut self, at: DateTime<Utc>) {
+        let (first, started) = *self.started.get_or_insert((at, Instant::now()));
+        let due = started + replay_offset(first, at, self.speed);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+    }
+}
+
+impl<R: BufRead + Send> SensorSource for ReplaySource<R> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_reading(&mut self) -> io::Result<Option<RawReading>> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "end of capture",
+            ));
+        }
+
+        let Ok(entry) = serde_json::from_str::<CaptureEntry>(self.line.trim()) else {
+            return Ok(None);
+        };
+        self.wait_for(entry.at);
+
+        // Debug output and corrupted frames were captured too, they are skipped like they were live
+        let reading = match frame_from_capture(&entry.line) {
+            Some(frame) => decode_frame(&frame).ok(),
+            None => parse_line(&entry.line),
+        };
+        let Some(mut reading) = reading else {
+            return Ok(None);
+        };
+        // Keep the original arrival time so device clocks resolve as they did live
+        reading.received_at = Some(entry.at);
+        // Keep interleaved devices apart, like the live listeners did
+        if reading.device.is_none() {
+            reading.device = Some(entry.source);
+        }
+        Ok(Some(reading))
+    }
+}
+
+/// Feeds a capture file through the full pipeline (classification, Redis,
+/// WebSocket, DB) on a dedicated thread, then stops
+pub fn spawn_replay(config: ReplayConfig, sink: Sink) {
+    thread::spawn(move || {
+        let file = match File::open(&config.path) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("Cannot open REPLAY_FILE '{}': {}", config.path, e);
+                return;
+            }
+        };
+
+        let mut source = ReplaySource::new(&config.path, BufReader::new(file), config.speed);
+        let mut pipelines: HashMap<String, Pipeline> = HashMap::new();
+        let mut replayed: u64 = 0;
+
+        let origin = format!("replay://{}", config.path);
+        println!("Replaying {} at {:?}...", config.path, config.speed);
+        let end = loop {
+            match source.next_reading() {
+                Ok(Some(reading)) => {
+                    let device = reading.device.clone().unwrap_or_default();
+                    let pipeline = pipelines
+                        .entry(device.clone())
+                        .or_insert_with(|| sink.new_pipeline(&device));
+                    let output = pipeline.process(reading);
+                    sink.publish(&output);
+                    sink.publish_quality(&origin, &device, pipeline.take_quality());
+                    sink.publish_health(&origin, &device, pipeline.take_health());
+                    replayed += 1;
+                }
+                Ok(None) => continue,
+                Err(e) => break e,
+            }
+        };
+        println!(
+            "Replay of {} finished after {} readings: {}",
+            config.path, replayed, end
+        );
+    });
+}
+
+#[cfg(test)]
+#[path = "capture_tests.rs"]
+mod tests;
@@ -0,0 +1,210 @@
+use super::*;
+use chrono::TimeZone;
+use std::io::Cursor;
+
+// Write target the tests can read back
+#[derive(Clone, Default)]
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuf {
+    fn entries(&self) -> Vec<CaptureEntry> {
+        let bytes = self.0.lock().unwrap().clone();
+        String::from_utf8(bytes)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+}
+
+fn config_from(vars: &[(&str, &str)]) -> Option<ReplayConfig> {
+    ReplayConfig::from_lookup(|key| {
+        vars.iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.to_string())
+    })
+}
+
+fn at(secs: i64, millis: u32) -> DateTime<Utc> {
+    Utc.timestamp_opt(1_760_000_000 + secs, millis * 1_000_000)
+        .unwrap()
+}
+
+fn capture_line(at: DateTime<Utc>, source: &str, line: &str) -> String {
+    let entry = CaptureEntry {
+        at,
+        source: source.to_string(),
+        line: line.to_string(),
+    };
+    format!("{}\n", serde_json::to_string(&entry).unwrap())
+}
+
+// Capture Tests
+
+#[test]
+fn test_capture_records_raw_lines() {
+    let buf = SharedBuf::default();
+    let capture = Capture::from_writer(buf.clone());
+
+    capture.record("/dev/ttyACM0", "MPU6050 found\r\n");
+    capture.record(
+        "/dev/ttyACM0",
+        "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}\r\n",
+    );
+    capture.record("/dev/ttyACM0", "\r\n"); // Blank lines are not worth keeping
+
+    let entries = buf.entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].source, "/dev/ttyACM0");
+    assert_eq!(entries[0].line, "MPU6050 found");
+    assert_eq!(
+        entries[1].line,
+        "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}"
+    );
+    assert!(entries[0].at <= entries[1].at);
+}
+
+#[test]
+fn test_capture_is_shared_between_clones() {
+    let buf = SharedBuf::default();
+    let capture = Capture::from_writer(buf.clone());
+
+    capture.clone().record("tcp://10.0.0.5:5000", "a");
+    capture.record("udp://10.0.0.6:5000", "b");
+
+    assert_eq!(buf.entries().len(), 2);
+}
+
+// Replay Config Tests
+
+#[test]
+fn test_replay_disabled_without_file() {
+    assert_eq!(config_from(&[("REPLAY_SPEED", "10")]), None);
+}
+
+#[test]
+fn test_replay_speed_parsing() {
+    let speed = |raw: &str| {
+        config_from(&[("REPLAY_FILE", "session.ndjson"), ("REPLAY_SPEED", raw)])
+            .unwrap()
+            .speed
+    };
+
+    assert_eq!(
+        config_from(&[("REPLAY_FILE", "session.ndjson")]).unwrap(),
+        ReplayConfig {
+            path: "session.ndjson".to_string(),
+            speed: ReplaySpeed::Factor(1.0),
+        }
+    );
+    assert_eq!(speed("10"), ReplaySpeed::Factor(10.0));
+    assert_eq!(speed("2.5x"), ReplaySpeed::Factor(2.5));
+    assert_eq!(speed("MAX"), ReplaySpeed::Max);
+    // Invalid values fall back to real time
+    assert_eq!(speed("0"), ReplaySpeed::Factor(1.0));
+    assert_eq!(speed("fast"), ReplaySpeed::Factor(1.0));
+}
+
+#[test]
+fn test_replay_offset_scales_with_speed() {
+    let first = at(0, 0);
+
+    assert_eq!(
+        replay_offset(first, at(10, 0), ReplaySpeed::Factor(1.0)),
+        Duration::from_secs(10)
+    );
+    assert_eq!(
+        replay_offset(first, at(10, 0), ReplaySpeed::Factor(4.0)),
+        Duration::from_millis(2500)
+    );
+    assert_eq!(
+        replay_offset(first, at(10, 0), ReplaySpeed::Max),
+        Duration::ZERO
+    );
+    // Clock stepped backwards during capture
+    assert_eq!(
+        replay_offset(first, at(-5, 0), ReplaySpeed::Factor(1.0)),
+        Duration::ZERO
+    );
+}
+
+// ReplaySource Tests
+
+#[test]
+fn test_replay_source_yields_captured_readings() {
+    let input = [
+        capture_line(at(0, 0), "/dev/ttyACM0", "MPU6050 found"),
+        capture_line(
+            at(0, 100),
+            "/dev/ttyACM0",
+            "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}",
+        ),
+        "not a capture entry\n".to_string(),
+        capture_line(
+            at(0, 200),
+            "tcp://10.0.0.5:5000",
+            "{\"ts\":\"10:00:00\",\"pir\":1,\"acc\":0.2,\"device\":\"esp32-1\"}",
+        ),
+    ]
+    .concat();
+    let mut source = ReplaySource::new("session", Cursor::new(input), ReplaySpeed::Max);
+
+    assert_eq!(source.name(), "session");
+    assert_eq!(source.next_reading().unwrap(), None); // debug line
+    let serial = source.next_reading().unwrap().unwrap();
+    assert_eq!(serial.device.as_deref(), Some("/dev/ttyACM0"));
+    assert_eq!(source.next_reading().unwrap(), None); // unreadable entry
+    let tcp = source.next_reading().unwrap().unwrap();
+    assert_eq!(tcp.device.as_deref(), Some("esp32-1"));
+    assert_eq!(tcp.pir, 1);
+
+    let err = source.next_reading().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_replay_source_keeps_original_spacing() {
+    let reading = "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}";
+    let input = [
+        capture_line(at(0, 0), "serial", reading),
+        capture_line(at(0, 400), "serial", reading),
+    ]
+    .concat();
+    let mut source = ReplaySource::new("session", Cursor::new(input), ReplaySpeed::Factor(4.0));
+
+    let started = Instant::now();
+    source.next_reading().unwrap().unwrap();
+    source.next_reading().unwrap().unwrap();
+
+    // 400ms captured at 4x is ~100ms
+    let elapsed = started.elapsed();
+    assert!(elapsed >= Duration::from_millis(100), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(390), "{:?}", elapsed);
+}
+
+#[test]
+fn test_capture_round_trips_through_replay() {
+    let buf = SharedBuf::default();
+    let capture = Capture::from_writer(buf.clone());
+    capture.record(
+        "udp://10.0.0.6:5000",
+        "{\"ts\":\"11:00:00\",\"pir\":0,\"acc\":0.03}",
+    );
+
+    let bytes = buf.0.lock().unwrap().clone();
+    let mut source = ReplaySource::new("session", Cursor::new(bytes), ReplaySpeed::Max);
+    let reading = source.next_reading().unwrap().unwrap();
+
+    assert_eq!(reading.ts, "11:00:00");
+    assert_eq!(reading.device.as_deref(), Some("udp://10.0.0.6:5000"));
+}
@@ -0,0 +1,111 @@
+use axum::response::Json;
+use logic::{
+    classifier_by_name, Classifier, StateMachineConfig, Thresholds, CLASSIFIERS, SMOOTHING_WINDOW,
+};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+const DEFAULT_CLASSIFIER: &str = "threshold";
+
+/// Classifier named by CLASSIFIER (threshold, acceleration or hjorth), the default when unset or unknown
+pub fn from_env() -> Box<dyn Classifier> {
+    from_lookup(|key| env::var(key).ok())
+}
+
+// Split out from from_env so selection can be tested without touching the process env
+pub fn from_lookup(get: impl Fn(&str) -> Option<String>) -> Box<dyn Classifier> {
+    let name = get("CLASSIFIER").map(|name| name.trim().to_lowercase());
+    let name = match name.as_deref() {
+        Some(name) if CLASSIFIERS.contains(&name) => name.to_string(),
+        Some(name) => {
+            eprintln!(
+                "Unknown CLASSIFIER '{}' (expected one of {}), using {}",
+                name,
+                CLASSIFIERS.join(", "),
+                DEFAULT_CLASSIFIER
+            );
+            DEFAULT_CLASSIFIER.to_string()
+        }
+        None => DEFAULT_CLASSIFIER.to_string(),
+    };
+    classifier_by_name(&name, Thresholds::default()).expect("listed in CLASSIFIERS")
+}
+
+/// Hysteresis and dwell from HYSTERESIS_EXIT_RATIO / MIN_DWELL_MS, the defaults when unset or invalid
+pub fn state_machine_from_env() -> StateMachineConfig {
+    state_machine_from_lookup(|key| env::var(key).ok())
+}
+
+pub fn state_machine_from_lookup(get: impl Fn(&str) -> Option<String>) -> StateMachineConfig {
+    let defaults = StateMachineConfig::default();
+    let exit_ratio = match get("HYSTERESIS_EXIT_RATIO") {
+        Some(raw) => raw
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|ratio| *ratio > 0.0 && *ratio <= 1.0)
+            .unwrap_or_else(|| {
+                eprintln!(
+                    "Invalid HYSTERESIS_EXIT_RATIO '{}' (expected 0-1), using {}",
+                    raw, defaults.exit_ratio
+                );
+                defaults.exit_ratio
+            }),
+        None => defaults.exit_ratio,
+    };
+    let min_dwell_ms = match get("MIN_DWELL_MS") {
+        Some(raw) => raw
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|ms| *ms >= 0)
+            .unwrap_or_else(|| {
+                eprintln!(
+                    "Invalid MIN_DWELL_MS '{}', using {}",
+                    raw, defaults.min_dwell_ms
+                );
+                defaults.min_dwell_ms
+            }),
+        None => defaults.min_dwell_ms,
+    };
+    StateMachineConfig {
+        exit_ratio,
+        min_dwell_ms,
+    }
+}
+
+/// Active classification settings, so the nightly job and the dashboard don't
+/// keep their own copies of the thresholds
+/// Format: {"name":"threshold","thresholds":{"fidget":0.02,"active":0.04},"state_machine":{"exit_ratio":0.8,"min_dwell_ms":2000},"smoothing_window":10,"available":["threshold","acceleration","hjorth"]}
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ClassifierInfo {
+    pub name: String,
+    pub thresholds: Thresholds,
+    pub state_machine: StateMachineConfig, // Hysteresis and dwell applied to the classifier output
+    pub smoothing_window: usize, // Samples at the default 10Hz; a hello with another rate changes it
+    pub available: Vec<String>,
+}
+
+impl ClassifierInfo {
+    pub fn of(classifier: &dyn Classifier, state_machine: StateMachineConfig) -> Self {
+        ClassifierInfo {
+            name: classifier.name().to_string(),
+            thresholds: classifier.thresholds(),
+            state_machine,
+            smoothing_window: SMOOTHING_WINDOW,
+            available: CLASSIFIERS.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+// GET /api/classifier
+pub async fn get_classifier() -> Json<ClassifierInfo> {
+    Json(ClassifierInfo::of(
+        from_env().as_ref(),
+        state_machine_from_env(),
+    ))
+}
+
+#[cfg(test)]
+#[path = "classifier_tests.rs"]
+mod tests;
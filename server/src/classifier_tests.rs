@@ -0,0 +1,68 @@
+use super::*;
+use std::collections::HashMap;
+
+fn lookup(vars: &[(&str, &str)]) -> HashMap<String, String> {
+    vars.iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn classifier_from(vars: &[(&str, &str)]) -> Box<dyn Classifier> {
+    let vars = lookup(vars);
+    from_lookup(|key| vars.get(key).cloned())
+}
+
+fn state_machine_from(vars: &[(&str, &str)]) -> StateMachineConfig {
+    let vars = lookup(vars);
+    state_machine_from_lookup(|key| vars.get(key).cloned())
+}
+
+#[test]
+fn test_default_classifier() {
+    assert_eq!(classifier_from(&[]).name(), "threshold");
+}
+
+#[test]
+fn test_classifier_from_env() {
+    assert_eq!(
+        classifier_from(&[("CLASSIFIER", " Acceleration ")]).name(),
+        "acceleration"
+    );
+}
+
+#[test]
+fn test_unknown_classifier_falls_back() {
+    assert_eq!(
+        classifier_from(&[("CLASSIFIER", "neural")]).name(),
+        "threshold"
+    );
+}
+
+#[test]
+fn test_classifier_info_format() {
+    let info = ClassifierInfo::of(classifier_from(&[]).as_ref(), StateMachineConfig::default());
+
+    let json = serde_json::to_string(&info).unwrap();
+    assert_eq!(
+        json,
+        r#"{"name":"threshold","thresholds":{"fidget":0.02,"active":0.04},"state_machine":{"exit_ratio":0.8,"min_dwell_ms":2000},"smoothing_window":10,"available":["threshold","acceleration","hjorth"]}"#
+    );
+}
+
+#[test]
+fn test_state_machine_from_env() {
+    assert_eq!(state_machine_from(&[]), StateMachineConfig::default());
+    assert_eq!(
+        state_machine_from(&[("HYSTERESIS_EXIT_RATIO", "0.9"), ("MIN_DWELL_MS", "0")]),
+        StateMachineConfig {
+            exit_ratio: 0.9,
+            min_dwell_ms: 0,
+        }
+    );
+}
+
+#[test]
+fn test_invalid_state_machine_falls_back() {
+    let config = state_machine_from(&[("HYSTERESIS_EXIT_RATIO", "1.5"), ("MIN_DWELL_MS", "-1")]);
+    assert_eq!(config, StateMachineConfig::default());
+}
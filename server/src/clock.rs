@@ -0,0 +1,142 @@
+use crate::models::Timing;
+use chrono::{DateTime, Days, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Utc};
+use std::collections::VecDeque;
+
+// CLOCK SYNC
+// Transport delay only ever adds to the observed offset, so the smallest
+// offset seen recently is the best estimate of the real one
+const OFFSET_WINDOW: TimeDelta = TimeDelta::minutes(10);
+const DRIFT_MIN_SPAN: TimeDelta = TimeDelta::minutes(10); // Sync time needed before drift is reported
+const CLOCK_STEP_MS: i64 = 2000; // Estimate jumps beyond this are a clock set, not drift
+const SAME_SESSION: TimeDelta = TimeDelta::hours(1); // Previous reading still dates the next one
+
+const SECONDS_PER_DAY: u32 = 86_400;
+
+/// Parses the Arduino "HH:MM:SS" time of day
+pub fn parse_ts(ts: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(ts.trim(), "%H:%M:%S").ok()
+}
+
+/// Sort key that orders times of day relative to `first` (up to 12h before
+/// or after it), so a batch spanning midnight stays in order
+pub fn order_key(ts: &str, first: &str) -> Option<u32> {
+    let seconds = |ts| parse_ts(ts).map(|t| t.num_seconds_from_midnight());
+    let (ts, first) = (seconds(ts)?, seconds(first).unwrap_or(0));
+    Some((ts + SECONDS_PER_DAY + SECONDS_PER_DAY / 2 - first) % SECONDS_PER_DAY)
+}
+
+/// Turns one device's date-less RTC readings into real timestamps: resolves the
+/// date (including midnight rollover), estimates the offset to the server clock
+/// and tracks how fast the RTC drifts
+#[derive(Debug, Default)]
+pub struct DeviceClock {
+    last: Option<(NaiveDateTime, DateTime<Utc>)>, // Previous device time and when it arrived
+    window: VecDeque<(DateTime<Utc>, i64)>,       // (arrival, offset ms), offsets increasing
+    offset_ms: Option<i64>,
+    drift_anchor: Option<(DateTime<Utc>, i64)>, // Start of the drift measurement
+}
+
+impl DeviceClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stamp(&mut self, ts: &str, received_at: DateTime<Utc>) -> Timing {
+        let Some(time) = parse_ts(ts) else {
+            return Timing {
+                measured_at: received_at,
+                received_at,
+                device_time: None,
+                clock_offset_ms: None,
+                clock_drift_ppm: None,
+            };
+        };
+
+        let device_time = self.resolve(time, received_at);
+        // The RTC going back was set (a set_time correcting a fast clock): the
+        // smaller offsets seen before no longer apply. A larger offset alone
+        // can also be upload delay, which the window has to ride out.
+        if self
+            .last
+            .is_some_and(|(last, _)| (last - device_time).num_milliseconds() > CLOCK_STEP_MS)
+        {
+            self.window.clear();
+            self.drift_anchor = None;
+        }
+        let offset = (received_at.naive_utc() - device_time).num_milliseconds();
+        let (estimate, observed_at) = self.track(received_at, offset);
+        self.last = Some((device_time, received_at));
+
+        // The RTC only has whole seconds; arrival time places the reading within its second
+        let start = device_time.and_utc() + TimeDelta::milliseconds(estimate);
+        let measured_at = received_at.clamp(start, start + TimeDelta::milliseconds(999));
+
+        Timing {
+            measured_at,
+            received_at,
+            device_time: Some(device_time.and_utc()),
+            clock_offset_ms: Some(estimate),
+            clock_drift_ppm: self.drift_ppm(observed_at, estimate),
+        }
+    }
+
+    // Picks the date that puts this time of day closest to where the device clock
+    // should be: right after the previous reading, or now minus the known offset
+    fn resolve(&self, time: NaiveTime, received_at: DateTime<Utc>) -> NaiveDateTime {
+        let reference = match (self.last, self.offset_ms) {
+            (Some((last, at)), _) if received_at - at < SAME_SESSION => last,
+            (_, Some(offset)) => received_at.naive_utc() - TimeDelta::milliseconds(offset),
+            _ => received_at.naive_utc(),
+        };
+        let today = reference.date();
+        [
+            today.checked_sub_days(Days::new(1)),
+            Some(today),
+            today.checked_add_days(Days::new(1)),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|date| date.and_time(time))
+        .min_by_key(|candidate| (*candidate - reference).abs())
+        .unwrap()
+    }
+
+    // Sliding minimum of the observed offsets over OFFSET_WINDOW, with the
+    // arrival time of the reading it came from
+    fn track(&mut self, at: DateTime<Utc>, offset: i64) -> (i64, DateTime<Utc>) {
+        while self.window.back().is_some_and(|&(_, o)| o >= offset) {
+            self.window.pop_back();
+        }
+        self.window.push_back((at, offset));
+        while self
+            .window
+            .front()
+            .is_some_and(|&(t, _)| at - t > OFFSET_WINDOW)
+        {
+            self.window.pop_front();
+        }
+        let (observed_at, estimate) = self.window.front().copied().unwrap_or((at, offset));
+
+        // A step means the RTC was set, restart the drift measurement
+        match self.offset_ms {
+            Some(previous) if (estimate - previous).abs() <= CLOCK_STEP_MS => {}
+            _ => self.drift_anchor = Some((observed_at, estimate)),
+        }
+        self.offset_ms = Some(estimate);
+        (estimate, observed_at)
+    }
+
+    fn drift_ppm(&self, observed_at: DateTime<Utc>, estimate: i64) -> Option<f64> {
+        let (since, start) = self.drift_anchor?;
+        let span = observed_at - since;
+        if span < DRIFT_MIN_SPAN {
+            return None;
+        }
+        // A shrinking offset means the device clock gains on the server
+        Some((start - estimate) as f64 / span.num_milliseconds() as f64 * 1e6)
+    }
+}
+
+#[cfg(test)]
+#[path = "clock_tests.rs"]
+mod tests;
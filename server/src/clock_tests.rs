@@ -0,0 +1,193 @@
+use super::*;
+use chrono::TimeZone;
+
+// Server time on 2026-10-17
+fn at(h: u32, m: u32, s: u32, ms: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 17, h, m, s).unwrap() + TimeDelta::milliseconds(ms)
+}
+
+// What the RTC prints for a given device time
+fn ts(device: DateTime<Utc>) -> String {
+    device.format("%H:%M:%S").to_string()
+}
+
+// parse_ts / order_key Tests
+
+#[test]
+fn test_parse_ts() {
+    assert_eq!(parse_ts("09:05:03"), NaiveTime::from_hms_opt(9, 5, 3));
+    assert_eq!(parse_ts(" 23:59:59\r"), NaiveTime::from_hms_opt(23, 59, 59));
+    assert_eq!(parse_ts("25:00:00"), None);
+    assert_eq!(parse_ts("10:00"), None);
+}
+
+#[test]
+fn test_order_key_spans_midnight() {
+    let mut batch = vec!["00:00:01", "23:59:58", "00:00:00", "23:59:59"];
+    batch.sort_by_key(|t| order_key(t, "23:59:58"));
+
+    assert_eq!(batch, vec!["23:59:58", "23:59:59", "00:00:00", "00:00:01"]);
+}
+
+// DeviceClock Tests
+
+#[test]
+fn test_stamp_resolves_date_and_offset() {
+    let mut clock = DeviceClock::new();
+    // RTC 3 seconds ahead of the server
+    let timing = clock.stamp("10:00:03", at(10, 0, 0, 0));
+
+    assert_eq!(timing.device_time, Some(at(10, 0, 3, 0)));
+    assert_eq!(timing.clock_offset_ms, Some(-3000));
+    assert_eq!(timing.measured_at, at(10, 0, 0, 0));
+    assert_eq!(timing.received_at, at(10, 0, 0, 0));
+    assert_eq!(timing.clock_drift_ppm, None);
+}
+
+#[test]
+fn test_stamp_unreadable_ts_falls_back_to_arrival() {
+    let mut clock = DeviceClock::new();
+    let timing = clock.stamp("garbage", at(10, 0, 0, 0));
+
+    assert_eq!(timing.measured_at, at(10, 0, 0, 0));
+    assert_eq!(timing.device_time, None);
+    assert_eq!(timing.clock_offset_ms, None);
+}
+
+#[test]
+fn test_stamp_first_reading_near_midnight() {
+    let mut clock = DeviceClock::new();
+    // Server just past midnight, RTC still on the previous day
+    let timing = clock.stamp("23:59:58", at(0, 0, 1, 0));
+
+    assert_eq!(
+        timing.device_time,
+        Some(at(23, 59, 58, 0) - TimeDelta::days(1))
+    );
+    assert_eq!(timing.clock_offset_ms, Some(3000));
+}
+
+#[test]
+fn test_stamp_rolls_over_midnight() {
+    let mut clock = DeviceClock::new();
+    let start = at(23, 59, 58, 0);
+    let mut times = Vec::new();
+    for second in 0..4 {
+        let now = start + TimeDelta::seconds(second);
+        times.push(clock.stamp(&ts(now), now).device_time.unwrap());
+    }
+
+    assert_eq!(times[1], at(23, 59, 59, 0));
+    assert_eq!(times[2], at(0, 0, 0, 0) + TimeDelta::days(1));
+    assert!(times
+        .windows(2)
+        .all(|w| w[1] - w[0] == TimeDelta::seconds(1)));
+}
+
+#[test]
+fn test_stamp_after_long_gap_uses_offset() {
+    let mut clock = DeviceClock::new();
+    clock.stamp("10:00:00", at(10, 0, 5, 0));
+
+    // Back 20 hours later, after midnight: the offset places it on the next day
+    let later = at(6, 0, 5, 0) + TimeDelta::days(1);
+    let timing = clock.stamp("06:00:00", later);
+
+    assert_eq!(
+        timing.device_time,
+        Some(at(6, 0, 0, 0) + TimeDelta::days(1))
+    );
+    assert_eq!(timing.clock_offset_ms, Some(5000));
+}
+
+#[test]
+fn test_offset_ignores_transport_delay() {
+    let mut clock = DeviceClock::new();
+    clock.stamp("10:00:00", at(10, 0, 0, 50));
+
+    // A reading that sat in a buffer for 2 seconds doesn't move the estimate
+    let delayed = clock.stamp("10:00:01", at(10, 0, 3, 0));
+    assert_eq!(delayed.clock_offset_ms, Some(50));
+    assert_eq!(delayed.measured_at, at(10, 0, 2, 49));
+
+    // A faster path lowers it
+    let fast = clock.stamp("10:00:04", at(10, 0, 4, 20));
+    assert_eq!(fast.clock_offset_ms, Some(20));
+}
+
+#[test]
+fn test_measured_at_spreads_samples_within_second() {
+    let mut clock = DeviceClock::new();
+    let mut measured = Vec::new();
+    for tenth in 0..10 {
+        let now = at(10, 0, 0, tenth * 100);
+        measured.push(clock.stamp("10:00:00", now).measured_at);
+    }
+
+    assert_eq!(measured[0], at(10, 0, 0, 0));
+    assert_eq!(measured[9], at(10, 0, 0, 900));
+    assert!(measured.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn test_clock_set_back_takes_effect_at_once() {
+    let mut clock = DeviceClock::new();
+    clock.stamp("10:00:00", at(10, 0, 0, 0));
+    clock.stamp("10:00:01", at(10, 0, 1, 0));
+
+    // RTC set back by a minute while streaming
+    let set = clock.stamp("09:59:02", at(10, 0, 2, 0));
+    assert_eq!(set.clock_offset_ms, Some(60_000));
+    assert_eq!(set.measured_at, at(10, 0, 2, 0));
+    assert_eq!(set.clock_drift_ppm, None);
+    let next = clock.stamp("09:59:03", at(10, 0, 3, 0));
+    assert_eq!(next.clock_offset_ms, Some(60_000));
+}
+
+#[test]
+fn test_offset_window_expires() {
+    let mut clock = DeviceClock::new();
+    clock.stamp("10:00:00", at(10, 0, 0, 0));
+
+    // After a gap a larger offset may be upload delay: the old minimum holds
+    // until the window passes
+    let soon = clock.stamp("10:04:00", at(10, 5, 0, 0));
+    assert_eq!(soon.clock_offset_ms, Some(0));
+    let later = clock.stamp("10:10:00", at(10, 11, 0, 0));
+    assert_eq!(later.clock_offset_ms, Some(60_000));
+}
+
+// Device runs `ppm` fast or slow, sampled at 50Hz
+fn run_drift(ppm: f64, minutes: i64) -> Option<f64> {
+    let mut clock = DeviceClock::new();
+    let start = at(10, 0, 0, 0);
+    let mut last = None;
+    for step in 0..(minutes * 60 * 50) {
+        let now = start + TimeDelta::milliseconds(step * 20);
+        let elapsed_ms = (step * 20) as f64;
+        let device = start + TimeDelta::milliseconds((elapsed_ms * (1.0 + ppm / 1e6)) as i64);
+        last = clock.stamp(&ts(device), now).clock_drift_ppm;
+    }
+    last
+}
+
+#[test]
+fn test_drift_of_fast_clock() {
+    let drift = run_drift(1000.0, 12).unwrap();
+    assert!((drift - 1000.0).abs() < 100.0, "drift {}", drift);
+}
+
+#[test]
+fn test_drift_of_slow_clock() {
+    // A growing offset is only trusted once the old minimum left the window
+    assert_eq!(run_drift(-1000.0, 12), None);
+    let drift = run_drift(-1000.0, 22).unwrap();
+    assert!((drift + 1000.0).abs() < 150.0, "drift {}", drift);
+}
+
+#[test]
+fn test_drift_needs_time() {
+    let mut clock = DeviceClock::new();
+    let timing = clock.stamp("10:05:00", at(10, 5, 0, 0));
+    assert_eq!(timing.clock_drift_ppm, None);
+}
@@ -0,0 +1,387 @@
+use crate::handshake::Hello;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+// LIMITS
+const MAX_SAMPLE_RATE_HZ: u32 = 100;
+const MAX_ALERT_MS: u32 = 10_000;
+const MAX_RECORDS: usize = 200; // Oldest command records are dropped past this
+pub const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Alert sent to devices that report a buzzer or vibration motor in their hello
+pub const ALERT_DURATION_MS: u32 = 500;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertPattern {
+    Buzz,
+    Vibrate,
+}
+
+/// Configuration pushed to a device
+/// Request body: {"type":"set_rate","rate_hz":20}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Command {
+    SetRate {
+        rate_hz: u32,
+    },
+    // On-device classification thresholds (g), as used by the firmware LEDs
+    SetThresholds {
+        fidget: f32,
+        active: f32,
+    },
+    // RTC time, defaults to the server clock when the request leaves it out
+    SetTime {
+        #[serde(default)]
+        epoch: Option<i64>,
+    },
+    Alert {
+        pattern: AlertPattern,
+        duration_ms: u32,
+    },
+}
+
+impl Command {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Command::SetRate { rate_hz } if *rate_hz == 0 || *rate_hz > MAX_SAMPLE_RATE_HZ => {
+                Err(format!(
+                    "sample rate {}Hz outside 1-{}Hz",
+                    rate_hz, MAX_SAMPLE_RATE_HZ
+                ))
+            }
+            Command::SetThresholds { fidget, active }
+                if !fidget.is_finite()
+                    || !active.is_finite()
+                    || *fidget <= 0.0
+                    || fidget >= active =>
+            {
+                Err(format!(
+                    "thresholds must satisfy 0 < fidget < active (got {} / {})",
+                    fidget, active
+                ))
+            }
+            Command::SetTime { epoch: Some(epoch) } if *epoch < 0 => {
+                Err(format!("invalid epoch {}", epoch))
+            }
+            Command::Alert { duration_ms, .. }
+                if *duration_ms == 0 || *duration_ms > MAX_ALERT_MS =>
+            {
+                Err(format!(
+                    "alert duration {}ms outside 1-{}ms",
+                    duration_ms, MAX_ALERT_MS
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The line written to the device
+/// Format: {"cmd":{"id":17,"type":"set_rate","rate_hz":20}}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DeviceCommand {
+    pub id: u64,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+#[derive(Serialize)]
+struct CommandLine<'a> {
+    cmd: &'a DeviceCommand,
+}
+
+impl DeviceCommand {
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\n",
+            serde_json::to_string(&CommandLine { cmd: self }).unwrap()
+        )
+    }
+}
+
+/// Device reply to a command: {"ack":{"id":17,"ok":true}} or {"ack":{"id":17,"ok":false,"error":"unsupported"}}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Ack {
+    pub id: u64,
+    pub ok: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AckLine {
+    ack: Ack,
+}
+
+/// Parses an ack line; readings and debug output return None
+pub fn parse_ack(line: &str) -> Option<Ack> {
+    let clean_line = line.trim();
+    if !clean_line.starts_with("{\"ack\"") {
+        return None;
+    }
+    serde_json::from_str::<AckLine>(clean_line)
+        .ok()
+        .map(|l| l.ack)
+}
+
+/// Picks the alert a device can physically produce, from its hello
+pub fn alert_pattern(hello: &Hello) -> Option<AlertPattern> {
+    if hello.sensors.iter().any(|s| s == "buzzer") {
+        Some(AlertPattern::Buzz)
+    } else if hello.sensors.iter().any(|s| s == "vibration") {
+        Some(AlertPattern::Vibrate)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandStatus {
+    Queued,   // Waiting for the link writer
+    Sent,     // Written, waiting for the ack
+    Acked,    // Device confirmed
+    Failed,   // Write error or negative ack
+    TimedOut, // No ack within ACK_TIMEOUT
+}
+
+/// Tracking entry returned by the API
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommandRecord {
+    pub id: u64,
+    pub device: String,
+    pub command: Command,
+    pub status: CommandStatus,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub acked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    links: HashMap<String, mpsc::Sender<DeviceCommand>>,
+    records: VecDeque<CommandRecord>,
+}
+
+/// Outgoing command queues for connected devices, plus ack tracking.
+/// A link registers a channel under each name the device is known by
+/// (port, hello id); its writer drains the channel onto the wire.
+#[derive(Clone, Default)]
+pub struct Commands {
+    token: Option<String>, // COMMAND_TOKEN, API disabled when unset
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Commands {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    fn from_lookup(get: impl Fn(&str) -> Option<String>) -> Self {
+        Commands {
+            token: get("COMMAND_TOKEN").filter(|t| !t.trim().is_empty()),
+            registry: Arc::default(),
+        }
+    }
+
+    /// Checks the "Authorization: Bearer <token>" header
+    pub fn authorize(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let Some(token) = &self.token else {
+            return Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "command API disabled (COMMAND_TOKEN not set)".to_string(),
+            ));
+        };
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if constant_time_eq(given.as_bytes(), token.as_bytes()) {
+            Ok(())
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "invalid token".to_string()))
+        }
+    }
+
+    pub fn attach(&self, device: &str, tx: mpsc::Sender<DeviceCommand>) {
+        let mut registry = self.registry.lock().unwrap();
+        registry.links.insert(device.to_string(), tx);
+    }
+
+    /// Drops the link; its writer exits once every name is detached
+    pub fn detach(&self, devices: &[String]) {
+        let mut registry = self.registry.lock().unwrap();
+        for device in devices {
+            registry.links.remove(device);
+        }
+    }
+
+    pub fn is_connected(&self, device: &str) -> bool {
+        self.registry.lock().unwrap().links.contains_key(device)
+    }
+
+    /// Validates and queues a command for a connected device
+    pub fn send(
+        &self,
+        device: &str,
+        command: Command,
+    ) -> Result<CommandRecord, (StatusCode, String)> {
+        command
+            .validate()
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        let command = match command {
+            Command::SetTime { epoch: None } => Command::SetTime {
+                epoch: Some(Utc::now().timestamp()),
+            },
+            other => other,
+        };
+
+        let mut registry = self.registry.lock().unwrap();
+        let Some(tx) = registry.links.get(device).cloned() else {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("device '{}' not connected", device),
+            ));
+        };
+
+        registry.next_id += 1;
+        let record = CommandRecord {
+            id: registry.next_id,
+            device: device.to_string(),
+            command: command.clone(),
+            status: CommandStatus::Queued,
+            error: None,
+            created_at: Utc::now(),
+            sent_at: None,
+            acked_at: None,
+        };
+        if tx
+            .send(DeviceCommand {
+                id: record.id,
+                command,
+            })
+            .is_err()
+        {
+            // Writer already gone (link dropping), don't keep a dead entry
+            registry.links.remove(device);
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("device '{}' not connected", device),
+            ));
+        }
+
+        registry.records.push_back(record.clone());
+        if registry.records.len() > MAX_RECORDS {
+            registry.records.pop_front();
+        }
+        Ok(record)
+    }
+
+    /// Called by the link writer once the line is on the wire (or failed to get there)
+    pub fn mark_sent(&self, id: u64, result: Result<(), String>) {
+        self.update(id, |record| match result {
+            Ok(()) => {
+                record.status = CommandStatus::Sent;
+                record.sent_at = Some(Utc::now());
+            }
+            Err(e) => {
+                record.status = CommandStatus::Failed;
+                record.error = Some(e);
+            }
+        });
+    }
+
+    /// Called by the link reader for every ack line
+    pub fn acknowledge(&self, ack: Ack) {
+        let found = self.update(ack.id, |record| {
+            record.acked_at = Some(Utc::now());
+            if ack.ok {
+                record.status = CommandStatus::Acked;
+            } else {
+                record.status = CommandStatus::Failed;
+                record.error = Some(ack.error.unwrap_or_else(|| "rejected".to_string()));
+            }
+        });
+        if !found {
+            eprintln!("Ack for unknown command {}", ack.id);
+        }
+    }
+
+    /// Commands for a device, newest first, with overdue acks marked as timed out
+    pub fn records(&self, device: &str) -> Vec<CommandRecord> {
+        let now = Utc::now();
+        let mut registry = self.registry.lock().unwrap();
+        registry
+            .records
+            .iter_mut()
+            .rev()
+            .filter(|r| r.device == device)
+            .map(|record| {
+                if let (CommandStatus::Sent, Some(sent_at)) = (record.status, record.sent_at) {
+                    if (now - sent_at).to_std().unwrap_or_default() > ACK_TIMEOUT {
+                        record.status = CommandStatus::TimedOut;
+                    }
+                }
+                record.clone()
+            })
+            .collect()
+    }
+
+    fn update(&self, id: u64, apply: impl FnOnce(&mut CommandRecord)) -> bool {
+        let mut registry = self.registry.lock().unwrap();
+        match registry.records.iter_mut().find(|r| r.id == id) {
+            Some(record) => {
+                apply(record);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// Compares every byte so the response time doesn't leak the matching prefix
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// POST /api/devices/:id/commands
+pub async fn send_command(
+    State(state): State<AppState>,
+    Path(device): Path<String>,
+    headers: HeaderMap,
+    Json(command): Json<Command>,
+) -> Result<(StatusCode, Json<CommandRecord>), (StatusCode, String)> {
+    state.commands.authorize(&headers)?;
+    let record = state.commands.send(&device, command)?;
+    println!("Command {} queued for {}", record.id, device);
+    Ok((StatusCode::ACCEPTED, Json(record)))
+}
+
+// GET /api/devices/:id/commands
+pub async fn list_commands(
+    State(state): State<AppState>,
+    Path(device): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<CommandRecord>>, (StatusCode, String)> {
+    state.commands.authorize(&headers)?;
+    Ok(Json(state.commands.records(&device)))
+}
+
+#[cfg(test)]
+#[path = "commands_tests.rs"]
+mod tests;
@@ -0,0 +1,322 @@
+use super::*;
+use axum::http::HeaderValue;
+
+fn commands(token: Option<&str>) -> Commands {
+    Commands::from_lookup(|key| match key {
+        "COMMAND_TOKEN" => token.map(str::to_string),
+        _ => None,
+    })
+}
+
+fn bearer(token: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::AUTHORIZATION,
+        HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+    );
+    headers
+}
+
+fn connected(device: &str) -> (Commands, mpsc::Receiver<DeviceCommand>) {
+    let commands = commands(Some("secret"));
+    let (tx, rx) = mpsc::channel();
+    commands.attach(device, tx);
+    (commands, rx)
+}
+
+// Wire Format Tests
+
+#[test]
+fn test_command_line_format() {
+    let command = DeviceCommand {
+        id: 17,
+        command: Command::SetRate { rate_hz: 20 },
+    };
+
+    assert_eq!(
+        command.to_line(),
+        "{\"cmd\":{\"id\":17,\"type\":\"set_rate\",\"rate_hz\":20}}\n"
+    );
+}
+
+#[test]
+fn test_command_request_body() {
+    let body = r#"{"type":"alert","pattern":"vibrate","duration_ms":300}"#;
+
+    assert_eq!(
+        serde_json::from_str::<Command>(body).unwrap(),
+        Command::Alert {
+            pattern: AlertPattern::Vibrate,
+            duration_ms: 300
+        }
+    );
+    assert_eq!(
+        serde_json::from_str::<Command>(r#"{"type":"set_time"}"#).unwrap(),
+        Command::SetTime { epoch: None }
+    );
+    assert!(serde_json::from_str::<Command>(r#"{"type":"reboot"}"#).is_err());
+}
+
+#[test]
+fn test_parse_ack() {
+    assert_eq!(
+        parse_ack("{\"ack\":{\"id\":3,\"ok\":true}}\r\n"),
+        Some(Ack {
+            id: 3,
+            ok: true,
+            error: None
+        })
+    );
+    assert_eq!(
+        parse_ack(r#"{"ack":{"id":4,"ok":false,"error":"unsupported"}}"#)
+            .unwrap()
+            .error,
+        Some("unsupported".to_string())
+    );
+    assert_eq!(
+        parse_ack("{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}"),
+        None
+    );
+    assert_eq!(parse_ack("{\"ack\":{\"ok\":true}}"), None);
+}
+
+// Validation Tests
+
+#[test]
+fn test_validate_commands() {
+    assert!(Command::SetRate { rate_hz: 50 }.validate().is_ok());
+    assert!(Command::SetRate { rate_hz: 0 }.validate().is_err());
+    assert!(Command::SetRate { rate_hz: 500 }.validate().is_err());
+
+    assert!(Command::SetThresholds {
+        fidget: 0.02,
+        active: 0.1
+    }
+    .validate()
+    .is_ok());
+    assert!(Command::SetThresholds {
+        fidget: 0.2,
+        active: 0.1
+    }
+    .validate()
+    .is_err());
+    assert!(Command::SetThresholds {
+        fidget: f32::NAN,
+        active: 0.1
+    }
+    .validate()
+    .is_err());
+
+    assert!(Command::SetTime { epoch: Some(-1) }.validate().is_err());
+    assert!(Command::Alert {
+        pattern: AlertPattern::Buzz,
+        duration_ms: 60_000
+    }
+    .validate()
+    .is_err());
+}
+
+#[test]
+fn test_alert_pattern_from_hello() {
+    let mut hello = Hello {
+        device: "desk-3".to_string(),
+        firmware: "1.4.0".to_string(),
+        protocol: 2,
+        rate_hz: 10,
+        sensors: vec!["pir".to_string(), "mpu6050".to_string()],
+    };
+    assert_eq!(alert_pattern(&hello), None);
+
+    hello.sensors.push("vibration".to_string());
+    assert_eq!(alert_pattern(&hello), Some(AlertPattern::Vibrate));
+
+    hello.sensors.push("buzzer".to_string());
+    assert_eq!(alert_pattern(&hello), Some(AlertPattern::Buzz));
+}
+
+// Auth Tests
+
+#[test]
+fn test_authorize() {
+    let commands = commands(Some("secret"));
+
+    assert!(commands.authorize(&bearer("secret")).is_ok());
+    assert_eq!(
+        commands.authorize(&bearer("wrong")).unwrap_err().0,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        commands.authorize(&HeaderMap::new()).unwrap_err().0,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[test]
+fn test_authorize_disabled_without_token() {
+    assert_eq!(
+        commands(None).authorize(&bearer("")).unwrap_err().0,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(
+        commands(Some("  ")).authorize(&bearer("  ")).unwrap_err().0,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+}
+
+#[test]
+fn test_constant_time_eq() {
+    assert!(constant_time_eq(b"abc", b"abc"));
+    assert!(!constant_time_eq(b"abc", b"abd"));
+    assert!(!constant_time_eq(b"abc", b"abcd"));
+}
+
+// Queue and Ack Tracking Tests
+
+#[test]
+fn test_send_queues_for_writer() {
+    let (commands, rx) = connected("desk-3");
+
+    let record = commands
+        .send("desk-3", Command::SetRate { rate_hz: 20 })
+        .unwrap();
+
+    assert_eq!(record.status, CommandStatus::Queued);
+    assert_eq!(
+        rx.try_recv().unwrap(),
+        DeviceCommand {
+            id: record.id,
+            command: Command::SetRate { rate_hz: 20 }
+        }
+    );
+}
+
+#[test]
+fn test_send_fills_server_time() {
+    let (commands, rx) = connected("desk-3");
+
+    commands
+        .send("desk-3", Command::SetTime { epoch: None })
+        .unwrap();
+
+    match rx.try_recv().unwrap().command {
+        Command::SetTime { epoch: Some(epoch) } => {
+            assert!((epoch - Utc::now().timestamp()).abs() <= 1)
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn test_send_rejects_unknown_device_and_bad_command() {
+    let (commands, _rx) = connected("desk-3");
+
+    assert_eq!(
+        commands
+            .send("desk-9", Command::SetRate { rate_hz: 20 })
+            .unwrap_err()
+            .0,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(
+        commands
+            .send("desk-3", Command::SetRate { rate_hz: 0 })
+            .unwrap_err()
+            .0,
+        StatusCode::BAD_REQUEST
+    );
+    assert!(commands.records("desk-3").is_empty());
+}
+
+#[test]
+fn test_send_after_detach_fails() {
+    let (commands, rx) = connected("desk-3");
+    commands.detach(&["desk-3".to_string()]);
+    drop(rx);
+
+    assert!(!commands.is_connected("desk-3"));
+    assert_eq!(
+        commands
+            .send("desk-3", Command::SetRate { rate_hz: 20 })
+            .unwrap_err()
+            .0,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[test]
+fn test_ack_lifecycle() {
+    let (commands, _rx) = connected("desk-3");
+    let ok = commands
+        .send("desk-3", Command::SetRate { rate_hz: 20 })
+        .unwrap();
+    let rejected = commands
+        .send("desk-3", Command::SetRate { rate_hz: 50 })
+        .unwrap();
+
+    commands.mark_sent(ok.id, Ok(()));
+    commands.mark_sent(rejected.id, Ok(()));
+    assert_eq!(commands.records("desk-3")[1].status, CommandStatus::Sent);
+
+    commands.acknowledge(Ack {
+        id: ok.id,
+        ok: true,
+        error: None,
+    });
+    commands.acknowledge(Ack {
+        id: rejected.id,
+        ok: false,
+        error: Some("rate not supported".to_string()),
+    });
+
+    // Newest first
+    let records = commands.records("desk-3");
+    assert_eq!(records[1].status, CommandStatus::Acked);
+    assert!(records[1].acked_at.is_some());
+    assert_eq!(records[0].status, CommandStatus::Failed);
+    assert_eq!(records[0].error.as_deref(), Some("rate not supported"));
+}
+
+#[test]
+fn test_write_failure_marks_failed() {
+    let (commands, _rx) = connected("desk-3");
+    let record = commands
+        .send("desk-3", Command::SetRate { rate_hz: 20 })
+        .unwrap();
+
+    commands.mark_sent(record.id, Err("broken pipe".to_string()));
+
+    assert_eq!(commands.records("desk-3")[0].status, CommandStatus::Failed);
+}
+
+#[test]
+fn test_missing_ack_times_out() {
+    let (commands, _rx) = connected("desk-3");
+    let record = commands
+        .send("desk-3", Command::SetRate { rate_hz: 20 })
+        .unwrap();
+    commands.mark_sent(record.id, Ok(()));
+
+    // Pretend the line went out a while ago
+    commands.update(record.id, |r| {
+        r.sent_at = Some(Utc::now() - chrono::Duration::seconds(10))
+    });
+
+    assert_eq!(
+        commands.records("desk-3")[0].status,
+        CommandStatus::TimedOut
+    );
+}
+
+#[test]
+fn test_records_are_bounded() {
+    let (commands, _rx) = connected("desk-3");
+    for _ in 0..MAX_RECORDS + 5 {
+        commands
+            .send("desk-3", Command::SetRate { rate_hz: 20 })
+            .unwrap();
+    }
+
+    let records = commands.records("desk-3");
+    assert_eq!(records.len(), MAX_RECORDS);
+    assert_eq!(records[0].id, (MAX_RECORDS + 5) as u64);
+}
@@ -1,28 +1,183 @@
+use crate::handshake::HelloEvent;
+use crate::health::HealthAlert;
+use crate::metrics::Metrics;
 use crate::models::ProcessedState;
+use crate::quality::QualityEvent;
+use crate::quarantine::{self, QuarantinedLine};
 use sqlx::PgPool;
-use tokio::sync::broadcast;
+use tokio::sync::broadcast::{self, error::RecvError};
 
-pub async fn spawn_db_worker(pool: PgPool, mut rx: broadcast::Receiver<String>) {
+pub async fn spawn_db_worker(pool: PgPool, mut rx: broadcast::Receiver<String>, metrics: Metrics) {
     tokio::spawn(async move {
         println!("Logic Logger Started...");
 
-        while let Ok(json_msg) = rx.recv().await {
+        loop {
+            // A slow database makes the worker miss the oldest messages rather than stall ingestion
+            let json_msg = match rx.recv().await {
+                Ok(msg) => msg,
+                Err(RecvError::Lagged(missed)) => {
+                    metrics.broadcast_lagged("DB worker", missed);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
             // We deserialize the PROCESSED output, not the raw input
             if let Ok(data) = serde_json::from_str::<ProcessedState>(&json_msg) {
                 // Save to 'sedentary_log'
                 // We use valid data derived from our Logic Engine
+                let imu = data.imu;
+                let timing = data.timing.as_ref();
+                let features = data.features;
+                let faults: Vec<String> =
+                    data.faults.iter().map(|f| f.as_str().to_string()).collect();
                 let result = sqlx::query!(
                     r#"
-                    INSERT INTO sedentary_log (state, timer_seconds, acceleration_val)
-                    VALUES ($1, $2, $3)
+                    INSERT INTO sedentary_log
+                        (device_id, state, timer_seconds, unknown_seconds, acceleration_val, magnitude,
+                         ax, ay, az, gx, gy, gz,
+                         device_time, received_at, measured_at, clock_offset_ms, clock_drift_ppm,
+                         faults, hjorth_activity, hjorth_mobility, hjorth_complexity, stationary)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                            $18, $19, $20, $21, $22)
                     "#,
-                    data.state,
+                    Some(&data.device_id).filter(|id| !id.is_empty()),
+                    data.state.as_str(),
                     data.timer as i32,
-                    data.val
+                    data.unknown_sec as i32,
+                    data.val,
+                    data.magnitude,
+                    imu.map(|i| i.ax),
+                    imu.map(|i| i.ay),
+                    imu.map(|i| i.az),
+                    imu.and_then(|i| i.gx),
+                    imu.and_then(|i| i.gy),
+                    imu.and_then(|i| i.gz),
+                    timing.and_then(|t| t.device_time),
+                    timing.map(|t| t.received_at),
+                    timing.map(|t| t.measured_at),
+                    timing.and_then(|t| t.clock_offset_ms),
+                    timing.and_then(|t| t.clock_drift_ppm),
+                    &faults,
+                    features.map(|f| f.hjorth_activity),
+                    features.map(|f| f.hjorth_mobility),
+                    features.map(|f| f.hjorth_complexity),
+                    features.map(|f| f.stationarity_passed)
+                )
+                .execute(&pool)
+                .await;
+
+                if let Err(e) = result {
+                    eprintln!("DB Error: {}", e);
+                }
+            } else if let Ok(event) = serde_json::from_str::<HelloEvent>(&json_msg) {
+                // Latest handshake per device (what firmware is deployed where)
+                let hello = &event.hello;
+                let result = sqlx::query!(
+                    r#"
+                    INSERT INTO devices
+                        (device_id, firmware, protocol, sample_rate_hz, sensors, port, accepted, detail)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                    ON CONFLICT (device_id) DO UPDATE SET
+                        firmware = EXCLUDED.firmware,
+                        protocol = EXCLUDED.protocol,
+                        sample_rate_hz = EXCLUDED.sample_rate_hz,
+                        sensors = EXCLUDED.sensors,
+                        port = EXCLUDED.port,
+                        accepted = EXCLUDED.accepted,
+                        detail = EXCLUDED.detail,
+                        last_seen = NOW()
+                    "#,
+                    hello.device,
+                    hello.firmware,
+                    hello.protocol as i32,
+                    hello.rate_hz as i32,
+                    &hello.sensors,
+                    event.port,
+                    event.accepted,
+                    event.detail
                 )
                 .execute(&pool)
                 .await;
 
+                if let Err(e) = result {
+                    eprintln!("DB Error: {}", e);
+                }
+            } else if let Ok(event) = serde_json::from_str::<QualityEvent>(&json_msg) {
+                // Gaps and rate reports, to tell "still" from "offline" afterwards
+                let result = sqlx::query!(
+                    r#"
+                    INSERT INTO data_quality
+                        (source, device_id, kind, started_at, ended_at, duration_sec, expected_hz,
+                         effective_hz, irregular_sec, degraded)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    "#,
+                    event.source,
+                    Some(&event.device_id).filter(|id| !id.is_empty()),
+                    event.kind.as_str(),
+                    event.start,
+                    event.end,
+                    event.duration_sec.map(|d| d as f32),
+                    event.expected_hz as f32,
+                    event.effective_hz.map(|hz| hz as f32),
+                    event.irregular_sec.map(|n| n as i32),
+                    event.degraded
+                )
+                .execute(&pool)
+                .await;
+
+                if let Err(e) = result {
+                    eprintln!("DB Error: {}", e);
+                }
+            } else if let Ok(alert) = serde_json::from_str::<HealthAlert>(&json_msg) {
+                // Sensor faults raised and cleared, for device maintenance
+                let result = sqlx::query!(
+                    r#"
+                    INSERT INTO device_health
+                        (source, device_id, fault, active, since, until, detail)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    "#,
+                    alert.source,
+                    Some(&alert.device_id).filter(|id| !id.is_empty()),
+                    alert.fault.as_str(),
+                    alert.active,
+                    alert.since,
+                    alert.until,
+                    alert.detail
+                )
+                .execute(&pool)
+                .await;
+
+                if let Err(e) = result {
+                    eprintln!("DB Error: {}", e);
+                }
+            } else if let Ok(line) = serde_json::from_str::<QuarantinedLine>(&json_msg) {
+                // Bad payloads for firmware debugging, keeping only the newest rows
+                let result = sqlx::query_scalar!(
+                    r#"
+                    INSERT INTO quarantine (device_id, source, kind, reason, payload, received_at)
+                    VALUES ($1, $2, $3, $4, $5, $6)
+                    RETURNING id
+                    "#,
+                    line.device_id,
+                    line.source,
+                    line.kind.as_str(),
+                    line.reason,
+                    line.payload,
+                    line.received_at
+                )
+                .fetch_one(&pool)
+                .await;
+
+                let result = match result {
+                    Ok(id) => sqlx::query!(
+                        "DELETE FROM quarantine WHERE id <= $1",
+                        id - quarantine::MAX_ROWS
+                    )
+                    .execute(&pool)
+                    .await
+                    .map(|_| ()),
+                    Err(e) => Err(e),
+                };
                 if let Err(e) = result {
                     eprintln!("DB Error: {}", e);
                 }
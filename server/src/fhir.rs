@@ -1,5 +1,9 @@
 use crate::state::AppState;
-use axum::{extract::State, http::StatusCode, response::Json};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
 use serde::{Deserialize, Serialize};
 
 #[allow(non_snake_case)]
@@ -34,18 +38,26 @@ pub struct Reference {
     pub reference: String,
 }
 
-// GET /api/fhir/observation/latest
+#[derive(Debug, Deserialize)]
+pub struct LatestParams {
+    pub device: Option<String>, // Latest reading of this device (any device when unset)
+}
+
+// GET /api/fhir/observation/latest?device=<id>
 pub async fn get_latest_observation(
     State(state): State<AppState>,
+    Query(params): Query<LatestParams>,
 ) -> Result<Json<Vec<FhirObservation>>, StatusCode> {
     // 1. Fetch the latest reading from the NEW table (sedentary_log)
     let rec = sqlx::query!(
         r#"
-        SELECT id, state, timer_seconds, created_at 
+        SELECT id, state, timer_seconds, COALESCE(measured_at, created_at) AS created_at
         FROM sedentary_log 
+        WHERE $1::TEXT IS NULL OR device_id = $1
         ORDER BY created_at DESC 
         LIMIT 1
-        "#
+        "#,
+        params.device
     )
     .fetch_optional(&state.db)
     .await
@@ -0,0 +1,372 @@
+use crate::capture::Capture;
+use crate::commands::{parse_ack, Commands};
+use crate::handshake::{parse_hello, Hello};
+use crate::models::{Imu, LinkStats, RawReading};
+use crate::quarantine::Quarantine;
+use crate::source::{check_line, LineError, SensorSource};
+use crc::{Crc, CRC_16_IBM_3740};
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+
+// BINARY FRAME LAYOUT
+// Each frame is COBS-encoded and terminated by 0x00. Decoded (little-endian):
+// [type][hour][minute][second][pir][payload][crc16 over the preceding bytes]
+//   0x01 reading:    acc: f32
+//   0x02 accel:      ax, ay, az: i16 raw MPU6050 counts
+//   0x03 accel+gyro: ax, ay, az, gx, gy, gz: i16 raw MPU6050 counts
+const FRAME_READING: u8 = 0x01;
+const FRAME_ACCEL: u8 = 0x02;
+const FRAME_ACCEL_GYRO: u8 = 0x03;
+const READING_FRAME_LEN: usize = 11;
+const ACCEL_FRAME_LEN: usize = 13;
+const ACCEL_GYRO_FRAME_LEN: usize = 19;
+
+// MPU6050 default full-scale ranges (±2g, ±250°/s)
+const ACCEL_LSB_PER_G: f32 = 16384.0;
+const GYRO_LSB_PER_DPS: f32 = 131.0;
+const MAX_FRAME_BYTES: usize = 4096; // No delimiter after this many bytes means garbage
+const READ_CHUNK: usize = 256;
+
+// CRC-16/CCITT-FALSE, as computed by the Arduino CRC16 library defaults
+const CRC16: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+// Binary frames are stored in capture files as hex behind this prefix
+pub const CAPTURE_PREFIX: &str = "cobs:";
+
+/// Decodes one binary frame (COBS payload without the 0x00 delimiter)
+pub fn decode_frame(encoded: &[u8]) -> Result<RawReading, String> {
+    let frame = cobs::decode_vec(encoded).map_err(|_| "invalid COBS encoding".to_string())?;
+    if frame.len() < 3 {
+        return Err(format!("frame too short ({} bytes)", frame.len()));
+    }
+
+    let (body, crc) = frame.split_at(frame.len() - 2);
+    let expected = u16::from_le_bytes([crc[0], crc[1]]);
+    let actual = CRC16.checksum(body);
+    if actual != expected {
+        return Err(format!(
+            "CRC mismatch (got {:04x}, expected {:04x})",
+            actual, expected
+        ));
+    }
+
+    let expected_len = match body[0] {
+        FRAME_READING => READING_FRAME_LEN,
+        FRAME_ACCEL => ACCEL_FRAME_LEN,
+        FRAME_ACCEL_GYRO => ACCEL_GYRO_FRAME_LEN,
+        other => return Err(format!("unknown frame type 0x{:02x}", other)),
+    };
+    if frame.len() != expected_len {
+        return Err(format!(
+            "frame type 0x{:02x} with {} bytes, expected {}",
+            body[0],
+            frame.len(),
+            expected_len
+        ));
+    }
+
+    // i16 axis n of the payload, scaled to physical units
+    let axis =
+        |n: usize, lsb: f32| i16::from_le_bytes([body[5 + 2 * n], body[6 + 2 * n]]) as f32 / lsb;
+
+    let (acc, imu) = match body[0] {
+        FRAME_READING => (
+            f32::from_le_bytes([body[5], body[6], body[7], body[8]]),
+            None,
+        ),
+        frame_type => {
+            let gyro = frame_type == FRAME_ACCEL_GYRO;
+            let imu = Imu {
+                ax: axis(0, ACCEL_LSB_PER_G),
+                ay: axis(1, ACCEL_LSB_PER_G),
+                az: axis(2, ACCEL_LSB_PER_G),
+                gx: gyro.then(|| axis(3, GYRO_LSB_PER_DPS)),
+                gy: gyro.then(|| axis(4, GYRO_LSB_PER_DPS)),
+                gz: gyro.then(|| axis(5, GYRO_LSB_PER_DPS)),
+            };
+            (0.0, Some(imu))
+        }
+    };
+
+    Ok(RawReading {
+        ts: format!("{:02}:{:02}:{:02}", body[1], body[2], body[3]),
+        pir: body[4] as i32,
+        acc,
+        device: None,
+        imu,
+        received_at: None,
+    })
+}
+
+/// Hex form of a frame for the capture file
+pub fn frame_to_capture(encoded: &[u8]) -> String {
+    let hex: String = encoded.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", CAPTURE_PREFIX, hex)
+}
+
+/// Inverse of frame_to_capture, None if the line isn't a captured frame
+pub fn frame_from_capture(line: &str) -> Option<Vec<u8>> {
+    let hex = line.strip_prefix(CAPTURE_PREFIX)?;
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A complete unit cut out of the byte stream
+#[derive(Debug, PartialEq)]
+pub enum Frame {
+    Line(String), // Newline-terminated text (JSON reading or debug print)
+    Binary(Vec<u8>),
+    Overflow, // Too many bytes without a delimiter
+}
+
+/// Splits a byte stream into JSON lines and COBS frames.
+/// JSON never contains 0x00, so a 0x00 always ends a binary frame. Once the
+/// link is known to be binary, newlines are frame data rather than delimiters.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    binary: bool,
+}
+
+impl FrameDecoder {
+    pub fn push(&mut self, byte: u8) -> Option<Frame> {
+        match byte {
+            0x00 => Some(Frame::Binary(std::mem::take(&mut self.buf))),
+            b'\n' if !self.binary && !self.looks_binary() => {
+                let line = String::from_utf8_lossy(&self.buf).trim().to_string();
+                self.buf.clear();
+                Some(Frame::Line(line))
+            }
+            _ if self.buf.len() >= MAX_FRAME_BYTES => {
+                // Lost sync (or the firmware went back to JSON), detect again
+                self.buf.clear();
+                self.binary = false;
+                Some(Frame::Overflow)
+            }
+            _ => {
+                self.buf.push(byte);
+                None
+            }
+        }
+    }
+
+    pub fn set_binary(&mut self, binary: bool) {
+        self.binary = binary;
+    }
+
+    /// Whatever is left at end of stream (a last line without newline)
+    pub fn finish(&mut self) -> Option<Frame> {
+        if self.buf.is_empty() || self.binary || self.looks_binary() {
+            self.buf.clear();
+            return None;
+        }
+        let line = String::from_utf8_lossy(&self.buf).trim().to_string();
+        self.buf.clear();
+        Some(Frame::Line(line))
+    }
+
+    // A COBS frame starts with its code byte (1..=20 for our frames), text never
+    // starts with a control character, so a 0x0a inside the frame isn't a line end
+    fn looks_binary(&self) -> bool {
+        matches!(self.buf.first(), Some(&b) if b < 0x20 && b != b'\r')
+    }
+}
+
+/// Byte stream carrying either JSON lines or binary frames, auto-detected
+/// (a serial port, or a file / test vector).
+/// Every skipped line or corrupted frame is counted in the shared LinkStats.
+pub struct FramedSource<R> {
+    name: String,
+    reader: R,
+    decoder: FrameDecoder,
+    pending: VecDeque<RawReading>,
+    hello: Option<Hello>,
+    stats: Arc<Mutex<LinkStats>>,
+    capture: Option<Capture>,
+    commands: Option<Commands>, // Receives device acks
+    quarantine: Option<Quarantine>,
+    device: Option<String>, // From the hello, for the line counters
+}
+
+impl<R: Read> FramedSource<R> {
+    pub fn new(name: &str, reader: R) -> Self {
+        FramedSource {
+            name: name.to_string(),
+            reader,
+            decoder: FrameDecoder::default(),
+            pending: VecDeque::new(),
+            hello: None,
+            stats: Arc::new(Mutex::new(LinkStats::new(name))),
+            capture: None,
+            commands: None,
+            quarantine: None,
+            device: None,
+        }
+    }
+
+    pub fn with_capture(mut self, capture: Option<Capture>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    pub fn with_commands(mut self, commands: Commands) -> Self {
+        self.commands = Some(commands);
+        self
+    }
+
+    pub fn with_quarantine(mut self, quarantine: Quarantine) -> Self {
+        self.quarantine = Some(quarantine);
+        self
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Handle to the counters, readable while the source is being driven
+    pub fn stats(&self) -> Arc<Mutex<LinkStats>> {
+        self.stats.clone()
+    }
+
+    // Counts a reading, or counts and quarantines a bad payload
+    fn account(&self, result: Result<(), (&str, LineError)>) {
+        let Some(quarantine) = &self.quarantine else {
+            return;
+        };
+        let device = self.device.as_deref().unwrap_or(&self.name);
+        match result {
+            Ok(()) => quarantine.parsed(device),
+            Err((payload, error)) => {
+                quarantine.reject(device, &self.name, payload, &error);
+            }
+        }
+    }
+
+    fn handle(&mut self, frame: Frame) {
+        let mut stats = self.stats.lock().unwrap();
+        match frame {
+            Frame::Line(line) => {
+                if line.is_empty() {
+                    return;
+                }
+                if let Some(capture) = &self.capture {
+                    capture.record(&self.name, &line);
+                }
+                if let Some(hello) = parse_hello(&line) {
+                    self.device = Some(hello.device.clone());
+                    self.hello = Some(hello);
+                    return;
+                }
+                if let Some(ack) = parse_ack(&line) {
+                    if let Some(commands) = &self.commands {
+                        commands.acknowledge(ack);
+                    }
+                    return;
+                }
+                match check_line(&line) {
+                    Ok(reading) => {
+                        stats.readings += 1;
+                        stats.protocol = "json".to_string();
+                        self.pending.push_back(reading);
+                        self.account(Ok(()));
+                    }
+                    Err(error) => {
+                        if error == LineError::Text {
+                            stats.text_lines += 1;
+                        } else {
+                            stats.malformed += 1;
+                            eprintln!("{}: malformed reading '{}'", self.name, line);
+                        }
+                        self.account(Err((&line, error)));
+                    }
+                }
+            }
+            Frame::Binary(encoded) => {
+                // Back-to-back delimiters are padding, not frames
+                if encoded.is_empty() {
+                    return;
+                }
+                if let Some(capture) = &self.capture {
+                    capture.record(&self.name, &frame_to_capture(&encoded));
+                }
+                match decode_frame(&encoded) {
+                    Ok(reading) => {
+                        stats.readings += 1;
+                        stats.protocol = "binary".to_string();
+                        self.decoder.set_binary(true);
+                        self.pending.push_back(reading);
+                        self.account(Ok(()));
+                    }
+                    Err(e) => {
+                        stats.corrupted += 1;
+                        eprintln!(
+                            "{}: corrupted frame ({}), {} so far",
+                            self.name, e, stats.corrupted
+                        );
+                        let payload = frame_to_capture(&encoded);
+                        self.account(Err((&payload, LineError::Invalid(e))));
+                    }
+                }
+            }
+            Frame::Overflow => {
+                stats.corrupted += 1;
+                eprintln!("{}: no frame delimiter, resyncing", self.name);
+            }
+        }
+    }
+}
+
+impl<R: Read + Send> SensorSource for FramedSource<R> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_reading(&mut self) -> io::Result<Option<RawReading>> {
+        if let Some(reading) = self.pending.pop_front() {
+            return Ok(Some(reading));
+        }
+
+        let mut chunk = [0u8; READ_CHUNK];
+        let n = match self.reader.read(&mut chunk) {
+            // EOF means the other end closed (unplugged port, end of file)
+            Ok(0) => {
+                if let Some(frame) = self.decoder.finish() {
+                    self.handle(frame);
+                    if let Some(reading) = self.pending.pop_front() {
+                        return Ok(Some(reading));
+                    }
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "device closed the connection",
+                ));
+            }
+            Ok(n) => n,
+            // No data within the timeout, keep any partial frame and wait
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        for &byte in &chunk[..n] {
+            if let Some(frame) = self.decoder.push(byte) {
+                self.handle(frame);
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    fn take_hello(&mut self) -> Option<Hello> {
+        self.hello.take()
+    }
+}
+
+#[cfg(test)]
+#[path = "framing_tests.rs"]
+mod tests;
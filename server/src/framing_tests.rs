@@ -0,0 +1,321 @@
+use super::*;
+use std::io::Cursor;
+
+// Same encoding as the firmware: body + CRC16, COBS-encoded, 0x00 delimiter
+fn encode(hour: u8, minute: u8, second: u8, pir: u8, acc: f32) -> Vec<u8> {
+    let mut frame = vec![FRAME_READING, hour, minute, second, pir];
+    frame.extend_from_slice(&acc.to_le_bytes());
+    let crc = CRC16.checksum(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+
+    let mut encoded = cobs::encode_vec(&frame);
+    encoded.push(0x00);
+    encoded
+}
+
+// Extended frame with raw MPU6050 counts (gyro optional)
+fn encode_imu(accel: [i16; 3], gyro: Option<[i16; 3]>) -> Vec<u8> {
+    let frame_type = if gyro.is_some() {
+        FRAME_ACCEL_GYRO
+    } else {
+        FRAME_ACCEL
+    };
+    let mut frame = vec![frame_type, 10, 0, 0, 0];
+    for value in accel.iter().chain(gyro.iter().flatten()) {
+        frame.extend_from_slice(&value.to_le_bytes());
+    }
+    let crc = CRC16.checksum(&frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+    cobs::encode_vec(&frame)
+}
+
+fn drain(source: &mut FramedSource<Cursor<Vec<u8>>>) -> Vec<RawReading> {
+    let mut readings = Vec::new();
+    loop {
+        match source.next_reading() {
+            Ok(Some(reading)) => readings.push(reading),
+            Ok(None) => {}
+            Err(_) => return readings,
+        }
+    }
+}
+
+// decode_frame Tests
+
+#[test]
+fn test_crc16_check_value() {
+    assert_eq!(CRC16.checksum(b"123456789"), 0x29b1);
+}
+
+#[test]
+fn test_decode_frame_valid_reading() {
+    let encoded = encode(12, 34, 56, 1, 0.045);
+    let reading = decode_frame(&encoded[..encoded.len() - 1]).unwrap();
+
+    assert_eq!(reading.ts, "12:34:56");
+    assert_eq!(reading.pir, 1);
+    assert_eq!(reading.acc, 0.045);
+}
+
+#[test]
+fn test_decode_frame_rejects_bit_flip() {
+    let mut encoded = encode(12, 34, 56, 0, 0.045);
+    encoded[6] ^= 0x01;
+
+    let err = decode_frame(&encoded[..encoded.len() - 1]).unwrap_err();
+    assert!(err.contains("CRC"), "{}", err);
+}
+
+#[test]
+fn test_decode_frame_rejects_unknown_type_and_length() {
+    let frame_with = |body: &[u8]| {
+        let mut frame = body.to_vec();
+        frame.extend_from_slice(&CRC16.checksum(body).to_le_bytes());
+        cobs::encode_vec(&frame)
+    };
+
+    assert!(decode_frame(&frame_with(&[0x7f, 1, 2]))
+        .unwrap_err()
+        .contains("unknown frame type"));
+    assert!(decode_frame(&frame_with(&[FRAME_READING, 1, 2]))
+        .unwrap_err()
+        .contains("bytes"));
+    assert!(decode_frame(&[0x01]).is_err());
+}
+
+#[test]
+fn test_decode_frame_accel_axes() {
+    let reading = decode_frame(&encode_imu([0, -8192, 16384], None)).unwrap();
+    let imu = reading.imu.unwrap();
+
+    assert_eq!(reading.ts, "10:00:00");
+    assert_eq!((imu.ax, imu.ay, imu.az), (0.0, -0.5, 1.0));
+    assert_eq!(imu.gx, None);
+}
+
+#[test]
+fn test_decode_frame_accel_gyro_axes() {
+    let reading = decode_frame(&encode_imu([0, 0, 16384], Some([131, -262, 0]))).unwrap();
+    let imu = reading.imu.unwrap();
+
+    assert_eq!(imu.gx, Some(1.0));
+    assert_eq!(imu.gy, Some(-2.0));
+    assert_eq!(imu.gz, Some(0.0));
+}
+
+#[test]
+fn test_capture_hex_round_trip() {
+    let encoded = encode(8, 0, 1, 0, 0.01);
+    let line = frame_to_capture(&encoded);
+
+    assert!(line.starts_with("cobs:"));
+    assert_eq!(frame_from_capture(&line), Some(encoded));
+    assert_eq!(frame_from_capture("{\"ts\":\"08:00:01\"}"), None);
+    assert_eq!(frame_from_capture("cobs:0g"), None);
+}
+
+// FrameDecoder Tests
+
+#[test]
+fn test_decoder_splits_lines_and_frames() {
+    let mut decoder = FrameDecoder::default();
+    let mut frames = Vec::new();
+    for &byte in b"boot ok\r\nab\x00" {
+        frames.extend(decoder.push(byte));
+    }
+
+    assert_eq!(
+        frames,
+        vec![
+            Frame::Line("boot ok".to_string()),
+            Frame::Binary(b"ab".to_vec())
+        ]
+    );
+}
+
+#[test]
+fn test_decoder_keeps_newlines_in_binary_mode() {
+    let mut decoder = FrameDecoder::default();
+    decoder.set_binary(true);
+
+    assert_eq!(decoder.push(b'\n'), None);
+    assert_eq!(decoder.push(0x00), Some(Frame::Binary(vec![b'\n'])));
+}
+
+#[test]
+fn test_decoder_resyncs_after_overflow() {
+    let mut decoder = FrameDecoder::default();
+    decoder.set_binary(true);
+
+    let overflow = (0..=MAX_FRAME_BYTES)
+        .filter_map(|_| decoder.push(b'x'))
+        .count();
+    assert_eq!(overflow, 1);
+    // Back to auto-detection, so JSON lines work again
+    assert_eq!(decoder.push(b'\n'), Some(Frame::Line(String::new())));
+}
+
+// FramedSource Tests
+
+#[test]
+fn test_framed_source_yields_readings_then_eof() {
+    let input = "boot ok\n{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}\n{\"ts\":\"10:00:01\",\"pir\":1,\"acc\":0.2}\n";
+    let mut source = FramedSource::new("test", Cursor::new(input));
+
+    assert_eq!(source.name(), "test");
+    // The debug line yields nothing, readings come out in order
+    assert_eq!(source.next_reading().unwrap().unwrap().ts, "10:00:00");
+    assert_eq!(source.next_reading().unwrap().unwrap().pir, 1);
+
+    let err = source.next_reading().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_framed_source_last_line_without_newline() {
+    let input = "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}";
+    let mut source = FramedSource::new("test", Cursor::new(input.as_bytes().to_vec()));
+
+    assert_eq!(drain(&mut source).len(), 1);
+}
+
+#[test]
+fn test_framed_source_captures_every_line() {
+    let path =
+        std::env::temp_dir().join(format!("framed_line_capture_{}.ndjson", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+    let input = "boot ok\r\n{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}\r\n";
+    let mut source = FramedSource::new("/dev/ttyACM0", Cursor::new(input))
+        .with_capture(Some(Capture::from_writer(file)));
+
+    while source.next_reading().is_ok() {}
+
+    let captured = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let lines: Vec<&str> = captured.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains("\"line\":\"boot ok\""));
+    assert!(lines[1].contains("\"source\":\"/dev/ttyACM0\""));
+}
+
+#[test]
+fn test_framed_source_reads_json_lines() {
+    let input = b"MPU6050 found\r\n{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}\r\n{\"ts\":\"10:00:01\",\"pir\":0\r\n".to_vec();
+    let mut source = FramedSource::new("/dev/ttyACM0", Cursor::new(input));
+    let stats = source.stats();
+
+    let readings = drain(&mut source);
+
+    assert_eq!(readings.len(), 1);
+    let stats = stats.lock().unwrap();
+    assert_eq!(stats.protocol, "json");
+    assert_eq!(stats.readings, 1);
+    assert_eq!(stats.text_lines, 1);
+    assert_eq!(stats.malformed, 1);
+    assert_eq!(stats.corrupted, 0);
+}
+
+#[test]
+fn test_framed_source_detects_binary_and_counts_corruption() {
+    let mut input = b"boot ok\r\n".to_vec();
+    input.extend(encode(10, 0, 0, 0, 0.01));
+    let mut corrupted = encode(10, 0, 0, 0, 0.02);
+    corrupted[3] ^= 0x40;
+    input.extend(corrupted);
+    // Newline bytes inside a frame must not split it
+    input.extend(encode(
+        10,
+        0,
+        1,
+        1,
+        f32::from_le_bytes([0x0a, 0x0a, 0x0a, 0x3d]),
+    ));
+
+    let mut source = FramedSource::new("/dev/ttyACM0", Cursor::new(input));
+    let stats = source.stats();
+    let readings = drain(&mut source);
+
+    assert_eq!(readings.len(), 2);
+    assert_eq!(readings[0].ts, "10:00:00");
+    assert_eq!(readings[1].ts, "10:00:01");
+    assert_eq!(readings[1].pir, 1);
+
+    let stats = stats.lock().unwrap();
+    assert_eq!(stats.protocol, "binary");
+    assert_eq!(stats.readings, 2);
+    assert_eq!(stats.text_lines, 1);
+    assert_eq!(stats.corrupted, 1);
+}
+
+#[test]
+fn test_framed_source_captures_frames_as_hex() {
+    let path = std::env::temp_dir().join(format!("framed_capture_{}.ndjson", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+    let input = encode(9, 30, 0, 0, 0.015);
+    let mut source = FramedSource::new("/dev/ttyACM0", Cursor::new(input.clone()))
+        .with_capture(Some(Capture::from_writer(file)));
+
+    drain(&mut source);
+
+    let captured = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(captured.contains(&frame_to_capture(&input[..input.len() - 1])));
+}
+
+#[test]
+fn test_framed_source_hands_over_hello() {
+    let input = b"{\"hello\":{\"device\":\"desk-3\",\"firmware\":\"1.4.0\",\"protocol\":2,\"rate_hz\":20}}\r\n{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}\r\n".to_vec();
+    let mut source = FramedSource::new("/dev/ttyACM0", Cursor::new(input));
+    let stats = source.stats();
+
+    assert!(source.next_reading().unwrap().is_some());
+    let hello = source.take_hello().unwrap();
+    assert_eq!(hello.device, "desk-3");
+    assert_eq!(hello.rate_hz, 20);
+    assert_eq!(source.take_hello(), None);
+    // The hello is neither a reading nor noise
+    assert_eq!(stats.lock().unwrap().text_lines, 0);
+    assert_eq!(stats.lock().unwrap().malformed, 0);
+}
+
+#[test]
+fn test_framed_source_quarantines_bad_lines_per_device() {
+    let input = b"boot ok\r\n{\"hello\":{\"device\":\"desk-3\",\"firmware\":\"1.4.0\",\"protocol\":2,\"rate_hz\":20}}\r\n{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}\r\n{\"ts\":\"10:00:01\",\"pir\":0\r\n".to_vec();
+    let quarantine = Quarantine::default();
+    let mut source =
+        FramedSource::new("/dev/ttyACM0", Cursor::new(input)).with_quarantine(quarantine.clone());
+
+    assert_eq!(drain(&mut source).len(), 1);
+
+    // Lines before the hello count against the port
+    let counts = quarantine.counts();
+    assert_eq!(counts["/dev/ttyACM0"].rejected, 1);
+    assert_eq!(counts["desk-3"].parsed, 1);
+    assert_eq!(counts["desk-3"].partial, 1);
+}
+
+#[test]
+fn test_framed_source_routes_acks_to_commands() {
+    let commands = Commands::default();
+    let (tx, _rx) = std::sync::mpsc::channel();
+    commands.attach("desk-3", tx);
+    let record = commands
+        .send("desk-3", crate::commands::Command::SetRate { rate_hz: 20 })
+        .unwrap();
+    commands.mark_sent(record.id, Ok(()));
+
+    let input = format!(
+        "{{\"ack\":{{\"id\":{},\"ok\":true}}}}\r\n{{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}}\r\n",
+        record.id
+    );
+    let mut source = FramedSource::new("/dev/ttyACM0", Cursor::new(input.into_bytes()))
+        .with_commands(commands.clone());
+    let stats = source.stats();
+
+    assert_eq!(drain(&mut source).len(), 1);
+    assert_eq!(
+        commands.records("desk-3")[0].status,
+        crate::commands::CommandStatus::Acked
+    );
+    assert_eq!(stats.lock().unwrap().malformed, 0);
+}
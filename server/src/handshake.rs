@@ -0,0 +1,161 @@
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// PROTOCOL VERSIONS
+// v1: JSON readings with "acc", no hello (anonymous 10Hz stream)
+// v2: hello on connect, extended IMU readings, binary frames
+pub const PROTOCOL_VERSION: u32 = 2;
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+// LIMITS
+const MAX_DEVICE_ID_LEN: usize = 64;
+const MAX_FIRMWARE_LEN: usize = 32;
+const MAX_SAMPLE_RATE_HZ: u32 = 100;
+
+/// Sent by the device once after connecting, before any reading:
+/// {"hello":{"device":"desk-3","firmware":"1.4.0","protocol":2,"rate_hz":10,"sensors":["pir","mpu6050"]}}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Hello {
+    pub device: String,
+    pub firmware: String,
+    pub protocol: u32,
+    pub rate_hz: u32,
+    #[serde(default)]
+    pub sensors: Vec<String>, // e.g. "pir", "mpu6050", "gyro"
+}
+
+#[derive(Deserialize)]
+struct HelloLine {
+    hello: Hello,
+}
+
+/// Parses a hello line; readings and debug output return None
+pub fn parse_hello(line: &str) -> Option<Hello> {
+    let clean_line = line.trim();
+    if !clean_line.starts_with("{\"hello\"") {
+        return None;
+    }
+    serde_json::from_str::<HelloLine>(clean_line)
+        .ok()
+        .map(|l| l.hello)
+}
+
+/// Checks that the server can talk to this device
+pub fn validate(hello: &Hello) -> Result<(), String> {
+    let valid_id = !hello.device.is_empty()
+        && hello.device.len() <= MAX_DEVICE_ID_LEN
+        && hello
+            .device
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c));
+    if !valid_id {
+        return Err(format!("invalid device id '{}'", hello.device));
+    }
+    if hello.firmware.is_empty() || hello.firmware.len() > MAX_FIRMWARE_LEN {
+        return Err(format!("invalid firmware version '{}'", hello.firmware));
+    }
+    if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&hello.protocol) {
+        return Err(format!(
+            "protocol v{} not supported (server speaks v{}-v{})",
+            hello.protocol, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION
+        ));
+    }
+    if hello.rate_hz == 0 || hello.rate_hz > MAX_SAMPLE_RATE_HZ {
+        return Err(format!(
+            "sample rate {}Hz outside 1-{}Hz",
+            hello.rate_hz, MAX_SAMPLE_RATE_HZ
+        ));
+    }
+    Ok(())
+}
+
+/// What the dashboard and the DB worker get after a handshake
+/// Format: {"event":"device_hello","device":"desk-3",...,"port":"/dev/ttyACM0","accepted":true,"detail":""}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HelloEvent {
+    pub event: String, // Always "device_hello"
+    #[serde(flatten)]
+    pub hello: Hello,
+    pub port: String,   // Where the device connected
+    pub accepted: bool, // Passed validation?
+    pub detail: String, // Rejection reason
+}
+
+impl HelloEvent {
+    pub fn new(hello: &Hello, port: &str, result: &Result<(), String>) -> Self {
+        HelloEvent {
+            event: "device_hello".to_string(),
+            hello: hello.clone(),
+            port: port.to_string(),
+            accepted: result.is_ok(),
+            detail: result.clone().err().unwrap_or_default(),
+        }
+    }
+}
+
+/// Registry row from the devices table
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DeviceInfo {
+    pub device: String,
+    pub firmware: String,
+    pub protocol: i32,
+    pub rate_hz: i32,
+    pub sensors: Vec<String>,
+    pub port: String,
+    pub accepted: bool,
+    pub detail: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+// GET /api/devices
+pub async fn list_devices(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<DeviceInfo>>, StatusCode> {
+    let devices = sqlx::query_as!(
+        DeviceInfo,
+        r#"
+        SELECT device_id AS device, firmware, protocol, sample_rate_hz AS rate_hz, sensors,
+               port, accepted, detail, first_seen, last_seen
+        FROM devices
+        ORDER BY device_id
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(devices))
+}
+
+// GET /api/devices/:id
+pub async fn get_device(
+    State(state): State<AppState>,
+    Path(device): Path<String>,
+) -> Result<Json<DeviceInfo>, StatusCode> {
+    sqlx::query_as!(
+        DeviceInfo,
+        r#"
+        SELECT device_id AS device, firmware, protocol, sample_rate_hz AS rate_hz, sensors,
+               port, accepted, detail, first_seen, last_seen
+        FROM devices
+        WHERE device_id = $1
+        "#,
+        device
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map(Json)
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[cfg(test)]
+#[path = "handshake_tests.rs"]
+mod tests;
@@ -0,0 +1,88 @@
+use super::*;
+
+fn hello(protocol: u32, rate_hz: u32) -> Hello {
+    Hello {
+        device: "desk-3".to_string(),
+        firmware: "1.4.0".to_string(),
+        protocol,
+        rate_hz,
+        sensors: vec!["pir".to_string(), "mpu6050".to_string()],
+    }
+}
+
+// parse_hello Tests
+
+#[test]
+fn test_parse_hello_line() {
+    let line = "{\"hello\":{\"device\":\"desk-3\",\"firmware\":\"1.4.0\",\"protocol\":2,\"rate_hz\":10,\"sensors\":[\"pir\",\"mpu6050\"]}}\r\n";
+
+    assert_eq!(parse_hello(line), Some(hello(2, 10)));
+}
+
+#[test]
+fn test_parse_hello_sensors_optional() {
+    let line = r#"{"hello":{"device":"desk-3","firmware":"1.0","protocol":1,"rate_hz":10}}"#;
+
+    assert_eq!(parse_hello(line).unwrap().sensors, Vec::<String>::new());
+}
+
+#[test]
+fn test_parse_hello_ignores_readings_and_garbage() {
+    assert_eq!(
+        parse_hello("{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}"),
+        None
+    );
+    assert_eq!(parse_hello("{\"hello\":{\"device\":\"desk-3\"}}"), None);
+    assert_eq!(parse_hello("hello"), None);
+}
+
+// validate Tests
+
+#[test]
+fn test_validate_accepts_supported_versions() {
+    assert_eq!(validate(&hello(1, 10)), Ok(()));
+    assert_eq!(validate(&hello(PROTOCOL_VERSION, 50)), Ok(()));
+}
+
+#[test]
+fn test_validate_rejects_newer_protocol() {
+    let err = validate(&hello(PROTOCOL_VERSION + 1, 10)).unwrap_err();
+    assert!(err.contains("not supported"), "{}", err);
+}
+
+#[test]
+fn test_validate_rejects_bad_fields() {
+    assert!(validate(&hello(2, 0)).is_err());
+    assert!(validate(&hello(2, 1000)).is_err());
+
+    let mut bad_id = hello(2, 10);
+    bad_id.device = "desk 3; drop".to_string();
+    assert!(validate(&bad_id).is_err());
+
+    let mut no_firmware = hello(2, 10);
+    no_firmware.firmware = String::new();
+    assert!(validate(&no_firmware).is_err());
+}
+
+// HelloEvent Tests
+
+#[test]
+fn test_hello_event_flattens_hello() {
+    let rejected = Err("protocol v9 not supported".to_string());
+    let event = HelloEvent::new(&hello(9, 10), "/dev/ttyACM0", &rejected);
+
+    let json = serde_json::to_string(&event).unwrap();
+    assert!(json.contains("\"event\":\"device_hello\""));
+    assert!(json.contains("\"device\":\"desk-3\""));
+    assert!(json.contains("\"accepted\":false"));
+
+    let back: HelloEvent = serde_json::from_str(&json).unwrap();
+    assert_eq!(back, event);
+}
+
+#[test]
+fn test_hello_event_does_not_match_readings() {
+    let json = r#"{"state":"SEDENTARY","timer":1,"val":0.01,"alert":false,"timestamp":"10:00:00"}"#;
+
+    assert!(serde_json::from_str::<HelloEvent>(json).is_err());
+}
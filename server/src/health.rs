@@ -0,0 +1,253 @@
+use crate::models::{RawReading, SensorFault};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// SENSOR HEALTH
+const STUCK_ACC: TimeDelta = TimeDelta::hours(2); // Unchanged acceleration this long = frozen MPU6050
+const STUCK_PIR: TimeDelta = TimeDelta::minutes(30); // PIR high without a break this long = stuck
+const MAX_STEP: TimeDelta = TimeDelta::seconds(60); // Longer gaps between readings don't count toward the above
+const CLEAR_AFTER: TimeDelta = TimeDelta::seconds(60); // Per-reading faults clear after this long without one
+const FLAT_EPSILON: f32 = 1e-6; // Smaller changes count as "unchanged"
+
+// Widest ranges of the MPU6050
+const MAX_ACC_G: f32 = 16.0;
+const MAX_GYRO_DPS: f32 = 2000.0;
+
+/// Device health alert, sent when a fault appears and again when it clears
+/// Format: {"event":"device_health","source":"/dev/ttyACM0","device_id":"desk-3","fault":"stuck_pir","active":true,"since":"...","detail":"PIR high for 30 min"}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthAlert {
+    pub event: String,  // Always "device_health"
+    pub source: String, // Transport: port path or tcp://, udp://, mqtt://, http:// source
+    #[serde(default)]
+    pub device_id: String, // Device the readings came from, filled in with source
+    pub fault: SensorFault, // What is wrong
+    pub active: bool,   // Raised (true) or cleared (false)
+    pub since: DateTime<Utc>, // When the fault started (device time)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>, // When it cleared
+    pub detail: String,
+}
+
+// A raised fault
+#[derive(Debug)]
+struct Active {
+    since: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    detail: String,
+}
+
+/// Checks one device's readings for sensor faults: values outside what the
+/// hardware can produce, time going backwards, and sensors stuck on one value
+#[derive(Debug, Default)]
+pub struct HealthMonitor {
+    latest: Option<DateTime<Utc>>, // Latest device time seen
+    flat: Option<[f32; 3]>,        // Acceleration the current unchanged run started with
+    flat_for: TimeDelta,
+    pir_high_for: TimeDelta,
+    active: HashMap<SensorFault, Active>,
+    alerts: Vec<HealthAlert>,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks a reading taken at `now` (device time) and returns the faults
+    /// that apply to it
+    pub fn check(&mut self, reading: &RawReading, now: DateTime<Utc>) -> Vec<SensorFault> {
+        let out_of_range = out_of_range(reading);
+        if let Some(detail) = &out_of_range {
+            self.raise(SensorFault::OutOfRange, now, detail);
+        }
+
+        match self.latest {
+            Some(latest) if now < latest => {
+                let back = (latest - now).num_seconds();
+                self.raise(
+                    SensorFault::NonMonotonic,
+                    latest,
+                    &format!("device time went back {}s", back),
+                );
+            }
+            latest => {
+                // Stuck checks follow the stream forward only
+                let step = latest.map_or(TimeDelta::zero(), |latest| now - latest);
+                let step = if step > MAX_STEP {
+                    TimeDelta::zero()
+                } else {
+                    step
+                };
+                self.latest = Some(now);
+                if out_of_range.is_none() {
+                    self.track_flat(reading, step);
+                }
+                self.track_pir(reading.pir, step);
+            }
+        }
+
+        let latest = self.latest.unwrap_or(now);
+        self.update_stuck(latest);
+        for fault in [SensorFault::OutOfRange, SensorFault::NonMonotonic] {
+            let stale = self
+                .active
+                .get(&fault)
+                .is_some_and(|a| latest - a.last_seen >= CLEAR_AFTER);
+            if stale {
+                self.clear(fault, latest);
+            }
+        }
+
+        let mut faults = Vec::new();
+        if out_of_range.is_some() {
+            faults.push(SensorFault::OutOfRange);
+        }
+        if now < latest {
+            faults.push(SensorFault::NonMonotonic);
+        }
+        for fault in [SensorFault::StuckAccelerometer, SensorFault::StuckPir] {
+            if self.active.contains_key(&fault) {
+                faults.push(fault);
+            }
+        }
+        faults
+    }
+
+    /// PIR is stuck high and shouldn't decide the state
+    pub fn pir_stuck(&self) -> bool {
+        self.active.contains_key(&SensorFault::StuckPir)
+    }
+
+    /// Alerts raised or cleared since the last call
+    pub fn take_alerts(&mut self) -> Vec<HealthAlert> {
+        std::mem::take(&mut self.alerts)
+    }
+
+    fn track_flat(&mut self, reading: &RawReading, step: TimeDelta) {
+        // Raw axes when sent: a frozen sensor repeats them exactly
+        let value = match reading.imu {
+            Some(imu) => [imu.ax, imu.ay, imu.az],
+            None => [reading.acc, 0.0, 0.0],
+        };
+        match self.flat {
+            Some(flat)
+                if flat
+                    .iter()
+                    .zip(value)
+                    .all(|(a, b)| (a - b).abs() <= FLAT_EPSILON) =>
+            {
+                self.flat_for += step
+            }
+            _ => {
+                self.flat = Some(value);
+                self.flat_for = TimeDelta::zero();
+            }
+        }
+    }
+
+    fn track_pir(&mut self, pir: i32, step: TimeDelta) {
+        if pir == 1 {
+            self.pir_high_for += step;
+        } else {
+            self.pir_high_for = TimeDelta::zero();
+        }
+    }
+
+    fn update_stuck(&mut self, now: DateTime<Utc>) {
+        if self.flat_for >= STUCK_ACC {
+            let detail = format!(
+                "acceleration unchanged for {} min",
+                self.flat_for.num_minutes()
+            );
+            self.raise(
+                SensorFault::StuckAccelerometer,
+                now - self.flat_for,
+                &detail,
+            );
+        } else {
+            self.clear(SensorFault::StuckAccelerometer, now);
+        }
+
+        if self.pir_high_for >= STUCK_PIR {
+            let detail = format!(
+                "PIR high for {} min, ignored for classification",
+                self.pir_high_for.num_minutes()
+            );
+            self.raise(SensorFault::StuckPir, now - self.pir_high_for, &detail);
+        } else {
+            self.clear(SensorFault::StuckPir, now);
+        }
+    }
+
+    fn raise(&mut self, fault: SensorFault, at: DateTime<Utc>, detail: &str) {
+        if let Some(active) = self.active.get_mut(&fault) {
+            active.last_seen = active.last_seen.max(at);
+            return;
+        }
+        eprintln!("Sensor fault {}: {}", fault.as_str(), detail);
+        self.active.insert(
+            fault,
+            Active {
+                since: at,
+                last_seen: at,
+                detail: detail.to_string(),
+            },
+        );
+        self.alerts.push(HealthAlert {
+            event: "device_health".to_string(),
+            source: String::new(),
+            device_id: String::new(),
+            fault,
+            active: true,
+            since: at,
+            until: None,
+            detail: detail.to_string(),
+        });
+    }
+
+    fn clear(&mut self, fault: SensorFault, at: DateTime<Utc>) {
+        if let Some(active) = self.active.remove(&fault) {
+            println!("Sensor fault {} cleared", fault.as_str());
+            self.alerts.push(HealthAlert {
+                event: "device_health".to_string(),
+                source: String::new(),
+                device_id: String::new(),
+                fault,
+                active: false,
+                since: active.since,
+                until: Some(at),
+                detail: active.detail,
+            });
+        }
+    }
+}
+
+/// Describes the first value the hardware can't have produced, if any
+fn out_of_range(reading: &RawReading) -> Option<String> {
+    if reading.pir != 0 && reading.pir != 1 {
+        return Some(format!("pir {} is not 0 or 1", reading.pir));
+    }
+    let Some(imu) = reading.imu else {
+        let acc = reading.acc;
+        return (!acc.is_finite() || !(0.0..=MAX_ACC_G).contains(&acc))
+            .then(|| format!("acc {} outside 0-{}g", acc, MAX_ACC_G));
+    };
+    let axes = [("ax", imu.ax), ("ay", imu.ay), ("az", imu.az)];
+    if let Some((name, value)) = axes
+        .into_iter()
+        .find(|(_, v)| !v.is_finite() || v.abs() > MAX_ACC_G)
+    {
+        return Some(format!("{} {} outside ±{}g", name, value, MAX_ACC_G));
+    }
+    let gyro = [("gx", imu.gx), ("gy", imu.gy), ("gz", imu.gz)];
+    gyro.into_iter()
+        .filter_map(|(name, value)| value.map(|v| (name, v)))
+        .find(|(_, v)| !v.is_finite() || v.abs() > MAX_GYRO_DPS)
+        .map(|(name, value)| format!("{} {} outside ±{}°/s", name, value, MAX_GYRO_DPS))
+}
+
+#[cfg(test)]
+#[path = "health_tests.rs"]
+mod tests;
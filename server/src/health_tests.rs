@@ -0,0 +1,202 @@
+use super::*;
+use crate::models::Imu;
+use chrono::TimeZone;
+
+fn at(secs: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 17, 10, 0, 0).unwrap() + TimeDelta::seconds(secs)
+}
+
+fn reading(pir: i32, acc: f32) -> RawReading {
+    RawReading {
+        ts: "10:00:00".to_string(),
+        pir,
+        acc,
+        device: None,
+        imu: None,
+        received_at: None,
+    }
+}
+
+fn imu_reading(ax: f32, gx: Option<f32>) -> RawReading {
+    RawReading {
+        imu: Some(Imu {
+            ax,
+            ay: 0.0,
+            az: 1.0,
+            gx,
+            gy: None,
+            gz: None,
+        }),
+        ..reading(0, 0.0)
+    }
+}
+
+// Noisy but plausible desk readings, one per second
+fn feed(monitor: &mut HealthMonitor, from: i64, to: i64, pir: i32) {
+    for second in from..to {
+        let acc = 0.001 + (second % 7) as f32 * 0.001;
+        monitor.check(&reading(pir, acc), at(second));
+    }
+}
+
+// Out-of-range Tests
+
+#[test]
+fn test_plausible_readings_have_no_faults() {
+    let mut monitor = HealthMonitor::new();
+    for second in 0..10 {
+        assert!(monitor.check(&reading(0, 0.012), at(second)).is_empty());
+    }
+    assert!(monitor
+        .check(&imu_reading(-1.5, Some(250.0)), at(10))
+        .is_empty());
+    assert!(monitor.take_alerts().is_empty());
+}
+
+#[test]
+fn test_out_of_range_values() {
+    for bad in [
+        reading(0, f32::NAN),
+        reading(0, -0.5),
+        reading(0, 40.0),
+        reading(2, 0.01),
+        imu_reading(f32::INFINITY, None),
+        imu_reading(20.0, None),
+        imu_reading(0.0, Some(5000.0)),
+    ] {
+        let mut monitor = HealthMonitor::new();
+        assert_eq!(
+            monitor.check(&bad, at(0)),
+            vec![SensorFault::OutOfRange],
+            "{:?}",
+            bad
+        );
+    }
+}
+
+#[test]
+fn test_out_of_range_alert_raised_once_and_cleared() {
+    let mut monitor = HealthMonitor::new();
+    monitor.check(&reading(0, -1.0), at(0));
+    monitor.check(&reading(0, f32::NAN), at(1));
+
+    let raised = monitor.take_alerts();
+    assert_eq!(raised.len(), 1);
+    assert_eq!(raised[0].fault, SensorFault::OutOfRange);
+    assert!(raised[0].active);
+    assert_eq!(raised[0].detail, "acc -1 outside 0-16g");
+
+    // A minute of good readings clears it
+    feed(&mut monitor, 2, 62, 0);
+    let cleared = monitor.take_alerts();
+    assert_eq!(cleared.len(), 1);
+    assert!(!cleared[0].active);
+    assert_eq!(cleared[0].since, at(0));
+    assert_eq!(cleared[0].until, Some(at(61)));
+}
+
+// Timestamp Tests
+
+#[test]
+fn test_time_going_back_is_flagged() {
+    let mut monitor = HealthMonitor::new();
+    feed(&mut monitor, 0, 10, 0);
+
+    assert_eq!(
+        monitor.check(&reading(0, 0.01), at(4)),
+        vec![SensorFault::NonMonotonic]
+    );
+    // Repeated timestamps are fine (several samples per RTC second)
+    assert!(monitor.check(&reading(0, 0.01), at(9)).is_empty());
+
+    let alerts = monitor.take_alerts();
+    assert_eq!(alerts[0].fault, SensorFault::NonMonotonic);
+    assert_eq!(alerts[0].detail, "device time went back 5s");
+}
+
+// Stuck Sensor Tests
+
+#[test]
+fn test_frozen_accelerometer() {
+    let mut monitor = HealthMonitor::new();
+    let frozen = imu_reading(0.01, None);
+    for second in 0..7199 {
+        assert!(monitor.check(&frozen, at(second)).is_empty());
+    }
+    assert_eq!(
+        monitor.check(&frozen, at(7200)),
+        vec![SensorFault::StuckAccelerometer]
+    );
+
+    let alerts = monitor.take_alerts();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].since, at(0));
+    assert_eq!(alerts[0].detail, "acceleration unchanged for 120 min");
+
+    // Any change clears it
+    assert!(monitor.check(&imu_reading(0.02, None), at(7201)).is_empty());
+    assert!(!monitor.take_alerts()[0].active);
+}
+
+#[test]
+fn test_still_but_noisy_accelerometer_is_fine() {
+    let mut monitor = HealthMonitor::new();
+    feed(&mut monitor, 0, 3 * 3600, 0);
+
+    assert!(monitor.take_alerts().is_empty());
+}
+
+#[test]
+fn test_gaps_dont_count_as_unchanged() {
+    let mut monitor = HealthMonitor::new();
+    // Same value, but only a few readings hours apart (device mostly offline)
+    for hour in 0..5 {
+        monitor.check(&reading(0, 0.0), at(hour * 3600));
+    }
+
+    assert!(monitor.take_alerts().is_empty());
+}
+
+#[test]
+fn test_stuck_pir() {
+    let mut monitor = HealthMonitor::new();
+    feed(&mut monitor, 0, 30 * 60, 1);
+    assert!(!monitor.pir_stuck());
+
+    let faults = monitor.check(&reading(1, 0.01), at(30 * 60));
+    assert_eq!(faults, vec![SensorFault::StuckPir]);
+    assert!(monitor.pir_stuck());
+    assert_eq!(monitor.take_alerts()[0].fault, SensorFault::StuckPir);
+
+    // Released once it drops
+    monitor.check(&reading(0, 0.01), at(30 * 60 + 1));
+    assert!(!monitor.pir_stuck());
+}
+
+#[test]
+fn test_pir_that_drops_now_and_then_is_fine() {
+    let mut monitor = HealthMonitor::new();
+    for minute in 0..120 {
+        feed(&mut monitor, minute * 60, minute * 60 + 59, 1);
+        feed(&mut monitor, minute * 60 + 59, minute * 60 + 60, 0);
+    }
+
+    assert!(!monitor.pir_stuck());
+    assert!(monitor.take_alerts().is_empty());
+}
+
+// Wire Format Tests
+
+#[test]
+fn test_health_alert_format() {
+    let mut monitor = HealthMonitor::new();
+    monitor.check(&reading(3, 0.01), at(0));
+    let alert = monitor.take_alerts().pop().unwrap();
+
+    let json = serde_json::to_string(&alert).unwrap();
+    assert!(json.starts_with(
+        "{\"event\":\"device_health\",\"source\":\"\",\"device_id\":\"\",\"fault\":\"out_of_range\",\"active\":true"
+    ));
+    assert!(!json.contains("until"));
+    assert_eq!(serde_json::from_str::<HealthAlert>(&json).unwrap(), alert);
+}
@@ -0,0 +1,249 @@
+use crate::clock;
+use crate::models::{ProcessedState, RawReading};
+use crate::pipeline::Pipeline;
+use crate::quarantine::Quarantine;
+use crate::source::{check_line, LineError};
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use logic::ActivityState;
+use serde::{Deserialize, Serialize};
+
+// A gateway uploading every few minutes at 10Hz sends a few thousand readings
+const MAX_BATCH_SIZE: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+pub struct IngestParams {
+    pub device: String,
+}
+
+/// Outcome for one reading of the batch, in upload order
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ItemResult {
+    pub index: usize,
+    pub accepted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<ActivityState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timer: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct IngestResponse {
+    pub device: String,
+    pub accepted: usize,
+    pub rejected: usize,
+    pub results: Vec<ItemResult>,
+}
+
+/// Splits the body into items. A body starting with '[' is a JSON array,
+/// anything else is NDJSON (one reading per line, blank lines ignored).
+/// Only an unreadable array fails the whole batch; bad items are reported per item.
+fn parse_batch(body: &str) -> Result<Vec<Result<RawReading, String>>, String> {
+    let body = body.trim();
+
+    if body.starts_with('[') {
+        let values: Vec<serde_json::Value> =
+            serde_json::from_str(body).map_err(|e| format!("invalid JSON array: {}", e))?;
+        Ok(values
+            .into_iter()
+            .map(|v| serde_json::from_value::<RawReading>(v).map_err(|e| e.to_string()))
+            .collect())
+    } else {
+        Ok(body
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_str::<RawReading>(line).map_err(|e| e.to_string()))
+            .collect())
+    }
+}
+
+/// The batch split back into one raw line per reading, for the capture file
+fn raw_lines(body: &str) -> Vec<String> {
+    let body = body.trim();
+    match serde_json::from_str::<Vec<serde_json::Value>>(body) {
+        Ok(values) if body.starts_with('[') => values.iter().map(|v| v.to_string()).collect(),
+        _ => body.lines().map(str::to_string).collect(),
+    }
+}
+
+/// Sanity checks the Arduino format can't express in serde
+fn validate(reading: &RawReading) -> Result<(), String> {
+    let parts: Vec<&str> = reading.ts.split(':').collect();
+    let valid_ts = parts.len() == 3
+        && parts
+            .iter()
+            .all(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_digit()))
+        && parts[0] < "24"
+        && parts[1] < "60"
+        && parts[2] < "60";
+    if !valid_ts {
+        return Err(format!(
+            "invalid timestamp '{}', expected HH:MM:SS",
+            reading.ts
+        ));
+    }
+    if reading.pir != 0 && reading.pir != 1 {
+        return Err(format!("invalid pir {}, expected 0 or 1", reading.pir));
+    }
+    if !reading.acc.is_finite() || reading.acc < 0.0 {
+        return Err(format!("invalid acc {}", reading.acc));
+    }
+    if let Some(imu) = &reading.imu {
+        let axes = [imu.ax, imu.ay, imu.az];
+        let gyro = [imu.gx, imu.gy, imu.gz];
+        if !axes
+            .iter()
+            .chain(gyro.iter().flatten())
+            .all(|v| v.is_finite())
+        {
+            return Err("invalid IMU axes".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Runs the valid items through the pipeline in timestamp order and returns
+/// per-item results (upload order) plus the processed outputs (timestamp order)
+fn process_batch(
+    pipeline: &mut Pipeline,
+    device: &str,
+    items: Vec<Result<RawReading, String>>,
+) -> (Vec<ItemResult>, Vec<ProcessedState>) {
+    let mut results: Vec<Option<ItemResult>> = Vec::with_capacity(items.len());
+    let mut valid: Vec<(usize, RawReading)> = Vec::new();
+
+    for (index, item) in items.into_iter().enumerate() {
+        match item.and_then(|reading| validate(&reading).map(|_| reading)) {
+            Ok(reading) => {
+                results.push(None);
+                valid.push((index, reading));
+            }
+            Err(error) => results.push(Some(ItemResult {
+                index,
+                accepted: false,
+                error: Some(error),
+                state: None,
+                timer: None,
+            })),
+        }
+    }
+
+    // Stable sort keeps upload order for samples within the same second; times
+    // are ordered around the first upload so a batch may span midnight
+    let first = valid.first().map(|(_, r)| r.ts.clone()).unwrap_or_default();
+    valid.sort_by_key(|(_, r)| clock::order_key(&r.ts, &first));
+
+    let mut outputs = Vec::with_capacity(valid.len());
+    for (index, mut reading) in valid {
+        reading.device = Some(device.to_string());
+        let output = pipeline.process(reading);
+        results[index] = Some(ItemResult {
+            index,
+            accepted: true,
+            error: None,
+            state: Some(output.state),
+            timer: Some(output.timer),
+        });
+        outputs.push(output);
+    }
+
+    (results.into_iter().flatten().collect(), outputs)
+}
+
+/// Counts the batch per device and quarantines the rejected items with their raw payload
+fn account(quarantine: &Quarantine, device: &str, body: &str, results: &[ItemResult]) {
+    let source = format!("http://{}", device);
+    let payloads: Vec<String> = raw_lines(body)
+        .into_iter()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    for result in results {
+        if result.accepted {
+            quarantine.parsed(device);
+            continue;
+        }
+        let payload = payloads.get(result.index).map_or("", String::as_str);
+        let reason = result.error.clone().unwrap_or_default();
+        let error = match check_line(payload) {
+            Err(LineError::Partial(_)) => LineError::Partial(reason),
+            _ => LineError::Invalid(reason),
+        };
+        quarantine.reject(device, &source, payload, &error);
+    }
+}
+
+// POST /api/ingest?device=<id>
+pub async fn ingest_batch(
+    State(state): State<AppState>,
+    Query(params): Query<IngestParams>,
+    body: String,
+) -> Result<Json<IngestResponse>, (StatusCode, String)> {
+    let device = params.device.trim().to_string();
+    if device.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "device must not be empty".to_string(),
+        ));
+    }
+
+    if state.sink.capture().is_some() {
+        let source = format!("http://{}", device);
+        for line in raw_lines(&body) {
+            state.sink.record_raw(&source, &line);
+        }
+    }
+
+    let items = parse_batch(&body).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    if items.len() > MAX_BATCH_SIZE {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "batch of {} exceeds {} readings",
+                items.len(),
+                MAX_BATCH_SIZE
+            ),
+        ));
+    }
+
+    // Pipeline state persists between uploads so the timer carries over
+    let (results, outputs, quality, health) = {
+        let mut pipelines = state.pipelines.lock().unwrap();
+        let pipeline = pipelines
+            .entry(device.clone())
+            .or_insert_with(|| state.sink.new_pipeline(&device));
+        let (results, outputs) = process_batch(pipeline, &device, items);
+        (
+            results,
+            outputs,
+            pipeline.take_quality(),
+            pipeline.take_health(),
+        )
+    };
+
+    account(&state.sink.quarantine(), &device, &body, &results);
+    for output in &outputs {
+        state.sink.publish(output);
+    }
+    let source = format!("http://{}", device);
+    state.sink.publish_quality(&source, &device, quality);
+    state.sink.publish_health(&source, &device, health);
+
+    let accepted = outputs.len();
+    Ok(Json(IngestResponse {
+        device,
+        accepted,
+        rejected: results.len() - accepted,
+        results,
+    }))
+}
+
+#[cfg(test)]
+#[path = "ingest_tests.rs"]
+mod tests;
@@ -0,0 +1,171 @@
+use super::*;
+use crate::source::parse_line;
+
+fn reading(ts: &str, pir: i32, acc: f32) -> RawReading {
+    RawReading {
+        ts: ts.to_string(),
+        pir,
+        acc,
+        device: None,
+        imu: None,
+        received_at: None,
+    }
+}
+
+// parse_batch Tests
+
+#[test]
+fn test_parse_batch_json_array() {
+    let body = r#"[{"ts":"10:00:00","pir":0,"acc":0.01},{"ts":"10:00:01","pir":1}]"#;
+    let items = parse_batch(body).unwrap();
+
+    assert_eq!(items.len(), 2);
+    assert!(items[0].is_ok());
+    assert!(items[1].as_ref().unwrap_err().contains("acc"));
+}
+
+#[test]
+fn test_parse_batch_ndjson() {
+    let body = "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}\n\nnot json\n{\"ts\":\"10:00:01\",\"pir\":0,\"acc\":0.02}\n";
+    let items = parse_batch(body).unwrap();
+
+    assert_eq!(items.len(), 3);
+    assert!(items[0].is_ok());
+    assert!(items[1].is_err());
+    assert!(items[2].is_ok());
+}
+
+#[test]
+fn test_parse_batch_broken_array() {
+    assert!(parse_batch(r#"[{"ts":"10:00:00","#).is_err());
+}
+
+#[test]
+fn test_raw_lines_splits_array_and_ndjson() {
+    let array = "[{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01},\n {\"ts\":\"10:00:01\",\"pir\":1,\"acc\":0.2}]";
+    let lines = raw_lines(array);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(parse_line(&lines[1]).unwrap().ts, "10:00:01");
+
+    let ndjson = "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}\nnot json\n";
+    assert_eq!(
+        raw_lines(ndjson),
+        vec![
+            "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}".to_string(),
+            "not json".to_string(),
+        ]
+    );
+}
+
+// validate Tests
+
+#[test]
+fn test_validate_accepts_arduino_reading() {
+    assert!(validate(&reading("23:59:59", 1, 0.0)).is_ok());
+}
+
+#[test]
+fn test_validate_rejects_bad_values() {
+    assert!(validate(&reading("24:00:00", 0, 0.01)).is_err());
+    assert!(validate(&reading("10:60:00", 0, 0.01)).is_err());
+    assert!(validate(&reading("1:00:00", 0, 0.01)).is_err());
+    assert!(validate(&reading("10:00:00", 2, 0.01)).is_err());
+    assert!(validate(&reading("10:00:00", 0, -0.01)).is_err());
+    assert!(validate(&reading("10:00:00", 0, f32::NAN)).is_err());
+}
+
+// process_batch Tests
+
+#[test]
+fn test_process_batch_runs_in_timestamp_order() {
+    let mut pipeline = Pipeline::new();
+    let items = vec![
+        Ok(reading("10:00:02", 0, 0.0)),
+        Ok(reading("10:00:00", 0, 0.0)),
+        Err("bad line".to_string()),
+        Ok(reading("10:00:01", 0, 0.0)),
+    ];
+
+    let (results, outputs) = process_batch(&mut pipeline, "gw-1", items);
+
+    // Outputs follow device time, results follow upload order
+    let stamps: Vec<&str> = outputs.iter().map(|o| o.timestamp.as_str()).collect();
+    assert_eq!(stamps, vec!["10:00:00", "10:00:01", "10:00:02"]);
+
+    assert_eq!(results.len(), 4);
+    assert_eq!(results[0].timer, Some(2));
+    assert_eq!(results[1].timer, Some(0));
+    assert!(!results[2].accepted);
+    assert_eq!(results[2].error.as_deref(), Some("bad line"));
+    assert_eq!(results[3].timer, Some(1));
+}
+
+#[test]
+fn test_process_batch_spans_midnight() {
+    let mut pipeline = Pipeline::new();
+    let items = vec![
+        Ok(reading("23:59:59", 0, 0.0)),
+        Ok(reading("00:00:01", 0, 0.0)),
+        Ok(reading("00:00:00", 0, 0.0)),
+    ];
+
+    let (_, outputs) = process_batch(&mut pipeline, "gw-1", items);
+
+    let stamps: Vec<&str> = outputs.iter().map(|o| o.timestamp.as_str()).collect();
+    assert_eq!(stamps, vec!["23:59:59", "00:00:00", "00:00:01"]);
+    let times: Vec<_> = outputs
+        .iter()
+        .map(|o| o.timing.as_ref().unwrap().device_time.unwrap())
+        .collect();
+    assert!(times.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn test_process_batch_rejects_invalid_readings() {
+    let mut pipeline = Pipeline::new();
+    let items = vec![
+        Ok(reading("10:00:00", 5, 0.0)),
+        Ok(reading("10:00:01", 0, 0.0)),
+    ];
+
+    let (results, outputs) = process_batch(&mut pipeline, "gw-1", items);
+
+    assert_eq!(outputs.len(), 1);
+    assert!(!results[0].accepted);
+    assert!(results[0].error.as_ref().unwrap().contains("pir"));
+    assert!(results[1].accepted);
+}
+
+#[test]
+fn test_process_batch_keeps_state_between_uploads() {
+    let mut pipeline = Pipeline::new();
+    process_batch(&mut pipeline, "gw-1", vec![Ok(reading("10:00:00", 0, 0.0))]);
+    let (results, _) = process_batch(&mut pipeline, "gw-1", vec![Ok(reading("10:00:01", 0, 0.0))]);
+
+    assert_eq!(results[0].timer, Some(1));
+}
+
+#[test]
+fn test_account_quarantines_rejected_items() {
+    let (tx, mut rx) = tokio::sync::broadcast::channel(4);
+    let quarantine = Quarantine::new(tx);
+    let body = "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.01}\n\n{\"ts\":\"10:00:01\",\"pir\":0,\n{\"ts\":\"10:00:02\",\"pir\":7,\"acc\":0.01}\n";
+    let mut pipeline = Pipeline::new();
+    let (results, _) = process_batch(&mut pipeline, "gw-1", parse_batch(body).unwrap());
+
+    account(&quarantine, "gw-1", body, &results);
+
+    let counts = &quarantine.counts()["gw-1"];
+    assert_eq!(counts.parsed, 1);
+    assert_eq!(counts.partial, 1);
+    assert_eq!(counts.rejected, 1);
+    // Payloads line up with the items despite the blank line
+    let partial: crate::quarantine::QuarantinedLine =
+        serde_json::from_str(&rx.try_recv().unwrap()).unwrap();
+    assert_eq!(partial.payload, "{\"ts\":\"10:00:01\",\"pir\":0,");
+    assert_eq!(partial.source, "http://gw-1");
+    let invalid: crate::quarantine::QuarantinedLine =
+        serde_json::from_str(&rx.try_recv().unwrap()).unwrap();
+    assert!(invalid.payload.contains("\"pir\":7"));
+    assert!(invalid.reason.contains("pir"));
+}
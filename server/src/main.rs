@@ -1,19 +1,46 @@
-use axum::{routing::get, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
 use dotenvy::dotenv;
+use std::collections::HashMap;
 use std::env;
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 use tower_http::services::ServeDir;
 
+mod cache;
+mod calibration;
+mod capture;
+mod classifier;
+mod clock;
+mod commands;
 mod db_worker;
 mod fhir;
+mod framing;
+mod handshake;
+mod health;
+mod ingest;
+mod metrics;
 mod models;
+mod mqtt;
+mod network;
+mod pipeline;
+mod quality;
+mod quarantine;
 mod serial;
+mod settings;
+mod source;
 mod state;
+mod suggestions;
+mod timeline;
 mod websocket;
 
 use state::AppState;
 
+const BROADCAST_CAPACITY: usize = 1024; // Messages a receiver may fall behind
+
 #[tokio::main]
 async fn main() {
     dotenv().ok();
@@ -35,21 +62,58 @@ async fn main() {
     println!("Redis client created.");
 
     //  Create the Broadcast Channel
-    let (tx, _rx) = broadcast::channel(100);
+    // Slow receivers miss the oldest messages (counted in /api/metrics) instead of stalling ingestion
+    let (tx, _rx) = broadcast::channel(BROADCAST_CAPACITY);
 
     //  Start Background Tasks/Data Pipeline
 
-    // Serial Listener Input - Pass Redis client for caching
-    serial::spawn_serial_listener(tx.clone(), redis_client.clone());
+    // Processed output fan-out (batched Redis cache writer + broadcast), shared by all sources
+    // Raw lines are optionally recorded to CAPTURE_FILE for later replay
+    let sink = pipeline::Sink::new(tx.clone(), redis_client.clone())
+        .with_capture(capture::Capture::from_env());
+
+    // Per-device thresholds and alert limit, changed live through the settings API
+    match sink.settings().load(&pool).await {
+        Ok(count) => println!("Loaded settings for {} device(s)", count),
+        Err(e) => eprintln!("Failed to load device settings, using defaults: {}", e),
+    }
+    // Calibrations only run in memory, so interrupted ones can't finish
+    match calibration::abandon_running(&pool).await {
+        Ok(0) => {}
+        Ok(count) => println!("Marked {} interrupted calibration(s) as failed", count),
+        Err(e) => eprintln!("Failed to close interrupted calibrations: {}", e),
+    }
+
+    // Device command queues, written back over the serial link (API enabled via COMMAND_TOKEN)
+    let commands = commands::Commands::from_env();
+
+    // Serial Listener Input
+    serial::spawn_serial_listener(sink.clone(), commands.clone());
+
+    // Network Input (WiFi boards over TCP/UDP, enabled via TCP_INGEST_ADDR / UDP_INGEST_ADDR)
+    network::spawn_network_listeners(network::NetworkConfig::from_env(), sink.clone()).await;
+
+    // MQTT Input (enabled via MQTT_URL)
+    if let Some(config) = mqtt::MqttConfig::from_env() {
+        mqtt::spawn_mqtt_subscriber(config, sink.clone()).await;
+    }
+
+    // Capture Replay (enabled via REPLAY_FILE)
+    if let Some(config) = capture::ReplayConfig::from_env() {
+        capture::spawn_replay(config, sink.clone());
+    }
 
     // DB Worker/Storage
-    db_worker::spawn_db_worker(pool.clone(), tx.subscribe()).await;
+    db_worker::spawn_db_worker(pool.clone(), tx.subscribe(), sink.metrics()).await;
 
     //  Build the Application State
     let app_state = AppState {
         db: pool,
         tx,
         redis: redis_client,
+        pipelines: Arc::new(Mutex::new(HashMap::new())),
+        sink,
+        commands,
     };
 
     //  Define Routes
@@ -61,6 +125,51 @@ async fn main() {
             "/api/fhir/observation/latest",
             get(fhir::get_latest_observation),
         )
+        // Device Registry (latest hello per device)
+        .route("/api/devices", get(handshake::list_devices))
+        .route("/api/devices/:id", get(handshake::get_device))
+        // Device Commands (bearer token)
+        .route(
+            "/api/devices/:id/commands",
+            get(commands::list_commands).post(commands::send_command),
+        )
+        // Per-device thresholds and alert limit (changes need the bearer token, audited)
+        .route(
+            "/api/devices/:id/settings",
+            get(settings::get_settings).put(settings::put_settings),
+        )
+        .route(
+            "/api/devices/:id/settings/history",
+            get(settings::settings_history),
+        )
+        // Guided calibration: sit still, fidget, walk (start/cancel need the bearer token)
+        .route(
+            "/api/devices/:id/calibration",
+            get(calibration::get_calibration)
+                .post(calibration::start_calibration)
+                .delete(calibration::cancel_calibration),
+        )
+        // Stored records with their real-time and HMM-smoothed states
+        .route("/api/devices/:id/timeline", get(timeline::get_timeline))
+        // Nightly threshold suggestions (review needs the bearer token)
+        .route("/api/suggestions", get(suggestions::list_suggestions))
+        .route(
+            "/api/suggestions/:id/apply",
+            post(suggestions::apply_suggestion),
+        )
+        .route(
+            "/api/suggestions/:id/dismiss",
+            post(suggestions::dismiss_suggestion),
+        )
+        // Malformed input (bad payloads and per-device line counters)
+        .route("/api/quarantine", get(quarantine::list_quarantine))
+        .route("/api/quarantine/stats", get(quarantine::quarantine_stats))
+        // Ingestion stage counters (queued, dropped, cache writes)
+        .route("/api/metrics", get(metrics::get_metrics))
+        // Active classifier and thresholds (nightly job, dashboard)
+        .route("/api/classifier", get(classifier::get_classifier))
+        // Batch Ingestion (gateways, integration tests)
+        .route("/api/ingest", post(ingest::ingest_batch))
         // Health Check
         .route("/health", get(|| async { "Status: Healthy" }))
         // Frontend Hosting
@@ -0,0 +1,122 @@
+use crate::state::AppState;
+use axum::{extract::State, response::Json};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Counters of the ingestion stages since startup, one set shared by every source.
+/// Each bounded queue has an explicit policy, and whatever it drops is counted here.
+#[derive(Debug, Default)]
+struct Counters {
+    readings_queued: AtomicU64,  // Read stage -> classify stage
+    readings_dropped: AtomicU64, // Read queue full (classification fell behind)
+    readings_processed: AtomicU64,
+    cache_written: AtomicU64, // Cache writes stored in Redis
+    cache_batches: AtomicU64, // Pipelined round trips
+    cache_dropped: AtomicU64, // Cache queue full, or Redis down
+    cache_errors: AtomicU64,
+    broadcast_lagged: AtomicU64, // Messages a slow WebSocket client or the DB worker missed
+}
+
+/// Shared handle on the stage counters
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    counters: Arc<Counters>,
+}
+
+/// Counters as returned by GET /api/metrics
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MetricsSnapshot {
+    pub readings_queued: u64,
+    pub readings_dropped: u64,
+    pub readings_processed: u64,
+    pub cache_written: u64,
+    pub cache_batches: u64,
+    pub cache_dropped: u64,
+    pub cache_errors: u64,
+    pub broadcast_lagged: u64,
+}
+
+// Logs the first drop and then every thousandth, so a stall is visible without flooding the log
+fn report(what: &str, before: u64, dropped: u64) {
+    let total = before + dropped;
+    if before == 0 || before / 1000 != total / 1000 {
+        eprintln!("{}: {} dropped so far", what, total);
+    }
+}
+
+impl Metrics {
+    pub fn reading_queued(&self) {
+        self.counters
+            .readings_queued
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn reading_dropped(&self) {
+        let before = self
+            .counters
+            .readings_dropped
+            .fetch_add(1, Ordering::Relaxed);
+        report("Read queue full, readings", before, 1);
+    }
+
+    pub fn reading_processed(&self) {
+        self.counters
+            .readings_processed
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cache_written(&self, writes: u64) {
+        self.counters
+            .cache_written
+            .fetch_add(writes, Ordering::Relaxed);
+        self.counters.cache_batches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn cache_dropped(&self, writes: u64) {
+        let before = self
+            .counters
+            .cache_dropped
+            .fetch_add(writes, Ordering::Relaxed);
+        report("Redis cache writes", before, writes);
+    }
+
+    pub fn cache_error(&self) {
+        self.counters.cache_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn broadcast_lagged(&self, who: &str, missed: u64) {
+        let total = self
+            .counters
+            .broadcast_lagged
+            .fetch_add(missed, Ordering::Relaxed)
+            + missed;
+        eprintln!(
+            "{} fell behind, missed {} messages ({} so far)",
+            who, missed, total
+        );
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let c = &self.counters;
+        MetricsSnapshot {
+            readings_queued: c.readings_queued.load(Ordering::Relaxed),
+            readings_dropped: c.readings_dropped.load(Ordering::Relaxed),
+            readings_processed: c.readings_processed.load(Ordering::Relaxed),
+            cache_written: c.cache_written.load(Ordering::Relaxed),
+            cache_batches: c.cache_batches.load(Ordering::Relaxed),
+            cache_dropped: c.cache_dropped.load(Ordering::Relaxed),
+            cache_errors: c.cache_errors.load(Ordering::Relaxed),
+            broadcast_lagged: c.broadcast_lagged.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// GET /api/metrics
+pub async fn get_metrics(State(state): State<AppState>) -> Json<MetricsSnapshot> {
+    Json(state.sink.metrics().snapshot())
+}
+
+#[cfg(test)]
+#[path = "metrics_tests.rs"]
+mod tests;
@@ -0,0 +1,37 @@
+use super::*;
+
+#[test]
+fn test_counters_are_shared_between_clones() {
+    let metrics = Metrics::default();
+    let other = metrics.clone();
+
+    metrics.reading_queued();
+    other.reading_queued();
+    other.reading_dropped();
+    metrics.reading_processed();
+    metrics.cache_written(3);
+    metrics.cache_written(2);
+    other.cache_dropped(4);
+    other.cache_error();
+    metrics.broadcast_lagged("DB worker", 7);
+
+    assert_eq!(
+        metrics.snapshot(),
+        MetricsSnapshot {
+            readings_queued: 2,
+            readings_dropped: 1,
+            readings_processed: 1,
+            cache_written: 5,
+            cache_batches: 2,
+            cache_dropped: 4,
+            cache_errors: 1,
+            broadcast_lagged: 7,
+        }
+    );
+}
+
+#[test]
+fn test_snapshot_format() {
+    let json = serde_json::to_string(&Metrics::default().snapshot()).unwrap();
+    assert!(json.starts_with("{\"readings_queued\":0,\"readings_dropped\":0"));
+}
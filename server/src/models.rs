@@ -1,23 +1,199 @@
+use chrono::{DateTime, Utc};
+use logic::{ActivityState, SignalFeatures};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 // 1. RAW INPUT From Arduino
 // Format: {"ts":"12:34:56","pir":0,"acc":0.045}
+// Extended: {"ts":"12:34:56","pir":0,"ax":0.01,"ay":-0.02,"az":0.98,"gx":1.5,"gy":-0.3,"gz":0.1}
+// Either "acc" or all of ax/ay/az must be present; gyro is optional
 #[derive(Debug, Deserialize, PartialEq)]
+#[serde(try_from = "WireReading")]
 pub struct RawReading {
-    pub ts: String, // Timestamp from RTC (HH:MM:SS)
-    pub pir: i32,   // PIR sensor (0 or 1)
-    pub acc: f32,   // Acceleration delta magnitude
+    pub ts: String,                         // Timestamp from RTC (HH:MM:SS)
+    pub pir: i32,                           // PIR sensor (0 or 1)
+    pub acc: f32, // Acceleration delta magnitude (computed server-side when axes are sent)
+    pub device: Option<String>, // Optional board id (network boards), e.g. "esp32-desk-3"
+    pub imu: Option<Imu>, // Raw axes from the extended format
+    pub received_at: Option<DateTime<Utc>>, // Arrival time when not "now" (capture replay)
+}
+
+// Raw MPU6050 axes
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Imu {
+    pub ax: f32, // Acceleration (g)
+    pub ay: f32,
+    pub az: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gx: Option<f32>, // Angular rate (deg/s), optional
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gy: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gz: Option<f32>,
+}
+
+impl Imu {
+    // sqrt(x^2 + y^2 + z^2), ~1.0 at rest
+    pub fn magnitude(&self) -> f32 {
+        (self.ax * self.ax + self.ay * self.ay + self.az * self.az).sqrt()
+    }
+}
+
+// On-the-wire shape of both reading formats
+#[derive(Deserialize)]
+struct WireReading {
+    ts: String,
+    pir: i32,
+    acc: Option<f32>,
+    #[serde(default)]
+    device: Option<String>,
+    #[serde(flatten)]
+    imu: Option<Imu>,
+}
+
+impl TryFrom<WireReading> for RawReading {
+    type Error = String;
+
+    fn try_from(wire: WireReading) -> Result<Self, String> {
+        if wire.acc.is_none() && wire.imu.is_none() {
+            return Err("reading needs either acc or ax/ay/az".to_string());
+        }
+        Ok(RawReading {
+            ts: wire.ts,
+            pir: wire.pir,
+            acc: wire.acc.unwrap_or(0.0),
+            device: wire.device,
+            imu: wire.imu,
+            received_at: None,
+        })
+    }
 }
 
 // 2. PROCESSED OUTPUT (To Frontend & DB)
 // Classification is done server-side in serial.rs
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ProcessedState {
-    pub state: String,     // "ACTIVE", "FIDGET", "SEDENTARY"
-    pub timer: u64,        // Inactive seconds
-    pub val: f32,          // Smoothed acceleration value
-    pub alert: bool,       // Trigger alert?
-    pub timestamp: String, // Timestamp from Arduino
+    #[serde(default)]
+    pub device_id: String, // Hello id, board id or serial port the reading came from
+    pub state: ActivityState, // "ACTIVE", "FIDGET", "SEDENTARY"
+    pub timer: u64,           // Inactive seconds
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub unknown_sec: u64, // Data gaps within the timer span, neither counted nor reset
+    pub val: f32,             // Smoothed acceleration value
+    pub alert: bool,          // Trigger alert?
+    pub timestamp: String,    // Timestamp from Arduino
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faults: Vec<SensorFault>, // Sensor problems affecting this record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub magnitude: Option<f32>, // Acceleration magnitude (g), extended format only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imu: Option<Imu>, // Raw axes, kept for downstream features
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features: Option<SignalFeatures>, // Hjorth features of the latest hop (hjorth classifier only)
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>, // Device time resolved onto the server clock
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+// Sensor problems flagged on a record (detected in health.rs)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorFault {
+    StuckAccelerometer, // Acceleration unchanged for hours (frozen MPU6050)
+    StuckPir,           // PIR high without a break, ignored for classification
+    OutOfRange,         // NaN, negative or implausibly large values, left out of smoothing
+    NonMonotonic,       // Device time went backwards
+}
+
+impl SensorFault {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SensorFault::StuckAccelerometer => "stuck_accelerometer",
+            SensorFault::StuckPir => "stuck_pir",
+            SensorFault::OutOfRange => "out_of_range",
+            SensorFault::NonMonotonic => "non_monotonic",
+        }
+    }
+}
+
+// When a reading was taken, from the device RTC (HH:MM:SS) and the arrival time
+// Format: "measured_at":"2026-10-17T10:00:00.250Z","received_at":"...","device_time":"2026-10-17T10:00:03Z","clock_offset_ms":-2750
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Timing {
+    pub measured_at: DateTime<Utc>, // Device time corrected by the clock offset
+    pub received_at: DateTime<Utc>, // Arrival at the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_time: Option<DateTime<Utc>>, // RTC time with the date resolved (None if ts is unreadable)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_offset_ms: Option<i64>, // Server clock minus device clock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock_drift_ppm: Option<f64>, // Device clock rate error, positive = running fast
+}
+
+// 3. DEVICE STATUS (To Frontend)
+// Published whenever the Arduino (or a network board) connects or drops
+// Format: {"event":"device_status","connected":false,"port":"/dev/ttyACM0","detail":"...","retry_in_sec":4}
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeviceStatus {
+    pub event: String,             // Always "device_status"
+    pub connected: bool,           // Is the serial link up?
+    pub port: String,              // Port name (resolved when using USB discovery)
+    pub detail: String,            // Reason for the last disconnect
+    pub retry_in_sec: Option<u64>, // Next reconnect attempt, when disconnected
+}
+
+impl DeviceStatus {
+    pub fn connected(port: &str) -> Self {
+        DeviceStatus {
+            event: "device_status".to_string(),
+            connected: true,
+            port: port.to_string(),
+            detail: String::new(),
+            retry_in_sec: None,
+        }
+    }
+
+    // retry_in is None for sources the server can't reconnect itself (network boards)
+    pub fn disconnected(port: &str, detail: &str, retry_in: Option<Duration>) -> Self {
+        DeviceStatus {
+            event: "device_status".to_string(),
+            connected: false,
+            port: port.to_string(),
+            detail: detail.to_string(),
+            retry_in_sec: retry_in.map(|d| d.as_secs()),
+        }
+    }
+}
+
+// 4. LINK STATS (To Frontend)
+// Per-port counters for the serial protocol, so bad input is visible instead of silently dropped
+// Format: {"event":"link_stats","port":"/dev/ttyACM0","protocol":"binary","readings":1200,"text_lines":2,"malformed":0,"corrupted":3}
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LinkStats {
+    pub event: String,    // Always "link_stats"
+    pub port: String,     // Port name
+    pub protocol: String, // "unknown" until the first good reading, then "json" or "binary"
+    pub readings: u64,    // Good readings (either protocol)
+    pub text_lines: u64,  // Non-JSON text lines (boot messages, debug prints)
+    pub malformed: u64,   // JSON lines that are not a valid reading
+    pub corrupted: u64,   // Binary frames failing the COBS, length or CRC checks
+}
+
+impl LinkStats {
+    pub fn new(port: &str) -> Self {
+        LinkStats {
+            event: "link_stats".to_string(),
+            port: port.to_string(),
+            protocol: "unknown".to_string(),
+            readings: 0,
+            text_lines: 0,
+            malformed: 0,
+            corrupted: 0,
+        }
+    }
 }
 
 #[cfg(test)]
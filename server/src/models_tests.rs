@@ -1,4 +1,5 @@
 use super::*;
+use logic::ActivityState;
 
 // RawReading Tests
 // Tests for Arduino input format: {"ts":"12:34:56","pir":0,"acc":0.045}
@@ -39,22 +40,108 @@ fn test_raw_reading_high_acceleration() {
     assert!((reading.acc - 2.5).abs() < 0.001);
 }
 
+#[test]
+fn test_raw_reading_without_device_id() {
+    let json = r#"{"ts": "12:00:00", "pir": 0, "acc": 0.01}"#;
+    let reading: RawReading = serde_json::from_str(json).unwrap();
+
+    assert_eq!(reading.device, None);
+}
+
+#[test]
+fn test_raw_reading_with_device_id() {
+    let json = r#"{"ts": "12:00:00", "pir": 0, "acc": 0.01, "device": "esp32-desk-3"}"#;
+    let reading: RawReading = serde_json::from_str(json).unwrap();
+
+    assert_eq!(reading.device.as_deref(), Some("esp32-desk-3"));
+}
+
+#[test]
+fn test_raw_reading_legacy_has_no_axes() {
+    let json = r#"{"ts": "12:00:00", "pir": 0, "acc": 0.01}"#;
+    let reading: RawReading = serde_json::from_str(json).unwrap();
+
+    assert_eq!(reading.imu, None);
+}
+
+#[test]
+fn test_raw_reading_extended_axes() {
+    let json = r#"{"ts": "12:00:00", "pir": 0, "ax": 0.6, "ay": 0.0, "az": 0.8}"#;
+    let reading: RawReading = serde_json::from_str(json).unwrap();
+    let imu = reading.imu.unwrap();
+
+    assert_eq!(imu.ax, 0.6);
+    assert_eq!(imu.gx, None);
+    assert!((imu.magnitude() - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_raw_reading_extended_with_gyro() {
+    let json = r#"{"ts": "12:00:00", "pir": 1, "ax": 0.0, "ay": 0.0, "az": 1.0, "gx": 1.5, "gy": -0.3, "gz": 0.1}"#;
+    let reading: RawReading = serde_json::from_str(json).unwrap();
+    let imu = reading.imu.unwrap();
+
+    assert_eq!(imu.gx, Some(1.5));
+    assert_eq!(imu.gy, Some(-0.3));
+    assert_eq!(imu.gz, Some(0.1));
+}
+
+#[test]
+fn test_raw_reading_requires_acc_or_all_axes() {
+    assert!(serde_json::from_str::<RawReading>(r#"{"ts": "12:00:00", "pir": 0}"#).is_err());
+    // Partial axes are not an extended reading
+    assert!(serde_json::from_str::<RawReading>(
+        r#"{"ts": "12:00:00", "pir": 0, "ax": 0.1, "ay": 0.2}"#
+    )
+    .is_err());
+}
+
+#[test]
+fn test_processed_state_omits_missing_axes() {
+    let state = ProcessedState {
+        device_id: "desk-3".to_string(),
+        state: ActivityState::Sedentary,
+        timer: 1,
+        unknown_sec: 0,
+        val: 0.01,
+        alert: false,
+        timestamp: "12:00:00".to_string(),
+        faults: vec![],
+        magnitude: None,
+        imu: None,
+        features: None,
+        timing: None,
+    };
+
+    let json = serde_json::to_string(&state).unwrap();
+    assert!(!json.contains("magnitude"));
+    assert!(!json.contains("imu"));
+}
+
 // ProcessedState Tests
 
 #[test]
 fn test_processed_state_serialization() {
     let state = ProcessedState {
-        state: "SEDENTARY".to_string(),
+        device_id: "desk-3".to_string(),
+        state: ActivityState::Sedentary,
         timer: 600,
+        unknown_sec: 0,
         val: 0.02,
         alert: true,
         timestamp: "2026-01-06T10:00:00Z".to_string(),
+        faults: vec![],
+        magnitude: None,
+        imu: None,
+        features: None,
+        timing: None,
     };
 
     let json = serde_json::to_string(&state).unwrap();
     assert!(json.contains("\"state\":\"SEDENTARY\""));
     assert!(json.contains("\"timer\":600"));
     assert!(json.contains("\"alert\":true"));
+    assert!(json.starts_with("{\"device_id\":\"desk-3\","));
 }
 
 #[test]
@@ -68,20 +155,29 @@ fn test_processed_state_deserialization() {
     }"#;
 
     let state: ProcessedState = serde_json::from_str(json).unwrap();
-    assert_eq!(state.state, "ACTIVE");
+    assert_eq!(state.state, ActivityState::Active);
     assert_eq!(state.timer, 0);
     assert!(!state.alert);
+    // Cached before readings were tagged
+    assert_eq!(state.device_id, "");
 }
 
 #[test]
 fn test_processed_state_alert_threshold() {
     // Test case for alert being true (sedentary for too long)
     let state = ProcessedState {
-        state: "SEDENTARY".to_string(),
+        device_id: "desk-3".to_string(),
+        state: ActivityState::Sedentary,
         timer: 1800, // 30 minutes
+        unknown_sec: 0,
         val: 0.01,
         alert: true,
         timestamp: "2026-01-06T10:30:00Z".to_string(),
+        faults: vec![],
+        magnitude: None,
+        imu: None,
+        features: None,
+        timing: None,
     };
 
     assert!(state.alert);
@@ -91,11 +187,18 @@ fn test_processed_state_alert_threshold() {
 #[test]
 fn test_processed_state_no_alert() {
     let state = ProcessedState {
-        state: "FIDGET".to_string(),
+        device_id: "desk-3".to_string(),
+        state: ActivityState::Fidget,
         timer: 60,
+        unknown_sec: 0,
         val: 0.2,
         alert: false,
         timestamp: "2026-01-06T10:01:00Z".to_string(),
+        faults: vec![],
+        magnitude: None,
+        imu: None,
+        features: None,
+        timing: None,
     };
 
     assert!(!state.alert);
@@ -104,11 +207,18 @@ fn test_processed_state_no_alert() {
 #[test]
 fn test_processed_state_clone() {
     let state = ProcessedState {
-        state: "ACTIVE".to_string(),
+        device_id: "desk-3".to_string(),
+        state: ActivityState::Active,
         timer: 0,
+        unknown_sec: 0,
         val: 1.5,
         alert: false,
         timestamp: "2026-01-06T10:00:00Z".to_string(),
+        faults: vec![],
+        magnitude: None,
+        imu: None,
+        features: None,
+        timing: None,
     };
 
     let cloned = state.clone();
@@ -118,11 +228,18 @@ fn test_processed_state_clone() {
 #[test]
 fn test_processed_state_roundtrip() {
     let original = ProcessedState {
-        state: "SEDENTARY".to_string(),
+        device_id: "desk-3".to_string(),
+        state: ActivityState::Sedentary,
         timer: 900,
+        unknown_sec: 0,
         val: 0.05,
         alert: false,
         timestamp: "2026-01-06T10:15:00Z".to_string(),
+        faults: vec![],
+        magnitude: None,
+        imu: None,
+        features: None,
+        timing: None,
     };
 
     let json = serde_json::to_string(&original).unwrap();
@@ -130,3 +247,110 @@ fn test_processed_state_roundtrip() {
 
     assert_eq!(original, restored);
 }
+
+#[test]
+fn test_processed_state_carries_features() {
+    let features = SignalFeatures {
+        mean: 0.01,
+        variance: 0.0004,
+        stationarity_passed: true,
+        hjorth_activity: 0.0004,
+        hjorth_mobility: 1.2,
+        hjorth_complexity: 1.1,
+    };
+    let original = ProcessedState {
+        device_id: "desk-3".to_string(),
+        state: ActivityState::Fidget,
+        timer: 10,
+        unknown_sec: 0,
+        val: 0.01,
+        alert: false,
+        timestamp: "10:00:00".to_string(),
+        faults: vec![],
+        magnitude: None,
+        imu: None,
+        features: Some(features),
+        timing: None,
+    };
+
+    let json = serde_json::to_string(&original).unwrap();
+    assert!(json.contains("\"hjorth_mobility\":1.2"));
+    assert!(json.contains("\"stationarity_passed\":true"));
+    assert_eq!(
+        serde_json::from_str::<ProcessedState>(&json).unwrap(),
+        original
+    );
+}
+
+// DeviceStatus Tests
+
+#[test]
+fn test_device_status_connected() {
+    let status = DeviceStatus::connected("/dev/ttyACM0");
+
+    let json = serde_json::to_string(&status).unwrap();
+    assert!(json.contains("\"event\":\"device_status\""));
+    assert!(json.contains("\"connected\":true"));
+    assert!(json.contains("\"retry_in_sec\":null"));
+}
+
+#[test]
+fn test_device_status_disconnected() {
+    let status = DeviceStatus::disconnected(
+        "/dev/ttyACM0",
+        "device closed the connection",
+        Some(Duration::from_secs(4)),
+    );
+
+    assert!(!status.connected);
+    assert_eq!(status.retry_in_sec, Some(4));
+    assert_eq!(status.detail, "device closed the connection");
+}
+
+#[test]
+fn test_device_status_is_not_a_processed_state() {
+    // The DB worker relies on this to skip status messages on the shared channel
+    let json = serde_json::to_string(&DeviceStatus::connected("/dev/ttyACM0")).unwrap();
+    assert!(serde_json::from_str::<ProcessedState>(&json).is_err());
+}
+
+#[test]
+fn test_processed_state_timing_is_flattened() {
+    let original = ProcessedState {
+        device_id: "desk-3".to_string(),
+        state: ActivityState::Sedentary,
+        timer: 1,
+        unknown_sec: 0,
+        val: 0.01,
+        alert: false,
+        timestamp: "10:00:00".to_string(),
+        faults: vec![],
+        magnitude: None,
+        imu: None,
+        features: None,
+        timing: Some(Timing {
+            measured_at: "2026-10-17T10:00:00.250Z".parse().unwrap(),
+            received_at: "2026-10-17T10:00:00.250Z".parse().unwrap(),
+            device_time: Some("2026-10-17T10:00:03Z".parse().unwrap()),
+            clock_offset_ms: Some(-2750),
+            clock_drift_ppm: None,
+        }),
+    };
+
+    let json = serde_json::to_string(&original).unwrap();
+    assert!(json.contains("\"measured_at\":\"2026-10-17T10:00:00.250Z\""));
+    assert!(json.contains("\"clock_offset_ms\":-2750"));
+    assert!(!json.contains("timing"));
+    assert!(!json.contains("clock_drift_ppm"));
+
+    let restored: ProcessedState = serde_json::from_str(&json).unwrap();
+    assert_eq!(original, restored);
+}
+
+#[test]
+fn test_processed_state_without_timing() {
+    let json = r#"{"state":"ACTIVE","timer":0,"val":1.0,"alert":false,"timestamp":"10:00:00"}"#;
+
+    let state: ProcessedState = serde_json::from_str(json).unwrap();
+    assert_eq!(state.timing, None);
+}
@@ -0,0 +1,193 @@
+use crate::models::DeviceStatus;
+use crate::pipeline::{Pipeline, Sink};
+use crate::serial::Backoff;
+use crate::source::check_line;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+
+// DEFAULTS
+const DEFAULT_CLIENT_ID: &str = "sedentary-tracker";
+const DEFAULT_TOPICS: &str = "sensors/+/reading";
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+
+/// MQTT subscriber settings, read from the environment.
+/// The subscriber is only started when MQTT_URL is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttConfig {
+    pub url: String,              // MQTT_URL, e.g. mqtt://localhost:1883
+    pub client_id: String,        // MQTT_CLIENT_ID
+    pub username: Option<String>, // MQTT_USERNAME
+    pub password: Option<String>, // MQTT_PASSWORD
+    pub qos: QoS,                 // MQTT_QOS (0, 1 or 2)
+    pub topics: Vec<String>,      // MQTT_TOPICS, comma separated filters
+}
+
+impl MqttConfig {
+    pub fn from_env() -> Option<Self> {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    fn from_lookup(get: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let url = get("MQTT_URL").filter(|u| !u.trim().is_empty())?;
+
+        let qos = match get("MQTT_QOS").as_deref().map(str::trim) {
+            None | Some("1") => QoS::AtLeastOnce,
+            Some("0") => QoS::AtMostOnce,
+            Some("2") => QoS::ExactlyOnce,
+            Some(other) => {
+                eprintln!("Invalid MQTT_QOS '{}', using 1", other);
+                QoS::AtLeastOnce
+            }
+        };
+
+        let topics: Vec<String> = get("MQTT_TOPICS")
+            .unwrap_or_else(|| DEFAULT_TOPICS.to_string())
+            .split(',')
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        Some(MqttConfig {
+            url: url.trim().to_string(),
+            client_id: get("MQTT_CLIENT_ID").unwrap_or_else(|| DEFAULT_CLIENT_ID.to_string()),
+            username: get("MQTT_USERNAME"),
+            password: get("MQTT_PASSWORD"),
+            qos,
+            topics,
+        })
+    }
+
+    fn options(&self) -> Result<MqttOptions, String> {
+        // rumqttc takes the client id from the url query string
+        let separator = if self.url.contains('?') { '&' } else { '?' };
+        let url = if self.url.contains("client_id=") {
+            self.url.clone()
+        } else {
+            format!("{}{}client_id={}", self.url, separator, self.client_id)
+        };
+
+        let mut options =
+            MqttOptions::parse_url(url).map_err(|e| format!("{}: {}", self.url, e))?;
+        options.set_keep_alive(KEEP_ALIVE);
+        if let Some(username) = &self.username {
+            options.set_credentials(username, self.password.clone().unwrap_or_default());
+        }
+        Ok(options)
+    }
+}
+
+/// Extracts the device id from a topic: the level matched by the first `+`
+/// of the filter, e.g. "sensors/desk-3/reading" on "sensors/+/reading" gives "desk-3"
+fn device_from_topic(filter: &str, topic: &str) -> Option<String> {
+    let mut device = None;
+    let mut levels = topic.split('/');
+
+    for pattern in filter.split('/') {
+        if pattern == "#" {
+            return device;
+        }
+        let level = levels.next()?;
+        match pattern {
+            "+" if device.is_none() => device = Some(level.to_string()),
+            "+" => {}
+            _ if pattern != level => return None,
+            _ => {}
+        }
+    }
+
+    // Topic has more levels than the filter
+    if levels.next().is_some() {
+        return None;
+    }
+    device
+}
+
+pub async fn spawn_mqtt_subscriber(config: MqttConfig, sink: Sink) {
+    let options = match config.options() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("MQTT Error: invalid MQTT_URL {}", e);
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        let (client, mut eventloop) = AsyncClient::new(options, 100);
+        let broker = config.url.clone();
+        let mut pipelines: HashMap<String, Pipeline> = HashMap::new();
+        let quarantine = sink.quarantine();
+        let mut backoff = Backoff::new(RECONNECT_MIN, RECONNECT_MAX);
+
+        println!("Connecting to MQTT broker at {}...", config.url);
+
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    println!("MQTT Connected! Subscribing to {:?}", config.topics);
+                    backoff.reset();
+                    // Subscriptions are re-sent after every reconnect (clean session)
+                    for topic in &config.topics {
+                        if let Err(e) = client.subscribe(topic, config.qos).await {
+                            eprintln!("MQTT subscribe error on {}: {}", topic, e);
+                        }
+                    }
+                    sink.publish_status(&DeviceStatus::connected(&broker));
+                }
+                Ok(Event::Incoming(Packet::Publish(message))) => {
+                    let device = config
+                        .topics
+                        .iter()
+                        .find_map(|filter| device_from_topic(filter, &message.topic))
+                        .unwrap_or_else(|| message.topic.clone());
+
+                    let payload = String::from_utf8_lossy(&message.payload);
+                    let source = format!("mqtt://{}", message.topic);
+                    for line in payload.lines() {
+                        sink.record_raw(&source, line);
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let mut reading = match check_line(line) {
+                            Ok(reading) => reading,
+                            Err(error) => {
+                                quarantine.reject(&device, &source, line, &error);
+                                continue;
+                            }
+                        };
+                        quarantine.parsed(&device);
+                        // The topic is authoritative for MQTT boards
+                        reading.device = Some(device.clone());
+                        let pipeline = pipelines.entry(device.clone()).or_insert_with(|| {
+                            println!("MQTT device '{}' seen on {}", device, message.topic);
+                            sink.new_pipeline(&device)
+                        });
+                        let output = pipeline.process(reading);
+                        sink.publish(&output);
+                        sink.publish_quality(&source, &device, pipeline.take_quality());
+                        sink.publish_health(&source, &device, pipeline.take_health());
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    // The event loop reconnects on the next poll, we only pace it
+                    let delay = backoff.next_delay();
+                    eprintln!("MQTT Error: {} (retrying in {}s)", e, delay.as_secs());
+                    sink.publish_status(&DeviceStatus::disconnected(
+                        &broker,
+                        &e.to_string(),
+                        Some(delay),
+                    ));
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+#[path = "mqtt_tests.rs"]
+mod tests;
@@ -0,0 +1,106 @@
+use super::*;
+
+fn config_from(vars: &[(&str, &str)]) -> Option<MqttConfig> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    MqttConfig::from_lookup(|key| vars.get(key).cloned())
+}
+
+// MqttConfig Tests
+
+#[test]
+fn test_config_disabled_without_url() {
+    assert_eq!(config_from(&[]), None);
+    assert_eq!(config_from(&[("MQTT_URL", " ")]), None);
+}
+
+#[test]
+fn test_config_defaults() {
+    let config = config_from(&[("MQTT_URL", "mqtt://localhost:1883")]).unwrap();
+
+    assert_eq!(config.client_id, "sedentary-tracker");
+    assert_eq!(config.qos, QoS::AtLeastOnce);
+    assert_eq!(config.topics, vec!["sensors/+/reading"]);
+    assert_eq!(config.username, None);
+}
+
+#[test]
+fn test_config_from_env_vars() {
+    let config = config_from(&[
+        ("MQTT_URL", "mqtt://broker.local:1883"),
+        ("MQTT_CLIENT_ID", "tracker-2"),
+        ("MQTT_USERNAME", "tracker"),
+        ("MQTT_PASSWORD", "secret"),
+        ("MQTT_QOS", "0"),
+        ("MQTT_TOPICS", "sensors/+/reading, lab/+/imu"),
+    ])
+    .unwrap();
+
+    assert_eq!(config.client_id, "tracker-2");
+    assert_eq!(config.username.as_deref(), Some("tracker"));
+    assert_eq!(config.password.as_deref(), Some("secret"));
+    assert_eq!(config.qos, QoS::AtMostOnce);
+    assert_eq!(config.topics, vec!["sensors/+/reading", "lab/+/imu"]);
+}
+
+#[test]
+fn test_config_invalid_qos_falls_back() {
+    let config = config_from(&[("MQTT_URL", "mqtt://localhost"), ("MQTT_QOS", "3")]).unwrap();
+    assert_eq!(config.qos, QoS::AtLeastOnce);
+}
+
+#[test]
+fn test_options_from_url() {
+    let config = config_from(&[("MQTT_URL", "mqtt://broker.local:1884")]).unwrap();
+    let options = config.options().unwrap();
+
+    assert_eq!(options.broker_address(), ("broker.local".to_string(), 1884));
+    assert_eq!(options.client_id(), "sedentary-tracker");
+}
+
+#[test]
+fn test_options_invalid_url() {
+    let config = config_from(&[("MQTT_URL", "localhost:1883")]).unwrap();
+    assert!(config.options().is_err());
+}
+
+// Topic Tests
+
+#[test]
+fn test_device_from_topic() {
+    assert_eq!(
+        device_from_topic("sensors/+/reading", "sensors/desk-3/reading"),
+        Some("desk-3".to_string())
+    );
+    assert_eq!(
+        device_from_topic("office/+/+/reading", "office/floor-2/desk-3/reading"),
+        Some("floor-2".to_string())
+    );
+    assert_eq!(
+        device_from_topic("sensors/+/#", "sensors/desk-3/imu/raw"),
+        Some("desk-3".to_string())
+    );
+}
+
+#[test]
+fn test_device_from_topic_no_match() {
+    assert_eq!(
+        device_from_topic("sensors/+/reading", "sensors/desk-3/status"),
+        None
+    );
+    assert_eq!(
+        device_from_topic("sensors/+/reading", "sensors/desk-3"),
+        None
+    );
+    assert_eq!(
+        device_from_topic("sensors/+/reading", "sensors/desk-3/reading/x"),
+        None
+    );
+    // Filters without a wildcard match but carry no device id
+    assert_eq!(
+        device_from_topic("sensors/reading", "sensors/reading"),
+        None
+    );
+}
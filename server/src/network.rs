@@ -0,0 +1,245 @@
+use crate::handshake::{parse_hello, HelloEvent};
+use crate::models::DeviceStatus;
+use crate::pipeline::{Pipeline, Sink};
+use crate::source::check_line;
+use std::collections::HashMap;
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::net::{TcpListener, UdpSocket};
+
+// LIMITS
+const MAX_LINE_BYTES: u64 = 4096; // A reading is ~50 bytes, anything longer is garbage
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(30); // Board stopped sending (no FIN)
+const UDP_DEVICE_EXPIRY: Duration = Duration::from_secs(600); // Forget silent UDP senders
+
+/// Network ingestion settings, read from the environment.
+/// Each listener is only started when its address is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkConfig {
+    pub tcp_addr: Option<SocketAddr>, // TCP_INGEST_ADDR, e.g. 0.0.0.0:7000
+    pub udp_addr: Option<SocketAddr>, // UDP_INGEST_ADDR, e.g. 0.0.0.0:7001
+}
+
+impl NetworkConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    fn from_lookup(get: impl Fn(&str) -> Option<String>) -> Self {
+        let addr = |key: &str| {
+            let raw = get(key)?;
+            match raw.trim().parse() {
+                Ok(addr) => Some(addr),
+                Err(_) => {
+                    eprintln!("Invalid {} '{}', listener disabled", key, raw);
+                    None
+                }
+            }
+        };
+
+        NetworkConfig {
+            tcp_addr: addr("TCP_INGEST_ADDR"),
+            udp_addr: addr("UDP_INGEST_ADDR"),
+        }
+    }
+}
+
+/// Identity for a network board: the "device" field of the reading if the
+/// firmware sends one, otherwise the sender's IP address
+fn device_id(reported: Option<&str>, peer: IpAddr) -> String {
+    match reported {
+        Some(id) if !id.trim().is_empty() => id.trim().to_string(),
+        _ => peer.to_string(),
+    }
+}
+
+pub async fn spawn_network_listeners(config: NetworkConfig, sink: Sink) {
+    if let Some(addr) = config.tcp_addr {
+        match TcpListener::bind(addr).await {
+            Ok(listener) => {
+                println!("TCP ingestion listening on {}", addr);
+                tokio::spawn(accept_tcp(listener, sink.clone()));
+            }
+            Err(e) => eprintln!("TCP ingestion bind error on {}: {}", addr, e),
+        }
+    }
+
+    if let Some(addr) = config.udp_addr {
+        match UdpSocket::bind(addr).await {
+            Ok(socket) => {
+                println!("UDP ingestion listening on {}", addr);
+                tokio::spawn(receive_udp(socket, sink));
+            }
+            Err(e) => eprintln!("UDP ingestion bind error on {}: {}", addr, e),
+        }
+    }
+}
+
+async fn accept_tcp(listener: TcpListener, sink: Sink) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tokio::spawn(handle_tcp_connection(stream, peer, sink.clone()));
+            }
+            Err(e) => eprintln!("TCP accept error: {}", e),
+        }
+    }
+}
+
+// One board per connection, with its own pipeline state
+async fn handle_tcp_connection(stream: impl AsyncRead + Unpin, peer: SocketAddr, sink: Sink) {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let mut pipeline = sink.new_pipeline("");
+    let mut device: Option<String> = None;
+    let quarantine = sink.quarantine();
+    let source = format!("tcp://{}", peer);
+
+    let reason = loop {
+        line.clear();
+        let mut limited = (&mut reader).take(MAX_LINE_BYTES);
+        match tokio::time::timeout(TCP_IDLE_TIMEOUT, limited.read_line(&mut line)).await {
+            Err(_) => break "idle timeout".to_string(),
+            Ok(Err(e)) => break e.to_string(),
+            Ok(Ok(0)) => break "device closed the connection".to_string(),
+            Ok(Ok(n)) if n as u64 == MAX_LINE_BYTES && !line.ends_with('\n') => {
+                break "line too long".to_string()
+            }
+            Ok(Ok(_)) => {}
+        }
+        sink.record_raw(&source, &line);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // A hello names the board and may reject it before any reading
+        if let Some(hello) = parse_hello(&line) {
+            let result = pipeline.handshake(&hello);
+            sink.publish_hello(&HelloEvent::new(
+                &hello,
+                &format!("tcp://{}", peer),
+                &result,
+            ));
+            if let Err(reason) = result {
+                eprintln!("TCP device '{}' rejected: {}", hello.device, reason);
+                break reason;
+            }
+            if device.is_none() {
+                println!("TCP device '{}' connected from {}", hello.device, peer);
+                sink.publish_status(&DeviceStatus::connected(&format!("tcp://{}", hello.device)));
+                device = Some(hello.device);
+            }
+            continue;
+        }
+
+        let reading = match check_line(&line) {
+            Ok(reading) => reading,
+            Err(error) => {
+                let id = device.clone().unwrap_or_else(|| device_id(None, peer.ip()));
+                quarantine.reject(&id, &source, &line, &error);
+                continue;
+            }
+        };
+
+        // The first reading fixes the identity for the rest of the connection
+        if device.is_none() {
+            let id = device_id(reading.device.as_deref(), peer.ip());
+            println!("TCP device '{}' connected from {}", id, peer);
+            sink.publish_status(&DeviceStatus::connected(&format!("tcp://{}", id)));
+            // No hello came, so the pipeline is still untouched
+            pipeline = sink.new_pipeline(&id);
+            device = Some(id);
+        }
+
+        let output = pipeline.process(reading);
+        sink.publish(&output);
+        if let Some(id) = &device {
+            quarantine.parsed(id);
+            sink.publish_quality(&source, id, pipeline.take_quality());
+            sink.publish_health(&source, id, pipeline.take_health());
+        }
+    };
+
+    if let Some(id) = device {
+        println!("TCP device '{}' disconnected: {}", id, reason);
+        sink.publish_status(&DeviceStatus::disconnected(
+            &format!("tcp://{}", id),
+            &reason,
+            None,
+        ));
+    }
+}
+
+// Boards share one socket, so pipelines are keyed by device id
+async fn receive_udp(socket: UdpSocket, sink: Sink) {
+    let mut buf = vec![0u8; MAX_LINE_BYTES as usize];
+    let mut pipelines: HashMap<String, (Pipeline, Instant)> = HashMap::new();
+    let mut last_sweep = Instant::now();
+    let quarantine = sink.quarantine();
+
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                eprintln!("UDP receive error: {}", e);
+                continue;
+            }
+        };
+
+        // A datagram may carry several newline-separated readings
+        let payload = String::from_utf8_lossy(&buf[..len]);
+        let source = format!("udp://{}", peer);
+        for line in payload.lines() {
+            sink.record_raw(&source, line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reading = match check_line(line) {
+                Ok(reading) => reading,
+                Err(error) => {
+                    quarantine.reject(&device_id(None, peer.ip()), &source, line, &error);
+                    continue;
+                }
+            };
+            let id = device_id(reading.device.as_deref(), peer.ip());
+            quarantine.parsed(&id);
+            if !pipelines.contains_key(&id) {
+                println!("UDP device '{}' seen from {}", id, peer);
+                sink.publish_status(&DeviceStatus::connected(&format!("udp://{}", id)));
+            }
+            let (pipeline, last_seen) = pipelines
+                .entry(id.clone())
+                .or_insert_with(|| (sink.new_pipeline(&id), Instant::now()));
+            *last_seen = Instant::now();
+
+            let output = pipeline.process(reading);
+            sink.publish(&output);
+            sink.publish_quality(&source, &id, pipeline.take_quality());
+            sink.publish_health(&source, &id, pipeline.take_health());
+        }
+
+        if last_sweep.elapsed() >= UDP_DEVICE_EXPIRY {
+            let expired: Vec<String> = pipelines
+                .iter()
+                .filter(|(_, (_, last_seen))| last_seen.elapsed() >= UDP_DEVICE_EXPIRY)
+                .map(|(id, _)| id.clone())
+                .collect();
+            for id in expired {
+                pipelines.remove(&id);
+                println!("UDP device '{}' expired", id);
+                sink.publish_status(&DeviceStatus::disconnected(
+                    &format!("udp://{}", id),
+                    "no datagrams received",
+                    None,
+                ));
+            }
+            last_sweep = Instant::now();
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "network_tests.rs"]
+mod tests;
@@ -0,0 +1,158 @@
+use super::*;
+use crate::models::ProcessedState;
+use crate::quality::QualityEvent;
+use crate::quarantine::QuarantinedLine;
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+
+fn config_from(vars: &[(&str, &str)]) -> NetworkConfig {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    NetworkConfig::from_lookup(|key| vars.get(key).cloned())
+}
+
+// Nothing listens on port 1, so Redis writes fail fast and only the broadcast is exercised
+fn test_sink() -> (Sink, broadcast::Receiver<String>) {
+    let (tx, rx) = broadcast::channel(100);
+    let redis = redis::Client::open("redis://127.0.0.1:1/").unwrap();
+    (Sink::new(tx, redis), rx)
+}
+
+// NetworkConfig Tests
+
+#[test]
+fn test_config_disabled_by_default() {
+    let config = config_from(&[]);
+
+    assert_eq!(config.tcp_addr, None);
+    assert_eq!(config.udp_addr, None);
+}
+
+#[test]
+fn test_config_from_env_vars() {
+    let config = config_from(&[
+        ("TCP_INGEST_ADDR", "0.0.0.0:7000"),
+        ("UDP_INGEST_ADDR", "127.0.0.1:7001"),
+    ]);
+
+    assert_eq!(config.tcp_addr, Some("0.0.0.0:7000".parse().unwrap()));
+    assert_eq!(config.udp_addr, Some("127.0.0.1:7001".parse().unwrap()));
+}
+
+#[test]
+fn test_config_invalid_address_disables_listener() {
+    let config = config_from(&[("TCP_INGEST_ADDR", "port 7000")]);
+    assert_eq!(config.tcp_addr, None);
+}
+
+// Device Identity Tests
+
+#[test]
+fn test_device_id_prefers_reported_id() {
+    let peer: IpAddr = "192.168.1.20".parse().unwrap();
+
+    assert_eq!(device_id(Some("esp32-desk-3"), peer), "esp32-desk-3");
+    assert_eq!(device_id(Some("  "), peer), "192.168.1.20");
+    assert_eq!(device_id(None, peer), "192.168.1.20");
+}
+
+// Ingestion Tests
+
+#[tokio::test]
+async fn test_tcp_connection_feeds_pipeline() {
+    let (sink, mut rx) = test_sink();
+    let input: &[u8] = b"hello\n{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.0,\"device\":\"esp32-a\"}\n{\"ts\":\"10:00:01\",\"pir\":0,\"acc\":0.0}\n";
+    let peer: SocketAddr = "192.168.1.20:50000".parse().unwrap();
+
+    handle_tcp_connection(input, peer, sink).await;
+
+    // The text line is quarantined before the device is known
+    let text: QuarantinedLine = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(text.device_id, "192.168.1.20");
+    assert_eq!(text.source, "tcp://192.168.1.20:50000");
+    assert_eq!(text.payload, "hello");
+
+    let connected: DeviceStatus = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert!(connected.connected);
+    assert_eq!(connected.port, "tcp://esp32-a");
+
+    let first: ProcessedState = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    let second: ProcessedState = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(first.timer, 0);
+    assert_eq!(second.timer, 1);
+
+    let disconnected: DeviceStatus = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert!(!disconnected.connected);
+    assert_eq!(disconnected.retry_in_sec, None);
+}
+
+#[tokio::test]
+async fn test_tcp_quality_events_carry_device_id() {
+    let (sink, mut rx) = test_sink();
+    let input: &[u8] = b"{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.0,\"device\":\"esp32-a\"}\n{\"ts\":\"10:01:00\",\"pir\":0,\"acc\":0.0}\n";
+    let peer: SocketAddr = "192.168.1.20:50000".parse().unwrap();
+
+    handle_tcp_connection(input, peer, sink).await;
+
+    // The gap is keyed by the device, the transport stays in source
+    let mut gaps = Vec::new();
+    while let Ok(message) = rx.try_recv() {
+        if let Ok(event) = serde_json::from_str::<QualityEvent>(&message) {
+            gaps.push(event);
+        }
+    }
+    assert!(!gaps.is_empty());
+    for event in gaps {
+        assert_eq!(event.device_id, "esp32-a");
+        assert_eq!(event.source, "tcp://192.168.1.20:50000");
+    }
+}
+
+#[tokio::test]
+async fn test_tcp_connection_rejects_oversized_line() {
+    let (sink, mut rx) = test_sink();
+    let mut input = b"{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.0}\n".to_vec();
+    input.extend(vec![b'x'; MAX_LINE_BYTES as usize + 10]);
+    let peer: SocketAddr = "192.168.1.20:50000".parse().unwrap();
+
+    handle_tcp_connection(input.as_slice(), peer, sink).await;
+
+    rx.recv().await.unwrap(); // connected
+    rx.recv().await.unwrap(); // reading
+    let disconnected: DeviceStatus = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+    assert_eq!(disconnected.detail, "line too long");
+}
+
+#[tokio::test]
+async fn test_udp_datagrams_keyed_by_device() {
+    let (sink, mut rx) = test_sink();
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    tokio::spawn(receive_udp(socket, sink));
+
+    let board = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    board
+        .send_to(
+            b"{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.0,\"device\":\"a\"}\n{\"ts\":\"10:00:05\",\"pir\":0,\"acc\":0.0,\"device\":\"b\"}\n",
+            addr,
+        )
+        .await
+        .unwrap();
+
+    let mut devices = Vec::new();
+    let mut readings = 0;
+    while readings < 2 {
+        let msg = rx.recv().await.unwrap();
+        if let Ok(status) = serde_json::from_str::<DeviceStatus>(&msg) {
+            devices.push(status.port);
+        } else {
+            let output: ProcessedState = serde_json::from_str(&msg).unwrap();
+            // Separate pipelines: both devices start their own timer
+            assert_eq!(output.timer, 0);
+            readings += 1;
+        }
+    }
+    assert_eq!(devices, vec!["udp://a", "udp://b"]);
+}
@@ -0,0 +1,461 @@
+use crate::cache::{Cache, CacheWrite};
+use crate::calibration::Calibrations;
+use crate::capture::Capture;
+use crate::classifier;
+use crate::clock::DeviceClock;
+use crate::handshake::{self, Hello, HelloEvent};
+use crate::health::{HealthAlert, HealthMonitor};
+use crate::metrics::Metrics;
+use crate::models::{DeviceStatus, LinkStats, ProcessedState, RawReading, SensorFault};
+use crate::quality::{QualityEvent, QualityMonitor};
+use crate::quarantine::Quarantine;
+use crate::settings::Settings;
+use crate::settings::{SettingsMap, ALERT_LIMIT_SEC};
+use crate::source::SensorSource;
+use chrono::{DateTime, TimeDelta, Utc};
+use logic::{ActivityState, Classifier, Observation, StateMachine, SMOOTHING_WINDOW};
+use std::collections::VecDeque;
+use std::env;
+use std::io;
+use tokio::sync::{broadcast, watch};
+
+// TIMER CONFIGURATION
+const MAX_GAP_SEC: i64 = 30; // Longer gaps between samples are unknown time (TIMER_MAX_GAP_SEC)
+
+/// Longest gap between two samples that still counts toward the timer
+fn max_gap(get: impl Fn(&str) -> Option<String>) -> TimeDelta {
+    let secs = match get("TIMER_MAX_GAP_SEC") {
+        Some(raw) => raw
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .unwrap_or_else(|| {
+                eprintln!("Invalid TIMER_MAX_GAP_SEC '{}', using {}", raw, MAX_GAP_SEC);
+                MAX_GAP_SEC
+            }),
+        None => MAX_GAP_SEC,
+    };
+    TimeDelta::seconds(secs)
+}
+
+/// Smoothing, classification and sedentary timer for one stream of readings
+#[derive(Debug)]
+pub struct Pipeline {
+    device_id: String, // Tags every output: hello id, board id or port
+    greeted: bool,     // device_id came from a hello
+    acc_buffer: VecDeque<f32>,
+    smoothing_window: usize, // Samples averaged: the calibrated window or rate_window
+    rate_window: usize,      // One second of samples, from the device's hello
+    sedentary_ms: i64,       // Timer, from elapsed time between samples
+    unknown_ms: i64,         // Gaps in the current timer span too long to count
+    last_time: Option<DateTime<Utc>>, // Time of the latest sample
+    max_gap: TimeDelta,
+    last_magnitude: Option<f32>, // Previous IMU magnitude, for the delta
+    clock: DeviceClock,          // Device RTC -> server time
+    quality: QualityMonitor,     // Gaps and sample rate
+    health: HealthMonitor,       // Stuck sensors, impossible values
+    classifier: Box<dyn Classifier>, // Selected by CLASSIFIER
+    state_machine: StateMachine, // Hysteresis and dwell on top of the classifier
+    alert_limit_sec: u64,
+    settings: Option<watch::Receiver<SettingsMap>>, // Live per-device settings
+    calibrations: Option<Calibrations>,             // Running calibration sessions
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline {
+            device_id: String::new(),
+            greeted: false,
+            acc_buffer: VecDeque::with_capacity(SMOOTHING_WINDOW),
+            smoothing_window: SMOOTHING_WINDOW,
+            rate_window: SMOOTHING_WINDOW,
+            sedentary_ms: 0,
+            unknown_ms: 0,
+            last_time: None,
+            max_gap: max_gap(|key| env::var(key).ok()),
+            last_magnitude: None,
+            clock: DeviceClock::new(),
+            // One second of samples is the default rate too
+            quality: QualityMonitor::new(SMOOTHING_WINDOW as u32),
+            health: HealthMonitor::new(),
+            classifier: classifier::from_env(),
+            state_machine: StateMachine::new(classifier::state_machine_from_env()),
+            alert_limit_sec: ALERT_LIMIT_SEC,
+            settings: None,
+            calibrations: None,
+        }
+    }
+
+    /// Pipeline for one device, so state never mixes between devices
+    pub fn for_device(device_id: &str) -> Self {
+        Pipeline {
+            device_id: device_id.to_string(),
+            ..Self::new()
+        }
+    }
+
+    /// Records the device's readings while it is being calibrated
+    pub fn with_calibrations(mut self, calibrations: Calibrations) -> Self {
+        self.calibrations = Some(calibrations);
+        self
+    }
+
+    /// Follows the device's settings from now on (thresholds, alert limit, smoothing)
+    pub fn with_settings(mut self, settings: watch::Receiver<SettingsMap>) -> Self {
+        self.settings = Some(settings);
+        self.apply_settings(true);
+        self
+    }
+
+    // Picks up the device's settings when they changed (or the device got its name)
+    fn apply_settings(&mut self, force: bool) {
+        let Some(rx) = &mut self.settings else {
+            return;
+        };
+        if !force && !rx.has_changed().unwrap_or(false) {
+            return;
+        }
+        let settings = rx
+            .borrow_and_update()
+            .get(&self.device_id)
+            .copied()
+            .unwrap_or_default();
+        self.classifier.set_thresholds(settings.thresholds);
+        self.alert_limit_sec = settings.alert_limit_sec;
+        self.set_smoothing(settings.smoothing_window.unwrap_or(self.rate_window));
+    }
+
+    fn set_smoothing(&mut self, window: usize) {
+        self.smoothing_window = window;
+        while self.acc_buffer.len() > self.smoothing_window {
+            self.acc_buffer.pop_front();
+        }
+    }
+
+    /// Applies a device hello: rejects incompatible devices and adapts the
+    /// smoothing window to the announced sample rate (unless calibrated).
+    /// A different board on the same link starts from scratch.
+    pub fn handshake(&mut self, hello: &Hello) -> Result<(), String> {
+        handshake::validate(hello)?;
+        if self.greeted && hello.device != self.device_id {
+            *self = Pipeline {
+                settings: self.settings.take(),
+                calibrations: self.calibrations.take(),
+                ..Self::new()
+            };
+        }
+        self.greeted = true;
+        self.device_id = hello.device.clone();
+        self.rate_window = hello.rate_hz as usize;
+        self.set_smoothing(self.rate_window);
+        self.quality.set_expected_hz(hello.rate_hz);
+        self.apply_settings(true);
+        Ok(())
+    }
+
+    pub fn process(&mut self, reading: RawReading) -> ProcessedState {
+        self.apply_settings(false);
+        let received_at = reading.received_at.unwrap_or_else(Utc::now);
+        let timing = self.clock.stamp(&reading.ts, received_at);
+        // Timer and checks follow the device clock (the arrival-based time
+        // would collapse batch uploads into one instant)
+        let now = timing.device_time.unwrap_or(timing.measured_at);
+
+        // Impossible values are flagged and kept out of the smoothing
+        let faults = self.health.check(&reading, now);
+        let in_range = !faults.contains(&SensorFault::OutOfRange);
+
+        // With raw axes the delta is computed here, the same way the Arduino
+        // derives "acc" (change in magnitude between consecutive samples)
+        let magnitude = reading.imu.map(|imu| imu.magnitude());
+        let acc = match magnitude {
+            _ if !in_range => None,
+            Some(magnitude) => {
+                let delta = self
+                    .last_magnitude
+                    .map_or(0.0, |previous| (magnitude - previous).abs());
+                self.last_magnitude = Some(magnitude);
+                Some(delta)
+            }
+            None => Some(reading.acc),
+        };
+
+        // Add to smoothing buffer
+        if let Some(acc) = acc {
+            if self.acc_buffer.len() >= self.smoothing_window {
+                self.acc_buffer.pop_front();
+            }
+            self.acc_buffer.push_back(acc);
+        }
+        if let Some(calibrations) = &self.calibrations {
+            calibrations.record(&self.device_id, acc, reading.pir == 1);
+        }
+
+        // Calculate smoothed acceleration (mean of buffer)
+        let smoothed_acc: f32 = if self.acc_buffer.is_empty() {
+            0.0
+        } else {
+            self.acc_buffer.iter().sum::<f32>() / self.acc_buffer.len() as f32
+        };
+
+        // Classify state; a stuck PIR would keep it ACTIVE forever
+        let verdict = self.classifier.classify(&Observation {
+            pir: reading.pir == 1 && !self.health.pir_stuck(),
+            smoothed_acc,
+            acc,
+        });
+        // Debounced, so a signal hovering at a threshold doesn't flicker (or reset the timer)
+        let state = self.state_machine.update(
+            verdict.state,
+            verdict.level,
+            &self.classifier.thresholds(),
+            now.timestamp_millis(),
+        );
+
+        // Update sedentary timer from the time elapsed on the device clock.
+        // Repeated or out-of-order samples add nothing, gaps past max_gap are unknown.
+        let elapsed = match self.last_time {
+            Some(last) if now <= last => TimeDelta::zero(),
+            Some(last) => now - last,
+            None => TimeDelta::zero(),
+        };
+        if elapsed > TimeDelta::zero() || self.last_time.is_none() {
+            self.last_time = Some(now);
+        }
+        self.quality
+            .observe(now, timing.measured_at, timing.received_at);
+
+        match state {
+            ActivityState::Active => {
+                // Reset on activity
+                self.sedentary_ms = 0;
+                self.unknown_ms = 0;
+            }
+            _ if elapsed > self.max_gap => self.unknown_ms += elapsed.num_milliseconds(),
+            ActivityState::Sedentary => self.sedentary_ms += elapsed.num_milliseconds(), // Increment
+            ActivityState::Fidget => {}                                                  // Pauses
+        }
+        let timer = (self.sedentary_ms / 1000) as u64;
+
+        // Build processed output
+        ProcessedState {
+            device_id: self.device_id.clone(),
+            state,
+            timer,
+            unknown_sec: (self.unknown_ms / 1000) as u64,
+            val: smoothed_acc,
+            alert: timer >= self.alert_limit_sec,
+            timestamp: reading.ts,
+            faults,
+            magnitude,
+            imu: reading.imu,
+            features: self.classifier.features(),
+            timing: Some(timing),
+        }
+    }
+}
+
+impl Pipeline {
+    /// No reading this time around; lets a live link report a gap as it starts
+    pub fn idle(&mut self, now: DateTime<Utc>) {
+        self.quality.idle(now);
+    }
+
+    /// Hello id, board id or port, whichever is known
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    /// Data quality events raised since the last call
+    pub fn take_quality(&mut self) -> Vec<QualityEvent> {
+        self.quality.take_events()
+    }
+
+    /// Device health alerts raised or cleared since the last call
+    pub fn take_health(&mut self) -> Vec<HealthAlert> {
+        self.health.take_alerts()
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What the read stage hands to the classify stage
+#[derive(Debug)]
+pub enum Input {
+    Hello(Hello, Result<(), String>), // Validated, so an incompatible device is dropped right away
+    Reading(RawReading),
+    Idle, // Nothing arrived this time around
+}
+
+/// Read stage: pulls readings off a blocking source until it is lost and hands
+/// them to `send`. An incompatible device ends the stream with InvalidData
+/// after its hello is passed on. The error that ended the stream is returned
+/// so the caller can decide whether to reconnect.
+pub fn read(source: &mut dyn SensorSource, mut send: impl FnMut(Input)) -> io::Error {
+    loop {
+        let next = source.next_reading();
+
+        if let Some(hello) = source.take_hello() {
+            let result = handshake::validate(&hello);
+            let rejected = result.clone().err();
+            send(Input::Hello(hello, result));
+            if let Some(reason) = rejected {
+                return io::Error::new(io::ErrorKind::InvalidData, reason);
+            }
+        }
+
+        match next {
+            Ok(Some(mut reading)) => {
+                // Stamped here, not when classified, so queueing doesn't skew the clock
+                reading.received_at.get_or_insert_with(Utc::now);
+                send(Input::Reading(reading))
+            }
+            Ok(None) => send(Input::Idle),
+            Err(e) => return e,
+        }
+    }
+}
+
+// REDIS KEYS
+pub const HISTORY_DEVICES: &str = "sensor_devices"; // Set of device ids with a history
+pub const DEVICE_STATUS: &str = "device_statuses"; // Hash: port -> latest DeviceStatus
+
+/// Latest readings of one device, newest first
+pub fn history_key(device_id: &str) -> String {
+    format!("sensor_history:{}", device_id)
+}
+
+/// Fan-out for processed output: Redis history cache + broadcast hub
+/// (WebSocket clients and the DB worker), plus the optional raw capture.
+/// Publishing never waits: the broadcast drops the oldest messages for slow
+/// receivers and the cache queue drops new writes when full, both counted in the metrics.
+#[derive(Clone)]
+pub struct Sink {
+    tx: broadcast::Sender<String>,
+    cache: Cache,
+    capture: Option<Capture>,
+    quarantine: Quarantine,
+    metrics: Metrics,
+    settings: Settings,
+    calibrations: Calibrations,
+}
+
+impl Sink {
+    /// Starts the Redis cache writer, so it must be called inside the Tokio runtime
+    pub fn new(tx: broadcast::Sender<String>, redis: redis::Client) -> Self {
+        let metrics = Metrics::default();
+        Sink {
+            quarantine: Quarantine::new(tx.clone()),
+            cache: Cache::spawn(redis, metrics.clone()),
+            tx,
+            capture: None,
+            metrics,
+            settings: Settings::default(),
+            calibrations: Calibrations::default(),
+        }
+    }
+
+    pub fn with_capture(mut self, capture: Option<Capture>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    pub fn capture(&self) -> Option<Capture> {
+        self.capture.clone()
+    }
+
+    // Line counters and bad payload store, shared by every source
+    pub fn quarantine(&self) -> Quarantine {
+        self.quarantine.clone()
+    }
+
+    // Per-device thresholds and alert limit, shared by every pipeline
+    pub fn settings(&self) -> Settings {
+        self.settings.clone()
+    }
+
+    // Running calibration sessions, fed by every pipeline
+    pub fn calibrations(&self) -> Calibrations {
+        self.calibrations.clone()
+    }
+
+    /// Pipeline for a device that follows its live settings and calibrations
+    pub fn new_pipeline(&self, device_id: &str) -> Pipeline {
+        Pipeline::for_device(device_id)
+            .with_calibrations(self.calibrations.clone())
+            .with_settings(self.settings.subscribe())
+    }
+
+    // Stage counters, shared by every source
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.clone()
+    }
+
+    // Records a raw line before parsing, so support cases can be replayed
+    pub fn record_raw(&self, source: &str, line: &str) {
+        if let Some(capture) = &self.capture {
+            capture.record(source, line);
+        }
+    }
+
+    pub fn publish(&self, output: &ProcessedState) {
+        let json_out = serde_json::to_string(output).unwrap();
+        self.metrics.reading_processed();
+
+        // Redis cache for reconnection, one history per device
+        self.cache.push(CacheWrite::History {
+            device_id: output.device_id.clone(),
+            json: json_out.clone(),
+        });
+        // Push to WebSocket
+        let _ = self.tx.send(json_out);
+    }
+
+    // Sends the connection status to the dashboard and keeps the latest copy per
+    // port in Redis so freshly connected browsers see it too
+    pub fn publish_status(&self, status: &DeviceStatus) {
+        let json_out = serde_json::to_string(status).unwrap();
+        self.cache.push(CacheWrite::Status {
+            port: status.port.clone(),
+            json: json_out.clone(),
+        });
+        let _ = self.tx.send(json_out);
+    }
+
+    // Handshake result for the dashboard; the DB worker records it in the devices table
+    pub fn publish_hello(&self, event: &HelloEvent) {
+        let _ = self.tx.send(serde_json::to_string(event).unwrap());
+    }
+
+    // Gap and sample rate events for the dashboard; the DB worker keeps them in data_quality
+    pub fn publish_quality(&self, source: &str, device_id: &str, events: Vec<QualityEvent>) {
+        for mut event in events {
+            event.source = source.to_string();
+            event.device_id = device_id.to_string();
+            let _ = self.tx.send(serde_json::to_string(&event).unwrap());
+        }
+    }
+
+    // Sensor fault alerts for the dashboard; the DB worker keeps them in device_health
+    pub fn publish_health(&self, source: &str, device_id: &str, alerts: Vec<HealthAlert>) {
+        for mut alert in alerts {
+            alert.source = source.to_string();
+            alert.device_id = device_id.to_string();
+            let _ = self.tx.send(serde_json::to_string(&alert).unwrap());
+        }
+    }
+
+    // Protocol counters for the dashboard, not cached
+    pub fn publish_link_stats(&self, stats: &LinkStats) {
+        let _ = self.tx.send(serde_json::to_string(stats).unwrap());
+    }
+}
+
+#[cfg(test)]
+#[path = "pipeline_tests.rs"]
+mod tests;
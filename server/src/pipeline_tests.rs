@@ -0,0 +1,533 @@
+use super::*;
+use crate::framing::FramedSource;
+use crate::models::Imu;
+use crate::settings::DeviceSettings;
+use logic::StateMachineConfig;
+use std::io::Cursor;
+
+fn reading(ts: &str, pir: i32, acc: f32) -> RawReading {
+    RawReading {
+        ts: ts.to_string(),
+        pir,
+        acc,
+        device: None,
+        imu: None,
+        received_at: None,
+    }
+}
+
+// Every classifier verdict counts at once, for tests about what happens after classification
+fn undebounced(pipeline: Pipeline) -> Pipeline {
+    Pipeline {
+        state_machine: StateMachine::new(StateMachineConfig::immediate()),
+        ..pipeline
+    }
+}
+
+fn imu_reading(ts: &str, ax: f32, ay: f32, az: f32) -> RawReading {
+    RawReading {
+        ts: ts.to_string(),
+        pir: 0,
+        acc: 0.0,
+        device: None,
+        imu: Some(Imu {
+            ax,
+            ay,
+            az,
+            gx: None,
+            gy: None,
+            gz: None,
+        }),
+        received_at: None,
+    }
+}
+
+// Classification Tests
+
+#[test]
+fn test_pipeline_uses_its_classifier() {
+    let mut pipeline = Pipeline {
+        classifier: Box::new(logic::AccelerationClassifier::default()),
+        ..Pipeline::new()
+    };
+
+    // PIR alone doesn't make it active
+    let output = pipeline.process(reading("10:00:00", 1, 0.0));
+    assert_eq!(output.state, ActivityState::Sedentary);
+}
+
+#[test]
+fn test_pipeline_broadcasts_classifier_features() {
+    let mut pipeline = Pipeline {
+        classifier: Box::new(logic::HjorthClassifier::default()),
+        ..Pipeline::new()
+    };
+    let first = pipeline.process(reading("10:00:00", 0, 0.0));
+    assert_eq!(first.features, None);
+
+    let mut output = first;
+    for _ in 1..logic::classifier::HJORTH_HOP {
+        output = pipeline.process(reading("10:00:00", 0, 0.0));
+    }
+    assert_eq!(output.features.unwrap().hjorth_activity, 0.0);
+
+    // Other classifiers have none
+    let output = Pipeline::new().process(reading("10:00:00", 0, 0.0));
+    assert_eq!(output.features, None);
+}
+
+#[test]
+fn test_pipeline_smooths_over_window() {
+    let mut pipeline = Pipeline::new();
+
+    let first = pipeline.process(reading("10:00:00", 0, 0.10));
+    assert!((first.val - 0.10).abs() < 1e-6);
+
+    let second = pipeline.process(reading("10:00:00", 0, 0.0));
+    assert!((second.val - 0.05).abs() < 1e-6);
+
+    // After a full window of zeros the spike has been pushed out
+    let mut last = second;
+    for _ in 0..SMOOTHING_WINDOW {
+        last = pipeline.process(reading("10:00:00", 0, 0.0));
+    }
+    assert_eq!(last.val, 0.0);
+}
+
+#[test]
+fn test_pipeline_timer_counts_elapsed_time() {
+    let mut pipeline = Pipeline::new();
+
+    // 10Hz samples: ten readings share a timestamp
+    for _ in 0..10 {
+        pipeline.process(reading("10:00:00", 0, 0.0));
+    }
+    let output = pipeline.process(reading("10:00:01", 0, 0.0));
+
+    assert_eq!(output.state, ActivityState::Sedentary);
+    assert_eq!(output.timer, 1);
+    assert_eq!(output.timestamp, "10:00:01");
+
+    // Dropped samples: the time still passed
+    let output = pipeline.process(reading("10:00:06", 0, 0.0));
+    assert_eq!(output.timer, 6);
+    assert_eq!(output.unknown_sec, 0);
+}
+
+#[test]
+fn test_pipeline_timer_ignores_repeated_and_old_samples() {
+    let mut pipeline = Pipeline::new();
+    pipeline.process(reading("10:00:00", 0, 0.0));
+    pipeline.process(reading("10:00:05", 0, 0.0));
+
+    assert_eq!(pipeline.process(reading("10:00:05", 0, 0.0)).timer, 5);
+    assert_eq!(pipeline.process(reading("10:00:02", 0, 0.0)).timer, 5);
+    // Counting resumes from the newest sample, not the late one
+    assert_eq!(pipeline.process(reading("10:00:06", 0, 0.0)).timer, 6);
+}
+
+#[test]
+fn test_pipeline_gap_is_unknown_time() {
+    let mut pipeline = undebounced(Pipeline::new());
+    pipeline.process(reading("10:00:00", 0, 0.0));
+    pipeline.process(reading("10:00:10", 0, 0.0));
+
+    // Five minutes without data: neither counted nor reset
+    let after_gap = pipeline.process(reading("10:05:10", 0, 0.0));
+    assert_eq!(after_gap.timer, 10);
+    assert_eq!(after_gap.unknown_sec, 300);
+
+    let next = pipeline.process(reading("10:05:11", 0, 0.0));
+    assert_eq!(next.timer, 11);
+    assert_eq!(next.unknown_sec, 300);
+
+    // Activity clears both
+    let active = pipeline.process(reading("10:05:12", 1, 0.0));
+    assert_eq!(active.timer, 0);
+    assert_eq!(active.unknown_sec, 0);
+}
+
+#[test]
+fn test_pipeline_max_gap_config() {
+    let lookup = |value: &'static str| {
+        move |key: &str| (key == "TIMER_MAX_GAP_SEC").then(|| value.to_string())
+    };
+
+    assert_eq!(max_gap(|_| None), TimeDelta::seconds(MAX_GAP_SEC));
+    assert_eq!(max_gap(lookup("120")), TimeDelta::seconds(120));
+    assert_eq!(max_gap(lookup("0")), TimeDelta::seconds(MAX_GAP_SEC));
+    assert_eq!(max_gap(lookup("soon")), TimeDelta::seconds(MAX_GAP_SEC));
+
+    let mut pipeline = Pipeline::new();
+    pipeline.max_gap = TimeDelta::seconds(600);
+    pipeline.process(reading("10:00:00", 0, 0.0));
+    assert_eq!(pipeline.process(reading("10:05:00", 0, 0.0)).timer, 300);
+}
+
+#[test]
+fn test_pipeline_active_resets_and_fidget_pauses() {
+    let mut pipeline = undebounced(Pipeline::new());
+    pipeline.process(reading("10:00:00", 0, 0.0));
+    pipeline.process(reading("10:00:01", 0, 0.0));
+
+    let fidget = pipeline.process(reading("10:00:02", 0, 0.09));
+    assert_eq!(fidget.state, ActivityState::Fidget);
+    assert_eq!(fidget.timer, 1);
+
+    let active = pipeline.process(reading("10:00:03", 1, 0.0));
+    assert_eq!(active.state, ActivityState::Active);
+    assert_eq!(active.timer, 0);
+}
+
+#[test]
+fn test_pipeline_alert_at_limit() {
+    let mut pipeline = Pipeline::new();
+    let start = chrono::NaiveTime::from_hms_opt(8, 0, 0).unwrap();
+    let mut output = pipeline.process(reading("08:00:00", 0, 0.0));
+    for second in 1..=ALERT_LIMIT_SEC as i64 {
+        let ts = (start + TimeDelta::seconds(second))
+            .format("%H:%M:%S")
+            .to_string();
+        output = pipeline.process(reading(&ts, 0, 0.0));
+    }
+
+    assert_eq!(output.timer, ALERT_LIMIT_SEC);
+    assert!(output.alert);
+}
+
+#[test]
+fn test_pipeline_follows_live_settings() {
+    let settings = Settings::default();
+    let mut pipeline =
+        undebounced(Pipeline::for_device("desk-3")).with_settings(settings.subscribe());
+    assert_eq!(
+        pipeline.process(reading("10:00:00", 0, 0.03)).state,
+        ActivityState::Fidget
+    );
+
+    // Applied from the next reading, without a new pipeline
+    settings.set(
+        "desk-3",
+        DeviceSettings {
+            thresholds: logic::Thresholds {
+                fidget: 0.05,
+                active: 0.10,
+            },
+            alert_limit_sec: 60,
+            smoothing_window: None,
+        },
+    );
+    let output = pipeline.process(reading("10:00:01", 0, 0.03));
+    assert_eq!(output.state, ActivityState::Sedentary);
+
+    let start = chrono::NaiveTime::from_hms_opt(10, 0, 1).unwrap();
+    let mut output = output;
+    for second in 1..=59 {
+        let ts = (start + TimeDelta::seconds(second))
+            .format("%H:%M:%S")
+            .to_string();
+        output = pipeline.process(reading(&ts, 0, 0.03));
+    }
+    assert_eq!(output.timer, 60);
+    assert!(output.alert);
+}
+
+#[test]
+fn test_pipeline_settings_follow_hello() {
+    let settings = Settings::default();
+    settings.set(
+        "desk-3",
+        DeviceSettings {
+            alert_limit_sec: 60,
+            ..DeviceSettings::default()
+        },
+    );
+    let mut pipeline = Pipeline::for_device("/dev/ttyACM0").with_settings(settings.subscribe());
+    assert_eq!(pipeline.alert_limit_sec, ALERT_LIMIT_SEC);
+
+    let hello = Hello {
+        device: "desk-3".to_string(),
+        firmware: "1.4.0".to_string(),
+        protocol: 2,
+        rate_hz: 10,
+        sensors: vec![],
+    };
+    pipeline.handshake(&hello).unwrap();
+    assert_eq!(pipeline.alert_limit_sec, 60);
+}
+
+#[test]
+fn test_pipeline_blips_do_not_reset_timer() {
+    let mut pipeline = Pipeline {
+        state_machine: StateMachine::new(StateMachineConfig {
+            exit_ratio: 0.8,
+            min_dwell_ms: 2000,
+        }),
+        ..Pipeline::new()
+    };
+    pipeline.process(reading("10:00:00", 0, 0.0));
+    pipeline.process(reading("10:00:01", 0, 0.0));
+
+    // One second of movement is shorter than the dwell
+    let blip = pipeline.process(reading("10:00:02", 1, 0.0));
+    assert_eq!(blip.state, ActivityState::Sedentary);
+    let after = pipeline.process(reading("10:00:03", 0, 0.0));
+    assert_eq!(after.timer, 3);
+
+    // Sustained movement does reset it
+    for ts in ["10:00:04", "10:00:05"] {
+        pipeline.process(reading(ts, 1, 0.0));
+    }
+    let active = pipeline.process(reading("10:00:06", 1, 0.0));
+    assert_eq!(active.state, ActivityState::Active);
+    assert_eq!(active.timer, 0);
+}
+
+// run() Tests
+
+#[test]
+fn test_pipeline_computes_delta_from_axes() {
+    let mut pipeline = undebounced(Pipeline::new());
+
+    let first = pipeline.process(imu_reading("10:00:00", 0.0, 0.0, 1.0));
+    assert_eq!(first.val, 0.0); // No previous sample yet
+    assert_eq!(first.magnitude, Some(1.0));
+    assert_eq!(first.imu.unwrap().az, 1.0);
+
+    // Magnitude 1.5, delta 0.5 -> smoothed over two samples
+    let second = pipeline.process(imu_reading("10:00:00", 0.0, 0.9, 1.2));
+    assert!((second.magnitude.unwrap() - 1.5).abs() < 1e-6);
+    assert!((second.val - 0.25).abs() < 1e-6);
+    assert_eq!(second.state, ActivityState::Active);
+}
+
+#[test]
+fn test_pipeline_still_axes_are_sedentary() {
+    let mut pipeline = Pipeline::new();
+
+    // Tilted but not moving: constant magnitude, any orientation
+    let out = pipeline.process(imu_reading("10:00:00", 0.0, 0.0, 1.0));
+    pipeline.process(imu_reading("10:00:01", 0.6, 0.0, 0.8));
+    let last = pipeline.process(imu_reading("10:00:02", 0.0, 0.8, 0.6));
+
+    assert_eq!(out.state, ActivityState::Sedentary);
+    assert_eq!(last.state, ActivityState::Sedentary);
+    assert_eq!(last.timer, 2);
+}
+
+#[test]
+fn test_pipeline_legacy_reading_has_no_magnitude() {
+    let mut pipeline = Pipeline::new();
+    let out = pipeline.process(reading("10:00:00", 0, 0.01));
+
+    assert_eq!(out.magnitude, None);
+}
+
+#[test]
+fn test_pipeline_stamps_device_time() {
+    let mut pipeline = Pipeline::new();
+    let received_at = "2026-10-17T10:00:05.200Z".parse().unwrap();
+    let mut raw = reading("10:00:00", 0, 0.0);
+    raw.received_at = Some(received_at);
+
+    let timing = pipeline.process(raw).timing.unwrap();
+
+    assert_eq!(timing.received_at, received_at);
+    assert_eq!(
+        timing.device_time,
+        Some("2026-10-17T10:00:00Z".parse().unwrap())
+    );
+    assert_eq!(timing.clock_offset_ms, Some(5200));
+    assert_eq!(timing.measured_at, received_at);
+}
+
+#[test]
+fn test_pipeline_out_of_range_kept_out_of_smoothing() {
+    let mut pipeline = Pipeline::new();
+    pipeline.process(reading("10:00:00", 0, 0.01));
+
+    let output = pipeline.process(reading("10:00:00", 0, f32::NAN));
+    assert_eq!(output.faults, vec![SensorFault::OutOfRange]);
+    assert!((output.val - 0.01).abs() < 1e-6);
+    assert_eq!(pipeline.take_health().len(), 1);
+
+    let output = pipeline.process(reading("10:00:01", 0, 0.03));
+    assert!(output.faults.is_empty());
+    assert!((output.val - 0.02).abs() < 1e-6);
+}
+
+#[test]
+fn test_pipeline_ignores_stuck_pir() {
+    let mut pipeline = undebounced(Pipeline::new());
+    let start = Utc::now();
+    let mut output = None;
+    for second in 0..=30 * 60 {
+        let now = start + TimeDelta::seconds(second);
+        output = Some(pipeline.process(RawReading {
+            received_at: Some(now),
+            ..reading(&now.format("%H:%M:%S").to_string(), 1, 0.0)
+        }));
+    }
+
+    // Still desk, PIR stuck high: sedentary from the moment it's flagged
+    let output = output.unwrap();
+    assert_eq!(output.faults, vec![SensorFault::StuckPir]);
+    assert_eq!(output.state, ActivityState::Sedentary);
+    assert_eq!(pipeline.take_health()[0].fault, SensorFault::StuckPir);
+}
+
+#[test]
+fn test_pipeline_handshake_adapts_smoothing_window() {
+    let mut pipeline = Pipeline::new();
+    let hello = Hello {
+        device: "desk-3".to_string(),
+        firmware: "1.4.0".to_string(),
+        protocol: 2,
+        rate_hz: 2,
+        sensors: Vec::new(),
+    };
+    pipeline.handshake(&hello).unwrap();
+
+    pipeline.process(reading("10:00:00", 0, 0.30));
+    pipeline.process(reading("10:00:00", 0, 0.10));
+    let out = pipeline.process(reading("10:00:01", 0, 0.0));
+
+    // Window is one second at 2Hz: only the last two samples count
+    assert!((out.val - 0.05).abs() < 1e-6);
+}
+
+#[test]
+fn test_pipeline_calibrated_smoothing_window_wins_over_hello() {
+    let settings = Settings::default();
+    settings.set(
+        "desk-3",
+        DeviceSettings {
+            smoothing_window: Some(3),
+            ..DeviceSettings::default()
+        },
+    );
+    let mut pipeline = Pipeline::for_device("desk-3").with_settings(settings.subscribe());
+    let hello = Hello {
+        device: "desk-3".to_string(),
+        firmware: "1.4.0".to_string(),
+        protocol: 2,
+        rate_hz: 2,
+        sensors: Vec::new(),
+    };
+    pipeline.handshake(&hello).unwrap();
+    assert_eq!(pipeline.smoothing_window, 3);
+
+    // Back to one second of samples once the calibration is dropped
+    settings.set("desk-3", DeviceSettings::default());
+    pipeline.process(reading("10:00:00", 0, 0.0));
+    assert_eq!(pipeline.smoothing_window, 2);
+}
+
+#[test]
+fn test_pipeline_tags_device() {
+    let mut pipeline = Pipeline::for_device("/dev/ttyACM0");
+    assert_eq!(
+        pipeline.process(reading("10:00:00", 0, 0.0)).device_id,
+        "/dev/ttyACM0"
+    );
+
+    // A hello names the device
+    let hello = Hello {
+        device: "desk-3".to_string(),
+        firmware: "1.4.0".to_string(),
+        protocol: 2,
+        rate_hz: 10,
+        sensors: vec![],
+    };
+    pipeline.handshake(&hello).unwrap();
+    assert_eq!(
+        pipeline.process(reading("10:00:01", 0, 0.0)).device_id,
+        "desk-3"
+    );
+}
+
+#[test]
+fn test_pipeline_new_device_on_same_link_starts_fresh() {
+    let hello = |device: &str| Hello {
+        device: device.to_string(),
+        firmware: "1.4.0".to_string(),
+        protocol: 2,
+        rate_hz: 10,
+        sensors: vec![],
+    };
+    let at = |ts: &str, received: &str| RawReading {
+        received_at: Some(received.parse().unwrap()),
+        ..reading(ts, 0, 0.0)
+    };
+    let mut pipeline = Pipeline::for_device("/dev/ttyACM0");
+    pipeline.handshake(&hello("desk-3")).unwrap();
+    pipeline.process(at("10:00:00", "2026-10-17T10:00:00Z"));
+    let out = pipeline.process(at("10:00:05", "2026-10-17T10:00:05Z"));
+    assert_eq!(out.timer, 5);
+
+    // Same board again: nothing is reset
+    pipeline.handshake(&hello("desk-3")).unwrap();
+    let out = pipeline.process(at("10:00:06", "2026-10-17T10:00:06Z"));
+    assert_eq!(out.timer, 6);
+
+    // Another board, its RTC 30s behind: no timer or clock offset carried over
+    pipeline.handshake(&hello("desk-4")).unwrap();
+    let out = pipeline.process(at("10:00:07", "2026-10-17T10:00:37Z"));
+    assert_eq!(out.device_id, "desk-4");
+    assert_eq!(out.timer, 0);
+    assert_eq!(out.timing.unwrap().clock_offset_ms, Some(30_000));
+}
+
+#[test]
+fn test_pipelines_are_isolated() {
+    let mut desk = Pipeline::for_device("desk-3");
+    let mut walker = Pipeline::for_device("desk-4");
+
+    // Interleaved readings don't share smoothing or timers
+    for second in 0..5 {
+        let ts = format!("10:00:0{}", second);
+        desk.process(reading(&ts, 0, 0.0));
+        walker.process(reading(&ts, 1, 0.2));
+    }
+    let desk_out = desk.process(reading("10:00:05", 0, 0.0));
+    let walker_out = walker.process(reading("10:00:05", 1, 0.2));
+
+    assert_eq!(desk_out.state, ActivityState::Sedentary);
+    assert_eq!(desk_out.timer, 5);
+    assert_eq!(desk_out.val, 0.0);
+    assert_eq!(walker_out.state, ActivityState::Active);
+    assert_eq!(walker_out.timer, 0);
+}
+
+#[test]
+fn test_read_stops_on_incompatible_hello() {
+    let input = "{\"hello\":{\"device\":\"desk-3\",\"firmware\":\"9.0\",\"protocol\":99,\"rate_hz\":10}}\n{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.0}\n";
+    let mut source = FramedSource::new("test", Cursor::new(input));
+    let mut inputs = Vec::new();
+
+    let err = read(&mut source, |input| inputs.push(input));
+
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(inputs.len(), 1);
+    assert!(matches!(&inputs[0], Input::Hello(hello, Err(_)) if hello.device == "desk-3"));
+}
+
+#[test]
+fn test_read_hands_over_source_until_eof() {
+    let input = "{\"ts\":\"10:00:00\",\"pir\":0,\"acc\":0.0}\nnoise\n{\"ts\":\"10:00:01\",\"pir\":0,\"acc\":0.0}\n";
+    let mut source = FramedSource::new("test", Cursor::new(input));
+    let mut pipeline = Pipeline::new();
+    let mut outputs = Vec::new();
+
+    let err = read(&mut source, |input| {
+        if let Input::Reading(reading) = input {
+            // Stamped on arrival, before queueing
+            assert!(reading.received_at.is_some());
+            outputs.push(pipeline.process(reading));
+        }
+    });
+
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[1].timer, 1);
+}
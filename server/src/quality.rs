@@ -0,0 +1,230 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+// DATA QUALITY
+const GAP: TimeDelta = TimeDelta::seconds(5); // No sample for this long = sensor offline
+const REPORT_EVERY: TimeDelta = TimeDelta::seconds(60); // Sample rate report per minute of data
+const DEGRADED_RATE: f64 = 0.8; // Effective rate below 80% of the expected rate
+const DEGRADED_IRREGULAR: f64 = 0.1; // More than 10% of seconds off the expected count
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityKind {
+    GapStart,   // Readings stopped (sent live when the link can tell, else with gap_end)
+    GapEnd,     // Readings resumed, with the gap duration
+    SampleRate, // Effective rate and spacing over the last minute of data
+}
+
+impl QualityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QualityKind::GapStart => "gap_start",
+            QualityKind::GapEnd => "gap_end",
+            QualityKind::SampleRate => "sample_rate",
+        }
+    }
+}
+
+/// Data quality event for the dashboard and the data_quality table
+/// Format: {"event":"data_quality","kind":"gap_end","source":"/dev/ttyACM0","device_id":"desk-3","start":"...","end":"...","duration_sec":42.0,"expected_hz":10.0,"degraded":true}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QualityEvent {
+    pub event: String,     // Always "data_quality"
+    pub kind: QualityKind, // What happened
+    pub source: String,    // Transport: port path or tcp://, udp://, mqtt://, http:// source
+    #[serde(default)]
+    pub device_id: String, // Device the readings came from, filled in with source
+    pub start: DateTime<Utc>, // Gap start (last sample before it) or report window start
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<DateTime<Utc>>, // Gap end (first sample after it) or report window end
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_sec: Option<f64>,
+    pub expected_hz: f64, // From the device hello (10Hz without one)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_hz: Option<f64>, // sample_rate only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub irregular_sec: Option<u32>, // sample_rate only: seconds with too few/many samples
+    pub degraded: bool,   // Gaps always are, reports when rate or spacing is off
+}
+
+impl QualityEvent {
+    fn new(kind: QualityKind, start: DateTime<Utc>, expected_hz: f64) -> Self {
+        QualityEvent {
+            event: "data_quality".to_string(),
+            kind,
+            source: String::new(),
+            device_id: String::new(),
+            start,
+            end: None,
+            duration_sec: None,
+            expected_hz,
+            effective_hz: None,
+            irregular_sec: None,
+            degraded: true,
+        }
+    }
+
+    fn ending(mut self, end: DateTime<Utc>) -> Self {
+        self.end = Some(end);
+        self.duration_sec = Some((end - self.start).num_milliseconds() as f64 / 1000.0);
+        self
+    }
+}
+
+// One sample's position on both clocks
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    device_time: DateTime<Utc>, // Device clock, used for spacing (survives batch uploads)
+    measured_at: DateTime<Utc>, // Server-anchored time, used in the events
+    received_at: DateTime<Utc>, // Arrival, for live gap detection
+}
+
+// Samples counted per device second over one report window
+#[derive(Debug)]
+struct RateWindow {
+    start: Sample,
+    samples: u64,
+    second: DateTime<Utc>, // Device second being counted
+    second_samples: u32,
+    irregular: u32,
+}
+
+impl RateWindow {
+    fn new(start: Sample) -> Self {
+        RateWindow {
+            start,
+            samples: 0,
+            second: start.device_time,
+            second_samples: 0,
+            irregular: 0,
+        }
+    }
+
+    // Closes the counted second (and any empty ones skipped since)
+    fn advance(&mut self, device_time: DateTime<Utc>, expected_hz: f64) {
+        let tolerance = (expected_hz * 0.2).max(1.0);
+        let count = self.second_samples as f64;
+        if count == 0.0 || (count - expected_hz).abs() > tolerance {
+            self.irregular += 1;
+        }
+        let skipped = (device_time - self.second).num_seconds() - 1;
+        self.irregular += skipped.max(0) as u32;
+        self.second = device_time;
+        self.second_samples = 0;
+    }
+}
+
+/// Watches one stream for gaps, low rates and irregular spacing
+#[derive(Debug)]
+pub struct QualityMonitor {
+    expected_hz: f64,
+    last: Option<Sample>,
+    gap_reported: bool, // gap_start already sent live
+    window: Option<RateWindow>,
+    events: Vec<QualityEvent>,
+}
+
+impl QualityMonitor {
+    pub fn new(expected_hz: u32) -> Self {
+        QualityMonitor {
+            expected_hz: expected_hz as f64,
+            last: None,
+            gap_reported: false,
+            window: None,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn set_expected_hz(&mut self, expected_hz: u32) {
+        self.expected_hz = expected_hz as f64;
+    }
+
+    pub fn observe(
+        &mut self,
+        device_time: DateTime<Utc>,
+        measured_at: DateTime<Utc>,
+        received_at: DateTime<Utc>,
+    ) {
+        let sample = Sample {
+            device_time,
+            measured_at,
+            received_at,
+        };
+
+        if let Some(last) = self.last {
+            // Late samples don't move the stream forward
+            if device_time < last.device_time {
+                return;
+            }
+            if device_time - last.device_time > GAP || self.gap_reported {
+                if !self.gap_reported {
+                    self.events.push(QualityEvent::new(
+                        QualityKind::GapStart,
+                        last.measured_at,
+                        self.expected_hz,
+                    ));
+                }
+                self.events.push(
+                    QualityEvent::new(QualityKind::GapEnd, last.measured_at, self.expected_hz)
+                        .ending(measured_at),
+                );
+                self.gap_reported = false;
+                // The rate window restarts after the gap, which is reported on its own
+                self.window = None;
+            }
+        }
+        self.last = Some(sample);
+
+        let expected_hz = self.expected_hz;
+        let window = self.window.get_or_insert_with(|| RateWindow::new(sample));
+        if device_time > window.second {
+            window.advance(device_time, expected_hz);
+        }
+        if device_time - window.start.device_time >= REPORT_EVERY {
+            let report = Self::report(window, sample, expected_hz);
+            self.events.push(report);
+            *window = RateWindow::new(sample);
+        }
+        window.samples += 1;
+        window.second_samples += 1;
+    }
+
+    /// Called while the link is up but quiet, so a gap is reported as it starts
+    pub fn idle(&mut self, now: DateTime<Utc>) {
+        if let Some(last) = self.last {
+            if !self.gap_reported && now - last.received_at > GAP {
+                self.gap_reported = true;
+                self.events.push(QualityEvent::new(
+                    QualityKind::GapStart,
+                    last.measured_at,
+                    self.expected_hz,
+                ));
+            }
+        }
+    }
+
+    pub fn take_events(&mut self) -> Vec<QualityEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn report(window: &RateWindow, end: Sample, expected_hz: f64) -> QualityEvent {
+        let span = end.device_time - window.start.device_time;
+        let seconds = span.num_seconds().max(1) as f64;
+        let effective_hz = window.samples as f64 / seconds;
+        let mut report = QualityEvent::new(
+            QualityKind::SampleRate,
+            window.start.measured_at,
+            expected_hz,
+        )
+        .ending(end.measured_at);
+        report.effective_hz = Some(effective_hz);
+        report.irregular_sec = Some(window.irregular);
+        report.degraded = effective_hz < expected_hz * DEGRADED_RATE
+            || window.irregular as f64 > seconds * DEGRADED_IRREGULAR;
+        report
+    }
+}
+
+#[cfg(test)]
+#[path = "quality_tests.rs"]
+mod tests;
@@ -0,0 +1,176 @@
+use super::*;
+use chrono::TimeZone;
+
+fn at(secs: i64, millis: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 17, 10, 0, 0).unwrap()
+        + TimeDelta::seconds(secs)
+        + TimeDelta::milliseconds(millis)
+}
+
+// Live stream: whole-second device time, arrival spread within the second
+fn feed(monitor: &mut QualityMonitor, from: i64, to: i64, hz: i64) {
+    for second in from..to {
+        for sample in 0..hz {
+            let arrival = at(second, sample * 1000 / hz);
+            monitor.observe(at(second, 0), arrival, arrival);
+        }
+    }
+}
+
+fn kinds(events: &[QualityEvent]) -> Vec<QualityKind> {
+    events.iter().map(|e| e.kind).collect()
+}
+
+// Gap Tests
+
+#[test]
+fn test_steady_stream_has_no_gaps() {
+    let mut monitor = QualityMonitor::new(10);
+    feed(&mut monitor, 0, 30, 10);
+
+    assert!(monitor.take_events().is_empty());
+}
+
+#[test]
+fn test_gap_reported_when_readings_resume() {
+    let mut monitor = QualityMonitor::new(10);
+    feed(&mut monitor, 0, 10, 10);
+    feed(&mut monitor, 52, 53, 10);
+
+    let events = monitor.take_events();
+    assert_eq!(
+        kinds(&events),
+        vec![QualityKind::GapStart, QualityKind::GapEnd]
+    );
+    assert_eq!(events[0].start, at(9, 900));
+    assert_eq!(events[0].end, None);
+    assert_eq!(events[1].start, at(9, 900));
+    assert_eq!(events[1].end, Some(at(52, 0)));
+    assert_eq!(events[1].duration_sec, Some(42.1));
+    assert!(events[1].degraded);
+    assert!(monitor.take_events().is_empty());
+}
+
+#[test]
+fn test_short_dropout_is_not_a_gap() {
+    let mut monitor = QualityMonitor::new(10);
+    feed(&mut monitor, 0, 10, 10);
+    feed(&mut monitor, 14, 20, 10);
+
+    assert!(monitor.take_events().is_empty());
+}
+
+#[test]
+fn test_idle_reports_gap_start_live() {
+    let mut monitor = QualityMonitor::new(10);
+    feed(&mut monitor, 0, 10, 10);
+
+    monitor.idle(at(12, 0));
+    assert!(monitor.take_events().is_empty());
+
+    monitor.idle(at(20, 0));
+    monitor.idle(at(21, 0));
+    let started = monitor.take_events();
+    assert_eq!(kinds(&started), vec![QualityKind::GapStart]);
+
+    // Only the end follows, the start was already sent
+    feed(&mut monitor, 40, 41, 10);
+    let ended = monitor.take_events();
+    assert_eq!(kinds(&ended), vec![QualityKind::GapEnd]);
+    assert_eq!(ended[0].end, Some(at(40, 0)));
+}
+
+#[test]
+fn test_late_samples_ignored() {
+    let mut monitor = QualityMonitor::new(10);
+    feed(&mut monitor, 0, 10, 10);
+    // Buffered reading from long ago doesn't open or close a gap
+    monitor.observe(at(-60, 0), at(10, 0), at(10, 0));
+    feed(&mut monitor, 10, 11, 10);
+
+    assert!(monitor.take_events().is_empty());
+}
+
+// Sample Rate Tests
+
+#[test]
+fn test_sample_rate_report_every_minute() {
+    let mut monitor = QualityMonitor::new(10);
+    feed(&mut monitor, 0, 61, 10);
+
+    let events = monitor.take_events();
+    assert_eq!(kinds(&events), vec![QualityKind::SampleRate]);
+    let report = &events[0];
+    assert_eq!(report.effective_hz, Some(10.0));
+    assert_eq!(report.irregular_sec, Some(0));
+    assert_eq!(report.expected_hz, 10.0);
+    assert_eq!(report.start, at(0, 0));
+    assert_eq!(report.end, Some(at(60, 0)));
+    assert!(!report.degraded);
+}
+
+#[test]
+fn test_low_rate_is_degraded() {
+    let mut monitor = QualityMonitor::new(10);
+    feed(&mut monitor, 0, 61, 6);
+
+    let report = monitor.take_events().pop().unwrap();
+    assert_eq!(report.effective_hz, Some(6.0));
+    assert!(report.degraded);
+}
+
+#[test]
+fn test_irregular_spacing_is_degraded() {
+    let mut monitor = QualityMonitor::new(10);
+    // Bursty: alternating 4 and 16 samples per second, still 10Hz on average
+    for second in 0..60 {
+        let count = if second % 2 == 0 { 4 } else { 16 };
+        for _ in 0..count {
+            monitor.observe(at(second, 0), at(second, 0), at(second, 0));
+        }
+    }
+    monitor.observe(at(60, 0), at(60, 0), at(60, 0));
+
+    let report = monitor.take_events().pop().unwrap();
+    assert_eq!(report.effective_hz, Some(10.0));
+    assert_eq!(report.irregular_sec, Some(60));
+    assert!(report.degraded);
+}
+
+#[test]
+fn test_expected_rate_from_hello() {
+    let mut monitor = QualityMonitor::new(10);
+    monitor.set_expected_hz(2);
+    feed(&mut monitor, 0, 61, 2);
+
+    let report = monitor.take_events().pop().unwrap();
+    assert_eq!(report.expected_hz, 2.0);
+    assert!(!report.degraded);
+}
+
+#[test]
+fn test_rate_window_restarts_after_gap() {
+    let mut monitor = QualityMonitor::new(10);
+    feed(&mut monitor, 0, 50, 10);
+    feed(&mut monitor, 100, 150, 10);
+
+    // Neither side of the gap has a full minute of data yet
+    assert_eq!(
+        kinds(&monitor.take_events()),
+        vec![QualityKind::GapStart, QualityKind::GapEnd]
+    );
+}
+
+// Wire Format Tests
+
+#[test]
+fn test_quality_event_format() {
+    let event = QualityEvent::new(QualityKind::GapEnd, at(0, 0), 10.0).ending(at(42, 0));
+
+    let json = serde_json::to_string(&event).unwrap();
+    assert!(json.starts_with("{\"event\":\"data_quality\",\"kind\":\"gap_end\""));
+    assert!(json.contains("\"duration_sec\":42.0"));
+    assert!(!json.contains("effective_hz"));
+    assert_eq!(serde_json::from_str::<QualityEvent>(&json).unwrap(), event);
+    assert_eq!(QualityKind::SampleRate.as_str(), "sample_rate");
+}
@@ -0,0 +1,239 @@
+use crate::source::LineError;
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+// LIMITS
+const STORE_PER_MINUTE: u32 = 60; // Stored payloads per device per minute, the rest are only counted
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const MAX_PAYLOAD_BYTES: usize = 1024; // Longer payloads are cut
+pub const MAX_ROWS: i64 = 10_000; // Oldest rows are deleted past this
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuarantineKind {
+    Rejected, // Not a reading (debug text, invalid JSON or fields, corrupted frame)
+    Partial,  // Reading cut off before the end
+}
+
+impl QuarantineKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuarantineKind::Rejected => "rejected",
+            QuarantineKind::Partial => "partial",
+        }
+    }
+}
+
+impl From<&LineError> for QuarantineKind {
+    fn from(error: &LineError) -> Self {
+        match error {
+            LineError::Partial(_) => QuarantineKind::Partial,
+            _ => QuarantineKind::Rejected,
+        }
+    }
+}
+
+/// A bad payload on its way to the quarantine table
+/// Format: {"event":"quarantine","device_id":"desk-3","source":"/dev/ttyACM0","kind":"partial","reason":"EOF while parsing...","payload":"{\"ts\":\"10:00","received_at":"..."}
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuarantinedLine {
+    pub event: String,     // Always "quarantine"
+    pub device_id: String, // Device id, or the source until the device is known
+    pub source: String,    // Ingestion path (port, tcp://, udp://, mqtt://, http://)
+    pub kind: QuarantineKind,
+    pub reason: String,
+    pub payload: String, // Raw line (binary frames as cobs:<hex>), cut at 1KB
+    pub received_at: DateTime<Utc>,
+}
+
+/// Line counters for one device since the server started
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct LineCounts {
+    pub parsed: u64,      // Valid readings
+    pub rejected: u64,    // Lines that aren't readings
+    pub partial: u64,     // Readings cut off before the end
+    pub quarantined: u64, // Bad lines stored (the rest exceeded the rate limit)
+    pub last_reason: Option<String>,
+    pub last_rejected_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct DeviceLines {
+    counts: LineCounts,
+    window_start: Instant,
+    window_stored: u32,
+}
+
+/// Counts good and bad lines per device and sends a rate-limited sample of the
+/// bad ones to the DB worker, so firmware bugs can be diagnosed afterwards.
+/// Shared by all sources through the Sink.
+#[derive(Clone, Default)]
+pub struct Quarantine {
+    tx: Option<broadcast::Sender<String>>,
+    devices: Arc<Mutex<HashMap<String, DeviceLines>>>,
+}
+
+impl Quarantine {
+    pub fn new(tx: broadcast::Sender<String>) -> Self {
+        Quarantine {
+            tx: Some(tx),
+            devices: Arc::default(),
+        }
+    }
+
+    pub fn parsed(&self, device: &str) {
+        self.update(device, |lines| lines.counts.parsed += 1);
+    }
+
+    /// Counts a bad line and returns the entry if it was stored
+    pub fn reject(
+        &self,
+        device: &str,
+        source: &str,
+        payload: &str,
+        error: &LineError,
+    ) -> Option<QuarantinedLine> {
+        let kind = QuarantineKind::from(error);
+        let now = Utc::now();
+        let store = self.update(device, |lines| {
+            match kind {
+                QuarantineKind::Rejected => lines.counts.rejected += 1,
+                QuarantineKind::Partial => lines.counts.partial += 1,
+            }
+            lines.counts.last_reason = Some(error.reason().to_string());
+            lines.counts.last_rejected_at = Some(now);
+
+            if lines.window_start.elapsed() >= RATE_WINDOW {
+                lines.window_start = Instant::now();
+                lines.window_stored = 0;
+            }
+            if lines.window_stored >= STORE_PER_MINUTE {
+                return false;
+            }
+            lines.window_stored += 1;
+            lines.counts.quarantined += 1;
+            true
+        });
+        if !store {
+            return None;
+        }
+
+        let entry = QuarantinedLine {
+            event: "quarantine".to_string(),
+            device_id: device.to_string(),
+            source: source.to_string(),
+            kind,
+            reason: error.reason().to_string(),
+            payload: truncate(payload.trim_end_matches(['\r', '\n']), MAX_PAYLOAD_BYTES),
+            received_at: now,
+        };
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(serde_json::to_string(&entry).unwrap());
+        }
+        Some(entry)
+    }
+
+    /// Counters per device, sorted by device
+    pub fn counts(&self) -> BTreeMap<String, LineCounts> {
+        let devices = self.devices.lock().unwrap();
+        devices
+            .iter()
+            .map(|(device, lines)| (device.clone(), lines.counts.clone()))
+            .collect()
+    }
+
+    fn update<T>(&self, device: &str, apply: impl FnOnce(&mut DeviceLines) -> T) -> T {
+        let mut devices = self.devices.lock().unwrap();
+        let lines = devices
+            .entry(device.to_string())
+            .or_insert_with(|| DeviceLines {
+                counts: LineCounts::default(),
+                window_start: Instant::now(),
+                window_stored: 0,
+            });
+        apply(lines)
+    }
+}
+
+// Cuts at a char boundary
+fn truncate(payload: &str, max: usize) -> String {
+    if payload.len() <= max {
+        return payload.to_string();
+    }
+    let mut end = max;
+    while !payload.is_char_boundary(end) {
+        end -= 1;
+    }
+    payload[..end].to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuarantineParams {
+    pub device: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Stored bad payload, as returned by the API
+#[derive(Debug, Serialize)]
+pub struct QuarantineRecord {
+    pub id: i64,
+    pub device_id: String,
+    pub source: String,
+    pub kind: String,
+    pub reason: String,
+    pub payload: String,
+    pub received_at: DateTime<Utc>,
+}
+
+// GET /api/quarantine?device=<id>&limit=<n>
+pub async fn list_quarantine(
+    State(state): State<AppState>,
+    Query(params): Query<QuarantineParams>,
+) -> Result<Json<Vec<QuarantineRecord>>, (StatusCode, String)> {
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("limit must be 1-{}", MAX_LIMIT),
+        ));
+    }
+
+    let records = sqlx::query_as!(
+        QuarantineRecord,
+        r#"
+        SELECT id, device_id, source, kind, reason, payload, received_at
+        FROM quarantine
+        WHERE $1::TEXT IS NULL OR device_id = $1
+        ORDER BY id DESC
+        LIMIT $2
+        "#,
+        params.device,
+        limit
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(records))
+}
+
+// GET /api/quarantine/stats
+pub async fn quarantine_stats(State(state): State<AppState>) -> Json<BTreeMap<String, LineCounts>> {
+    Json(state.sink.quarantine().counts())
+}
+
+#[cfg(test)]
+#[path = "quarantine_tests.rs"]
+mod tests;
@@ -0,0 +1,123 @@
+use super::*;
+
+fn invalid() -> LineError {
+    LineError::Invalid("missing field `acc`".to_string())
+}
+
+// Counter Tests
+
+#[test]
+fn test_counts_per_device() {
+    let quarantine = Quarantine::default();
+    quarantine.parsed("desk-3");
+    quarantine.parsed("desk-3");
+    quarantine.reject("desk-3", "/dev/ttyACM0", "MPU6050 found", &LineError::Text);
+    quarantine.reject(
+        "desk-3",
+        "/dev/ttyACM0",
+        "{\"ts\":\"10:00",
+        &LineError::Partial("EOF while parsing a string".to_string()),
+    );
+    quarantine.parsed("desk-4");
+
+    let counts = quarantine.counts();
+    let desk3 = &counts["desk-3"];
+    assert_eq!(desk3.parsed, 2);
+    assert_eq!(desk3.rejected, 1);
+    assert_eq!(desk3.partial, 1);
+    assert_eq!(desk3.quarantined, 2);
+    assert_eq!(
+        desk3.last_reason.as_deref(),
+        Some("EOF while parsing a string")
+    );
+    assert!(desk3.last_rejected_at.is_some());
+
+    assert_eq!(counts["desk-4"].parsed, 1);
+    assert_eq!(counts["desk-4"].last_rejected_at, None);
+    // Sorted by device
+    assert_eq!(counts.keys().collect::<Vec<_>>(), vec!["desk-3", "desk-4"]);
+}
+
+#[test]
+fn test_kind_from_line_error() {
+    assert_eq!(
+        QuarantineKind::from(&LineError::Partial(String::new())),
+        QuarantineKind::Partial
+    );
+    assert_eq!(
+        QuarantineKind::from(&LineError::Text),
+        QuarantineKind::Rejected
+    );
+    assert_eq!(QuarantineKind::from(&invalid()), QuarantineKind::Rejected);
+}
+
+// Rate Limit Tests
+
+#[test]
+fn test_store_is_rate_limited_but_counting_is_not() {
+    let quarantine = Quarantine::default();
+    for _ in 0..STORE_PER_MINUTE {
+        assert!(quarantine
+            .reject("desk-3", "/dev/ttyACM0", "{}", &invalid())
+            .is_some());
+    }
+    assert!(quarantine
+        .reject("desk-3", "/dev/ttyACM0", "{}", &invalid())
+        .is_none());
+    // Another device has its own budget
+    assert!(quarantine
+        .reject("desk-4", "/dev/ttyACM1", "{}", &invalid())
+        .is_some());
+
+    let counts = quarantine.counts();
+    assert_eq!(counts["desk-3"].rejected, STORE_PER_MINUTE as u64 + 1);
+    assert_eq!(counts["desk-3"].quarantined, STORE_PER_MINUTE as u64);
+}
+
+// Entry Tests
+
+#[test]
+fn test_payload_is_cut_at_char_boundary() {
+    let quarantine = Quarantine::default();
+    // 'é' is two bytes, so the limit falls inside one
+    let payload = format!("a{}", "é".repeat(MAX_PAYLOAD_BYTES));
+    let entry = quarantine
+        .reject("desk-3", "/dev/ttyACM0", &payload, &LineError::Text)
+        .unwrap();
+
+    assert_eq!(entry.payload.len(), MAX_PAYLOAD_BYTES - 1);
+    assert!(payload.starts_with(&entry.payload));
+}
+
+#[test]
+fn test_payload_line_ending_is_dropped() {
+    let quarantine = Quarantine::default();
+    let entry = quarantine
+        .reject("desk-3", "/dev/ttyACM0", "{\"ts\":1}\r\n", &invalid())
+        .unwrap();
+
+    assert_eq!(entry.payload, "{\"ts\":1}");
+    assert_eq!(entry.reason, "missing field `acc`");
+}
+
+#[test]
+fn test_entry_is_broadcast() {
+    let (tx, mut rx) = broadcast::channel(4);
+    let quarantine = Quarantine::new(tx);
+    quarantine.parsed("desk-3");
+    quarantine.reject(
+        "desk-3",
+        "mqtt://sensors/desk-3",
+        "{\"ts\":",
+        &LineError::Partial("EOF".to_string()),
+    );
+
+    let json = rx.try_recv().unwrap();
+    assert!(json.starts_with(
+        "{\"event\":\"quarantine\",\"device_id\":\"desk-3\",\"source\":\"mqtt://sensors/desk-3\",\"kind\":\"partial\""
+    ));
+    let entry: QuarantinedLine = serde_json::from_str(&json).unwrap();
+    assert_eq!(entry.payload, "{\"ts\":");
+    // Good lines are only counted
+    assert!(rx.try_recv().is_err());
+}
@@ -1,128 +1,479 @@
-use crate::models::{ProcessedState, RawReading};
-use redis::AsyncCommands;
-use std::collections::VecDeque;
-use std::io::{BufRead, BufReader};
+use crate::capture::Capture;
+use crate::commands::{self, AlertPattern, Command, Commands, DeviceCommand};
+use crate::framing::FramedSource;
+use crate::handshake::{Hello, HelloEvent};
+use crate::models::{DeviceStatus, LinkStats, RawReading};
+use crate::pipeline::{self, Input, Pipeline, Sink};
+use crate::quarantine::Quarantine;
+use crate::source::SensorSource;
+use chrono::Utc;
+use serialport::{SerialPort, SerialPortType};
+use std::env;
+use std::io::{self, Write};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use tokio::sync::broadcast;
-
-// CLASSIFICATION THRESHOLDS
-const THRESH_FIDGET: f32 = 0.020; // Smoothed acceleration delta threshold for fidgeting
-const THRESH_ACTIVE: f32 = 0.040; // Smoothed acceleration delta threshold for active
-const SMOOTHING_WINDOW: usize = 10; // Number of samples for smoothing buffer
-
-// ALERT CONFIGURATION
-const ALERT_LIMIT_SEC: u64 = 1200; // 20 minutes
-
-/// Classifies activity state based on PIR and smoothed acceleration
-fn classify_state(pir: i32, smoothed_acc: f32) -> &'static str {
-    if pir == 1 || smoothed_acc > THRESH_ACTIVE {
-        "ACTIVE"
-    } else if smoothed_acc > THRESH_FIDGET {
-        "FIDGET"
-    } else {
-        "SEDENTARY"
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self as tokio_mpsc, error::TrySendError};
+
+// CONNECTION DEFAULTS (overridden by SERIAL_PORT / BAUD_RATE)
+const DEFAULT_PORT: &str = "/dev/ttyACM0";
+const DEFAULT_BAUD_RATE: u32 = 115200;
+const READ_TIMEOUT: Duration = Duration::from_millis(1000);
+const RECONNECT_MIN: Duration = Duration::from_secs(1);
+const RECONNECT_MAX: Duration = Duration::from_secs(30);
+const LINK_STATS_INTERVAL: Duration = Duration::from_secs(10);
+const READ_QUEUE: usize = 1024; // Readings between the read thread and classification (~100s at 10Hz)
+
+/// Serial connection settings, read from the environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct SerialConfig {
+    pub ports: Vec<String>,      // SERIAL_PORT, comma separated for several boards
+    pub baud_rate: u32,          // BAUD_RATE
+    pub usb_vid: Option<u16>,    // SERIAL_VID (hex) - enables USB discovery
+    pub usb_pid: Option<u16>,    // SERIAL_PID (hex) - enables USB discovery
+    pub reconnect_max: Duration, // SERIAL_RECONNECT_MAX_SEC
+}
+
+impl SerialConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| env::var(key).ok())
+    }
+
+    // Split out from from_env so parsing can be tested without touching the process env
+    fn from_lookup(get: impl Fn(&str) -> Option<String>) -> Self {
+        let mut ports: Vec<String> = Vec::new();
+        for port in get("SERIAL_PORT").unwrap_or_default().split(',') {
+            let port = port.trim();
+            if !port.is_empty() && !ports.iter().any(|p| p == port) {
+                ports.push(port.to_string());
+            }
+        }
+        if ports.is_empty() {
+            ports.push(DEFAULT_PORT.to_string());
+        }
+
+        let baud_rate = match get("BAUD_RATE") {
+            Some(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                eprintln!("Invalid BAUD_RATE '{}', using {}", raw, DEFAULT_BAUD_RATE);
+                DEFAULT_BAUD_RATE
+            }),
+            None => DEFAULT_BAUD_RATE,
+        };
+
+        let reconnect_max = get("SERIAL_RECONNECT_MAX_SEC")
+            .and_then(|raw| raw.trim().parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(RECONNECT_MAX);
+
+        SerialConfig {
+            ports,
+            baud_rate,
+            usb_vid: get("SERIAL_VID").and_then(|raw| parse_usb_id(&raw)),
+            usb_pid: get("SERIAL_PID").and_then(|raw| parse_usb_id(&raw)),
+            reconnect_max,
+        }
     }
+
+    fn discovery_enabled(&self) -> bool {
+        self.usb_vid.is_some() || self.usb_pid.is_some()
+    }
+}
+
+/// Parses a USB vendor/product id as written by lsusb ("2341" or "0x2341")
+fn parse_usb_id(raw: &str) -> Option<u16> {
+    let raw = raw.trim();
+    let hex = raw
+        .strip_prefix("0x")
+        .or_else(|| raw.strip_prefix("0X"))
+        .unwrap_or(raw);
+    u16::from_str_radix(hex, 16).ok()
 }
 
-pub fn spawn_serial_listener(tx: broadcast::Sender<String>, redis_client: redis::Client) {
+/// Exponential reconnect delay: 1s, 2s, 4s ... capped at max
+#[derive(Debug)]
+pub(crate) struct Backoff {
+    current: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub(crate) fn new(min: Duration, max: Duration) -> Self {
+        Backoff {
+            current: min,
+            min,
+            max,
+        }
+    }
+
+    pub(crate) fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.current = self.min;
+    }
+}
+
+/// Finds the first USB serial device matching the configured VID/PID
+fn discover_port(config: &SerialConfig) -> Option<String> {
+    let ports = serialport::available_ports().ok()?;
+    ports.into_iter().find_map(|p| match p.port_type {
+        SerialPortType::UsbPort(info)
+            if config.usb_vid.is_none_or(|vid| vid == info.vid)
+                && config.usb_pid.is_none_or(|pid| pid == info.pid) =>
+        {
+            Some(p.port_name)
+        }
+        _ => None,
+    })
+}
+
+/// Serial port implementation of SensorSource (JSON lines or binary frames from the Arduino)
+pub struct SerialSource {
+    frames: FramedSource<Box<dyn SerialPort>>,
+}
+
+impl SerialSource {
+    /// Resolves the port name (re-discovered on every attempt, since a replugged
+    /// board may come back as /dev/ttyACM1) and opens it; `port` is used when
+    /// discovery is off
+    pub fn open(config: &SerialConfig, port: &str) -> Result<Self, String> {
+        let port_name = if config.discovery_enabled() {
+            discover_port(config).ok_or_else(|| {
+                format!(
+                    "No USB serial device matching VID {} / PID {}",
+                    config
+                        .usb_vid
+                        .map_or("*".to_string(), |v| format!("{:04x}", v)),
+                    config
+                        .usb_pid
+                        .map_or("*".to_string(), |p| format!("{:04x}", p)),
+                )
+            })?
+        } else {
+            port.to_string()
+        };
+
+        let port = serialport::new(&port_name, config.baud_rate)
+            .timeout(READ_TIMEOUT)
+            .open()
+            .map_err(|e| format!("{}: {}", port_name, e))?;
+
+        Ok(SerialSource {
+            frames: FramedSource::new(&port_name, port),
+        })
+    }
+
+    pub fn with_capture(mut self, capture: Option<Capture>) -> Self {
+        self.frames = self.frames.with_capture(capture);
+        self
+    }
+
+    pub fn with_commands(mut self, commands: Commands) -> Self {
+        self.frames = self.frames.with_commands(commands);
+        self
+    }
+
+    pub fn with_quarantine(mut self, quarantine: Quarantine) -> Self {
+        self.frames = self.frames.with_quarantine(quarantine);
+        self
+    }
+
+    pub fn stats(&self) -> Arc<Mutex<LinkStats>> {
+        self.frames.stats()
+    }
+
+    /// Second handle on the port for the command writer
+    pub fn writer(&self) -> io::Result<Box<dyn SerialPort>> {
+        self.frames.get_ref().try_clone().map_err(io::Error::from)
+    }
+}
+
+/// Drains queued commands onto the port until every name of the link is detached
+fn spawn_command_writer(
+    mut port: Box<dyn SerialPort>,
+    rx: mpsc::Receiver<DeviceCommand>,
+    commands: Commands,
+) {
     thread::spawn(move || {
-        let port_name = "/dev/ttyACM0";
-        let baud_rate = 115200;
-
-        println!("Connecting to Arduino at {}...", port_name);
-
-        let port = serialport::new(port_name, baud_rate)
-            .timeout(Duration::from_millis(1000))
-            .open();
-
-        // Create a dedicated async runtime for the serial thread
-        let rt = tokio::runtime::Runtime::new().unwrap();
-
-        // State tracking
-        let mut acc_buffer: VecDeque<f32> = VecDeque::with_capacity(SMOOTHING_WINDOW);
-        let mut sedentary_timer: u64 = 0;
-        let mut last_second: Option<String> = None;
-
-        match port {
-            Ok(p) => {
-                println!("Serial Connected! Processing raw sensor data...");
-                let mut reader = BufReader::new(p);
-                let mut line = String::new();
-
-                loop {
-                    line.clear();
-                    if let Ok(bytes_read) = reader.read_line(&mut line) {
-                        if bytes_read == 0 {
-                            continue;
-                        }
-
-                        let clean_line = line.trim();
-                        if clean_line.starts_with('{') {
-                            // Parse raw Arduino data
-                            if let Ok(reading) = serde_json::from_str::<RawReading>(clean_line) {
-                                // Add to smoothing buffer
-                                if acc_buffer.len() >= SMOOTHING_WINDOW {
-                                    acc_buffer.pop_front();
-                                }
-                                acc_buffer.push_back(reading.acc);
-
-                                // Calculate smoothed acceleration (mean of buffer)
-                                let smoothed_acc: f32 = if acc_buffer.is_empty() {
-                                    0.0
-                                } else {
-                                    acc_buffer.iter().sum::<f32>() / acc_buffer.len() as f32
-                                };
-
-                                // Classify state
-                                let state = classify_state(reading.pir, smoothed_acc);
-
-                                // Update sedentary timer (once per second based on timestamp)
-                                let current_second = reading.ts.clone();
-                                if last_second.as_ref() != Some(&current_second) {
-                                    last_second = Some(current_second);
-
-                                    match state {
-                                        "ACTIVE" => sedentary_timer = 0,     // Reset on activity
-                                        "FIDGET" => {}                       // Pause
-                                        "SEDENTARY" => sedentary_timer += 1, // Increment
-                                        _ => {}
-                                    }
-                                }
-
-                                // Build processed output
-                                let output = ProcessedState {
-                                    state: state.to_string(),
-                                    timer: sedentary_timer,
-                                    val: smoothed_acc,
-                                    alert: sedentary_timer >= ALERT_LIMIT_SEC,
-                                    timestamp: reading.ts,
-                                };
-
-                                let json_out = serde_json::to_string(&output).unwrap();
-
-                                // Broadcast to WebSocket and cache in Redis
-                                rt.block_on(async {
-                                    // Redis cache for reconnection
-                                    if let Ok(mut con) =
-                                        redis_client.get_multiplexed_async_connection().await
-                                    {
-                                        let _: () = con
-                                            .lpush("sensor_history", &json_out)
-                                            .await
-                                            .unwrap_or(());
-                                        let _: () =
-                                            con.ltrim("sensor_history", 0, 99).await.unwrap_or(());
-                                    }
-                                    // Push to WebSocket
-                                    let _ = tx.send(json_out);
-                                });
-                            }
-                        }
+        for command in rx {
+            let result = port
+                .write_all(command.to_line().as_bytes())
+                .and_then(|_| port.flush())
+                .map_err(|e| e.to_string());
+            if let Err(e) = &result {
+                eprintln!("Failed to send command {}: {}", command.id, e);
+            }
+            commands.mark_sent(command.id, result);
+        }
+    });
+}
+
+impl SensorSource for SerialSource {
+    fn name(&self) -> &str {
+        self.frames.name()
+    }
+
+    fn next_reading(&mut self) -> io::Result<Option<RawReading>> {
+        self.frames.next_reading()
+    }
+
+    fn take_hello(&mut self) -> Option<Hello> {
+        self.frames.take_hello()
+    }
+}
+
+/// Starts one listener per configured port (a single one when discovering by USB id)
+pub fn spawn_serial_listener(sink: Sink, commands: Commands) {
+    let config = SerialConfig::from_env();
+    let ports = if config.discovery_enabled() {
+        config.ports[..1].to_vec()
+    } else {
+        config.ports.clone()
+    };
+    for port in ports {
+        spawn_port_listener(config.clone(), port, sink.clone(), commands.clone());
+    }
+}
+
+/// What the read thread of a port tells its classify task
+#[derive(Debug)]
+enum PortEvent {
+    Connected {
+        port_name: String,
+        stats: Arc<Mutex<LinkStats>>,
+        command_tx: Option<mpsc::Sender<DeviceCommand>>,
+    },
+    Input(Input),
+    Lost {
+        port_name: String,
+        detail: String,
+        retry_in: Duration,
+    },
+}
+
+fn spawn_port_listener(config: SerialConfig, port: String, sink: Sink, commands: Commands) {
+    let (events, rx) = tokio_mpsc::channel(READ_QUEUE);
+    tokio::spawn(classify_port(rx, sink.clone(), commands.clone()));
+    thread::spawn(move || read_port(config, port, events, sink, commands));
+}
+
+/// Read stage: owns the blocking port, reconnects with backoff, and never waits
+/// on the rest of the pipeline for readings. When the queue is full the newest
+/// reading is dropped and counted; connection events and hellos always get through.
+fn read_port(
+    config: SerialConfig,
+    port: String,
+    events: tokio_mpsc::Sender<PortEvent>,
+    sink: Sink,
+    commands: Commands,
+) {
+    let metrics = sink.metrics();
+    let mut backoff = Backoff::new(RECONNECT_MIN, config.reconnect_max);
+
+    loop {
+        if config.discovery_enabled() {
+            println!("Searching for Arduino by USB VID/PID...");
+        } else {
+            println!("Connecting to Arduino at {}...", port);
+        }
+
+        let (port_name, detail) = match SerialSource::open(&config, &port).map(|s| {
+            s.with_capture(sink.capture())
+                .with_commands(commands.clone())
+                .with_quarantine(sink.quarantine())
+        }) {
+            Ok(mut source) => {
+                let port_name = source.name().to_string();
+                println!(
+                    "Serial Connected on {}! Processing raw sensor data...",
+                    port_name
+                );
+                backoff.reset();
+
+                // Commands are addressable by port name now, and by device id after the hello
+                let command_tx = match source.writer() {
+                    Ok(writer) => {
+                        let (command_tx, command_rx) = mpsc::channel();
+                        spawn_command_writer(writer, command_rx, commands.clone());
+                        commands.attach(&port_name, command_tx.clone());
+                        Some(command_tx)
+                    }
+                    Err(e) => {
+                        eprintln!("Command channel unavailable on {}: {}", port_name, e);
+                        None
                     }
+                };
+                let connected = PortEvent::Connected {
+                    port_name: port_name.clone(),
+                    stats: source.stats(),
+                    command_tx,
+                };
+                if events.blocking_send(connected).is_err() {
+                    return;
+                }
+
+                // Read until the device goes away
+                let lost = pipeline::read(&mut source, |input| match input {
+                    Input::Reading(_) => match events.try_send(PortEvent::Input(input)) {
+                        Ok(()) => metrics.reading_queued(),
+                        Err(TrySendError::Full(_)) => metrics.reading_dropped(),
+                        Err(TrySendError::Closed(_)) => {}
+                    },
+                    Input::Hello(..) => {
+                        let _ = events.blocking_send(PortEvent::Input(input));
+                    }
+                    Input::Idle => {
+                        let _ = events.try_send(PortEvent::Input(input));
+                    }
+                });
+
+                eprintln!("Serial connection lost on {}: {}", port_name, lost);
+                (port_name, lost.to_string())
+            }
+            Err(e) => {
+                eprintln!("Serial Error: {}", e);
+                (port.clone(), e)
+            }
+        };
+
+        let delay = backoff.next_delay();
+        let lost = PortEvent::Lost {
+            port_name,
+            detail,
+            retry_in: delay,
+        };
+        if events.blocking_send(lost).is_err() {
+            return;
+        }
+        println!("Retrying serial connection in {}s...", delay.as_secs());
+        thread::sleep(delay);
+    }
+}
+
+// One connection of the port, as seen by the classify task
+struct Connection {
+    port_name: String,
+    stats: Arc<Mutex<LinkStats>>,
+    command_tx: Option<mpsc::Sender<DeviceCommand>>,
+    links: Vec<String>, // Names the command channel is attached under
+    alert_to: Option<(String, AlertPattern)>, // Device that can buzz/vibrate
+    alerting: bool,
+    last_report: Instant,
+}
+
+/// Classify stage of one port: runs the readings through the pipeline and fans
+/// the results out through the sink, which never waits on Redis or the DB
+async fn classify_port(
+    mut events: tokio_mpsc::Receiver<PortEvent>,
+    sink: Sink,
+    commands: Commands,
+) {
+    // Pipeline state is kept across reconnects so a brief unplug doesn't reset the timer.
+    // It is created on the first connect, named after the port until a hello names the device;
+    // a hello from another board starts it over.
+    let mut pipeline: Option<Pipeline> = None;
+    let mut connection: Option<Connection> = None;
+
+    while let Some(event) = events.recv().await {
+        match event {
+            PortEvent::Connected {
+                port_name,
+                stats,
+                command_tx,
+            } => {
+                sink.publish_status(&DeviceStatus::connected(&port_name));
+                pipeline.get_or_insert_with(|| sink.new_pipeline(&port_name));
+                connection = Some(Connection {
+                    links: vec![port_name.clone()],
+                    port_name,
+                    stats,
+                    command_tx,
+                    alert_to: None,
+                    alerting: false,
+                    last_report: Instant::now(),
+                });
+            }
+            PortEvent::Input(input) => {
+                let (Some(pipeline), Some(link)) = (pipeline.as_mut(), connection.as_mut()) else {
+                    continue;
+                };
+                classify(input, pipeline, link, &sink, &commands);
+                let device = pipeline.device_id().to_string();
+                sink.publish_quality(&link.port_name, &device, pipeline.take_quality());
+                sink.publish_health(&link.port_name, &device, pipeline.take_health());
+            }
+            PortEvent::Lost {
+                port_name,
+                detail,
+                retry_in,
+            } => {
+                if let Some(link) = connection.take() {
+                    sink.publish_link_stats(&link.stats.lock().unwrap());
+                    commands.detach(&link.links);
                 }
+                sink.publish_status(&DeviceStatus::disconnected(
+                    &port_name,
+                    &detail,
+                    Some(retry_in),
+                ));
             }
-            Err(e) => eprintln!("Serial Error: {}", e),
         }
-    });
+    }
 }
+
+fn classify(
+    input: Input,
+    pipeline: &mut Pipeline,
+    link: &mut Connection,
+    sink: &Sink,
+    commands: &Commands,
+) {
+    match input {
+        Input::Hello(hello, result) => {
+            let result = result.and_then(|_| pipeline.handshake(&hello));
+            sink.publish_hello(&HelloEvent::new(&hello, &link.port_name, &result));
+            if let (Ok(()), Some(command_tx)) = (&result, &link.command_tx) {
+                if commands.is_connected(&link.port_name) {
+                    commands.attach(&hello.device, command_tx.clone());
+                    link.links.push(hello.device.clone());
+                    link.alert_to = commands::alert_pattern(&hello)
+                        .map(|pattern| (hello.device.clone(), pattern));
+                }
+            }
+        }
+        Input::Reading(reading) => {
+            let output = pipeline.process(reading);
+
+            // Buzz/vibrate once when the alert starts, if the device can
+            if let (true, false, Some((device, pattern))) =
+                (output.alert, link.alerting, link.alert_to.as_ref())
+            {
+                let alert = Command::Alert {
+                    pattern: *pattern,
+                    duration_ms: commands::ALERT_DURATION_MS,
+                };
+                if let Err((_, e)) = commands.send(device, alert) {
+                    eprintln!("Alert command not sent: {}", e);
+                }
+            }
+            link.alerting = output.alert;
+
+            sink.publish(&output);
+            // Link counters, reported periodically
+            if link.last_report.elapsed() >= LINK_STATS_INTERVAL {
+                sink.publish_link_stats(&link.stats.lock().unwrap());
+                link.last_report = Instant::now();
+            }
+        }
+        Input::Idle => pipeline.idle(Utc::now()),
+    }
+}
+
+#[cfg(test)]
+#[path = "serial_tests.rs"]
+mod tests;
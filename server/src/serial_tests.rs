@@ -0,0 +1,99 @@
+use super::*;
+use std::collections::HashMap;
+
+fn config_from(vars: &[(&str, &str)]) -> SerialConfig {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    SerialConfig::from_lookup(|key| vars.get(key).cloned())
+}
+
+// SerialConfig Tests
+
+#[test]
+fn test_config_defaults() {
+    let config = config_from(&[]);
+
+    assert_eq!(config.ports, vec!["/dev/ttyACM0"]);
+    assert_eq!(config.baud_rate, 115200);
+    assert_eq!(config.usb_vid, None);
+    assert_eq!(config.usb_pid, None);
+    assert_eq!(config.reconnect_max, Duration::from_secs(30));
+    assert!(!config.discovery_enabled());
+}
+
+#[test]
+fn test_config_from_env_vars() {
+    let config = config_from(&[
+        ("SERIAL_PORT", "/dev/ttyUSB1"),
+        ("BAUD_RATE", "57600"),
+        ("SERIAL_RECONNECT_MAX_SEC", "10"),
+    ]);
+
+    assert_eq!(config.ports, vec!["/dev/ttyUSB1"]);
+    assert_eq!(config.baud_rate, 57600);
+    assert_eq!(config.reconnect_max, Duration::from_secs(10));
+}
+
+#[test]
+fn test_config_several_ports() {
+    let config = config_from(&[(
+        "SERIAL_PORT",
+        "/dev/ttyACM0, /dev/ttyACM1,,/dev/ttyUSB0,/dev/ttyACM0",
+    )]);
+    assert_eq!(
+        config.ports,
+        vec!["/dev/ttyACM0", "/dev/ttyACM1", "/dev/ttyUSB0"]
+    );
+
+    let config = config_from(&[("SERIAL_PORT", " , ")]);
+    assert_eq!(config.ports, vec!["/dev/ttyACM0"]);
+}
+
+#[test]
+fn test_config_invalid_baud_rate_falls_back() {
+    let config = config_from(&[("BAUD_RATE", "fast")]);
+    assert_eq!(config.baud_rate, 115200);
+}
+
+#[test]
+fn test_config_usb_discovery() {
+    let config = config_from(&[("SERIAL_VID", "0x2341"), ("SERIAL_PID", "0043")]);
+
+    assert_eq!(config.usb_vid, Some(0x2341));
+    assert_eq!(config.usb_pid, Some(0x0043));
+    assert!(config.discovery_enabled());
+}
+
+#[test]
+fn test_parse_usb_id() {
+    assert_eq!(parse_usb_id("2341"), Some(0x2341));
+    assert_eq!(parse_usb_id("0X1A86"), Some(0x1a86));
+    assert_eq!(parse_usb_id(" 7523 "), Some(0x7523));
+    assert_eq!(parse_usb_id("arduino"), None);
+    assert_eq!(parse_usb_id("12345"), None);
+}
+
+// Backoff Tests
+
+#[test]
+fn test_backoff_doubles_up_to_max() {
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+
+    assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+    assert_eq!(backoff.next_delay(), Duration::from_secs(2));
+    assert_eq!(backoff.next_delay(), Duration::from_secs(4));
+    assert_eq!(backoff.next_delay(), Duration::from_secs(5));
+    assert_eq!(backoff.next_delay(), Duration::from_secs(5));
+}
+
+#[test]
+fn test_backoff_reset() {
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
+    backoff.next_delay();
+    backoff.next_delay();
+
+    backoff.reset();
+    assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+}
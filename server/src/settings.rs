@@ -0,0 +1,384 @@
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use logic::Thresholds;
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex, MutexGuard};
+
+// ALERT CONFIGURATION
+pub const ALERT_LIMIT_SEC: u64 = 1200; // 20 minutes, for devices without their own setting
+const MIN_ALERT_LIMIT_SEC: u64 = 60;
+const MAX_ALERT_LIMIT_SEC: u64 = 8 * 3600;
+const MAX_SMOOTHING_WINDOW: usize = 100; // Samples (10s at 10Hz)
+const HISTORY_LIMIT: i64 = 100; // Audit entries returned
+
+/// Classification settings of one device
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSettings {
+    pub thresholds: Thresholds,
+    pub alert_limit_sec: u64,            // Sedentary time before the alert
+    pub smoothing_window: Option<usize>, // Samples; None = one second at the device's rate
+}
+
+impl Default for DeviceSettings {
+    fn default() -> Self {
+        DeviceSettings {
+            thresholds: Thresholds::default(),
+            alert_limit_sec: ALERT_LIMIT_SEC,
+            smoothing_window: None,
+        }
+    }
+}
+
+/// Body of PUT /api/devices/:id/settings; fields left out keep their value
+#[derive(Debug, Deserialize)]
+pub struct SettingsUpdate {
+    pub fidget: Option<f32>,
+    pub active: Option<f32>,
+    pub alert_limit_sec: Option<u64>,
+    #[serde(default, deserialize_with = "explicit")]
+    pub smoothing_window: Option<Option<usize>>, // Some(None) = null, back to one second
+    pub changed_by: String, // Who made the change, self-reported (the token is shared)
+}
+
+// Tells an explicit null (Some(None)) apart from a field left out (None)
+fn explicit<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+impl DeviceSettings {
+    /// The settings after an update, if they still make sense
+    pub fn updated(&self, update: &SettingsUpdate) -> Result<DeviceSettings, String> {
+        let settings = DeviceSettings {
+            thresholds: Thresholds {
+                fidget: update.fidget.unwrap_or(self.thresholds.fidget),
+                active: update.active.unwrap_or(self.thresholds.active),
+            },
+            alert_limit_sec: update.alert_limit_sec.unwrap_or(self.alert_limit_sec),
+            smoothing_window: update.smoothing_window.unwrap_or(self.smoothing_window),
+        };
+        let Thresholds { fidget, active } = settings.thresholds;
+        if !fidget.is_finite() || !active.is_finite() || fidget <= 0.0 || active <= fidget {
+            return Err(format!(
+                "thresholds must satisfy 0 < fidget < active (got fidget {}, active {})",
+                fidget, active
+            ));
+        }
+        if !(MIN_ALERT_LIMIT_SEC..=MAX_ALERT_LIMIT_SEC).contains(&settings.alert_limit_sec) {
+            return Err(format!(
+                "alert_limit_sec must be {}-{}",
+                MIN_ALERT_LIMIT_SEC, MAX_ALERT_LIMIT_SEC
+            ));
+        }
+        if let Some(window) = settings.smoothing_window {
+            if !(1..=MAX_SMOOTHING_WINDOW).contains(&window) {
+                return Err(format!(
+                    "smoothing_window must be 1-{} samples",
+                    MAX_SMOOTHING_WINDOW
+                ));
+            }
+        }
+        Ok(settings)
+    }
+}
+
+pub type SettingsMap = Arc<HashMap<String, DeviceSettings>>;
+
+/// Live copy of the device_settings table. Pipelines subscribe and pick up
+/// changes with their next reading, so nothing needs a restart.
+#[derive(Clone)]
+pub struct Settings {
+    tx: Arc<watch::Sender<SettingsMap>>,
+    writes: Arc<Mutex<()>>, // One change at a time, from reading the old values to set()
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            tx: Arc::new(watch::Sender::new(SettingsMap::default())),
+            writes: Arc::new(Mutex::new(())),
+        }
+    }
+}
+
+impl Settings {
+    /// Fills the live copy from the database at startup
+    pub async fn load(&self, pool: &PgPool) -> Result<usize, sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT device_id, thresh_fidget, thresh_active, alert_limit_sec, smoothing_window FROM device_settings"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let map: HashMap<String, DeviceSettings> = rows
+            .into_iter()
+            .map(|row| {
+                let settings = DeviceSettings {
+                    thresholds: Thresholds {
+                        fidget: row.thresh_fidget,
+                        active: row.thresh_active,
+                    },
+                    alert_limit_sec: row.alert_limit_sec as u64,
+                    smoothing_window: row.smoothing_window.map(|n| n as usize),
+                };
+                (row.device_id, settings)
+            })
+            .collect();
+        let count = map.len();
+        self.tx.send_replace(Arc::new(map));
+        Ok(count)
+    }
+
+    /// Held across a change: read the stored settings, write them and set() the
+    /// live copy before releasing, so concurrent changes can't overwrite each other
+    pub async fn lock_writes(&self) -> MutexGuard<'_, ()> {
+        self.writes.lock().await
+    }
+
+    /// Settings of a device (the defaults unless it has its own)
+    pub fn get(&self, device: &str) -> DeviceSettings {
+        self.tx.borrow().get(device).copied().unwrap_or_default()
+    }
+
+    pub fn set(&self, device: &str, settings: DeviceSettings) {
+        self.tx.send_modify(|map| {
+            let mut updated = HashMap::clone(map);
+            updated.insert(device.to_string(), settings);
+            *map = Arc::new(updated);
+        });
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<SettingsMap> {
+        self.tx.subscribe()
+    }
+}
+
+/// Effective settings of a device, as returned by the API
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct SettingsResponse {
+    pub device_id: String,
+    pub thresholds: Thresholds,
+    pub alert_limit_sec: u64,
+    pub smoothing_window: Option<usize>,
+    pub updated_by: Option<String>, // None while the defaults apply
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// One audited change
+#[derive(Debug, Serialize)]
+pub struct SettingsChange {
+    pub id: i64,
+    pub changed_by: String,
+    pub changed_at: DateTime<Utc>,
+    pub old_fidget: f32,
+    pub old_active: f32,
+    pub old_alert_limit_sec: i32,
+    pub old_smoothing_window: Option<i32>,
+    pub fidget: f32,
+    pub active: f32,
+    pub alert_limit_sec: i32,
+    pub smoothing_window: Option<i32>,
+}
+
+/// Who made a change, required for the audit trail. The command token is
+/// shared, so this is the caller's own word and is stored as given.
+pub fn changed_by(raw: &str) -> Result<String, (StatusCode, String)> {
+    let changed_by = raw.trim();
+    if changed_by.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "changed_by must not be empty".to_string(),
+        ));
+    }
+    Ok(changed_by.to_string())
+}
+
+/// Stored settings of a device, locking its row until the caller's transaction ends
+pub async fn current(
+    tx: &mut Transaction<'_, Postgres>,
+    device: &str,
+) -> Result<DeviceSettings, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT thresh_fidget, thresh_active, alert_limit_sec, smoothing_window
+        FROM device_settings
+        WHERE device_id = $1
+        FOR UPDATE
+        "#,
+        device
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(
+        row.map_or_else(DeviceSettings::default, |row| DeviceSettings {
+            thresholds: Thresholds {
+                fidget: row.thresh_fidget,
+                active: row.thresh_active,
+            },
+            alert_limit_sec: row.alert_limit_sec as u64,
+            smoothing_window: row.smoothing_window.map(|n| n as usize),
+        }),
+    )
+}
+
+/// Writes a device's new settings and the audit entry inside the caller's transaction.
+/// The live copy is left to the caller, to update once the transaction committed.
+pub async fn store(
+    tx: &mut Transaction<'_, Postgres>,
+    device: &str,
+    old: &DeviceSettings,
+    new: &DeviceSettings,
+    changed_by: &str,
+) -> Result<DateTime<Utc>, sqlx::Error> {
+    let updated_at = sqlx::query_scalar!(
+        r#"
+        INSERT INTO device_settings
+            (device_id, thresh_fidget, thresh_active, alert_limit_sec, smoothing_window, updated_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (device_id) DO UPDATE SET
+            thresh_fidget = EXCLUDED.thresh_fidget,
+            thresh_active = EXCLUDED.thresh_active,
+            alert_limit_sec = EXCLUDED.alert_limit_sec,
+            smoothing_window = EXCLUDED.smoothing_window,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        RETURNING updated_at
+        "#,
+        device,
+        new.thresholds.fidget,
+        new.thresholds.active,
+        new.alert_limit_sec as i32,
+        new.smoothing_window.map(|n| n as i32),
+        changed_by
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO settings_audit
+            (device_id, changed_by, old_fidget, old_active, old_alert_limit_sec,
+             old_smoothing_window, thresh_fidget, thresh_active, alert_limit_sec,
+             smoothing_window)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+        device,
+        changed_by,
+        old.thresholds.fidget,
+        old.thresholds.active,
+        old.alert_limit_sec as i32,
+        old.smoothing_window.map(|n| n as i32),
+        new.thresholds.fidget,
+        new.thresholds.active,
+        new.alert_limit_sec as i32,
+        new.smoothing_window.map(|n| n as i32)
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(updated_at)
+}
+
+// GET /api/devices/:id/settings
+pub async fn get_settings(
+    State(state): State<AppState>,
+    Path(device): Path<String>,
+) -> Result<Json<SettingsResponse>, (StatusCode, String)> {
+    let row = sqlx::query!(
+        "SELECT updated_by, updated_at FROM device_settings WHERE device_id = $1",
+        device
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let settings = state.sink.settings().get(&device);
+    Ok(Json(SettingsResponse {
+        device_id: device,
+        thresholds: settings.thresholds,
+        alert_limit_sec: settings.alert_limit_sec,
+        smoothing_window: settings.smoothing_window,
+        updated_by: row.as_ref().map(|r| r.updated_by.clone()),
+        updated_at: row.map(|r| r.updated_at),
+    }))
+}
+
+// PUT /api/devices/:id/settings
+pub async fn put_settings(
+    State(state): State<AppState>,
+    Path(device): Path<String>,
+    headers: HeaderMap,
+    Json(update): Json<SettingsUpdate>,
+) -> Result<Json<SettingsResponse>, (StatusCode, String)> {
+    state.commands.authorize(&headers)?;
+    let changed_by = changed_by(&update.changed_by)?;
+
+    // Settings and audit entry are written together, on top of the stored values
+    let db_error = |e: sqlx::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let live = state.sink.settings();
+    let _writing = live.lock_writes().await;
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    let old = current(&mut tx, &device).await.map_err(db_error)?;
+    let new = old
+        .updated(&update)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let updated_at = store(&mut tx, &device, &old, &new, &changed_by)
+        .await
+        .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    // Live pipelines pick it up with their next reading
+    live.set(&device, new);
+    println!(
+        "Settings of {} changed by {}: fidget {}, active {}, alert after {}s",
+        device, changed_by, new.thresholds.fidget, new.thresholds.active, new.alert_limit_sec
+    );
+
+    Ok(Json(SettingsResponse {
+        device_id: device,
+        thresholds: new.thresholds,
+        alert_limit_sec: new.alert_limit_sec,
+        smoothing_window: new.smoothing_window,
+        updated_by: Some(changed_by),
+        updated_at: Some(updated_at),
+    }))
+}
+
+// GET /api/devices/:id/settings/history
+pub async fn settings_history(
+    State(state): State<AppState>,
+    Path(device): Path<String>,
+) -> Result<Json<Vec<SettingsChange>>, (StatusCode, String)> {
+    let changes = sqlx::query_as!(
+        SettingsChange,
+        r#"
+        SELECT id, changed_by, changed_at, old_fidget, old_active, old_alert_limit_sec,
+               old_smoothing_window, thresh_fidget AS fidget, thresh_active AS active,
+               alert_limit_sec, smoothing_window
+        FROM settings_audit
+        WHERE device_id = $1
+        ORDER BY id DESC
+        LIMIT $2
+        "#,
+        device,
+        HISTORY_LIMIT
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(changes))
+}
+
+#[cfg(test)]
+#[path = "settings_tests.rs"]
+mod tests;
@@ -0,0 +1,152 @@
+use super::*;
+
+fn update(
+    fidget: Option<f32>,
+    active: Option<f32>,
+    alert_limit_sec: Option<u64>,
+) -> SettingsUpdate {
+    SettingsUpdate {
+        fidget,
+        active,
+        alert_limit_sec,
+        smoothing_window: None,
+        changed_by: "nurse-1".to_string(),
+    }
+}
+
+// Validation Tests
+
+#[test]
+fn test_update_keeps_missing_fields() {
+    let settings = DeviceSettings::default()
+        .updated(&update(Some(0.03), None, Some(600)))
+        .unwrap();
+
+    assert_eq!(settings.thresholds.fidget, 0.03);
+    assert_eq!(settings.thresholds.active, logic::THRESH_ACTIVE);
+    assert_eq!(settings.alert_limit_sec, 600);
+}
+
+#[test]
+fn test_update_rejects_inverted_thresholds() {
+    let defaults = DeviceSettings::default();
+    assert!(defaults.updated(&update(Some(0.05), None, None)).is_err());
+    assert!(defaults
+        .updated(&update(Some(0.03), Some(0.03), None))
+        .is_err());
+    assert!(defaults.updated(&update(Some(0.0), None, None)).is_err());
+    assert!(defaults
+        .updated(&update(None, Some(f32::NAN), None))
+        .is_err());
+}
+
+#[test]
+fn test_update_rejects_alert_limit_out_of_range() {
+    let defaults = DeviceSettings::default();
+    assert!(defaults.updated(&update(None, None, Some(10))).is_err());
+    assert!(defaults
+        .updated(&update(None, None, Some(MAX_ALERT_LIMIT_SEC + 1)))
+        .is_err());
+    assert!(defaults
+        .updated(&update(None, None, Some(MIN_ALERT_LIMIT_SEC)))
+        .is_ok());
+}
+
+#[test]
+fn test_update_sets_smoothing_window() {
+    let defaults = DeviceSettings::default();
+    assert_eq!(defaults.smoothing_window, None);
+    let calibrated = defaults
+        .updated(&SettingsUpdate {
+            smoothing_window: Some(Some(15)),
+            ..update(None, None, None)
+        })
+        .unwrap();
+    assert_eq!(calibrated.smoothing_window, Some(15));
+    // Kept when left out
+    let changed = calibrated.updated(&update(None, None, Some(600))).unwrap();
+    assert_eq!(changed.smoothing_window, Some(15));
+    // An explicit null goes back to one second at the device's rate
+    let reset = changed
+        .updated(&SettingsUpdate {
+            smoothing_window: Some(None),
+            ..update(None, None, None)
+        })
+        .unwrap();
+    assert_eq!(reset.smoothing_window, None);
+    for window in [0, MAX_SMOOTHING_WINDOW + 1] {
+        assert!(defaults
+            .updated(&SettingsUpdate {
+                smoothing_window: Some(Some(window)),
+                ..update(None, None, None)
+            })
+            .is_err());
+    }
+}
+
+// Live Copy Tests
+
+#[test]
+fn test_settings_default_per_device() {
+    let settings = Settings::default();
+    assert_eq!(settings.get("desk-3"), DeviceSettings::default());
+    assert_eq!(settings.get("desk-3").alert_limit_sec, ALERT_LIMIT_SEC);
+}
+
+#[test]
+fn test_settings_set_notifies_subscribers() {
+    let settings = Settings::default();
+    let mut rx = settings.subscribe();
+    let changed = DeviceSettings {
+        alert_limit_sec: 900,
+        ..DeviceSettings::default()
+    };
+
+    settings.set("desk-3", changed);
+
+    assert!(rx.has_changed().unwrap());
+    assert_eq!(rx.borrow_and_update()["desk-3"], changed);
+    // Other devices keep the defaults
+    assert_eq!(settings.get("desk-4"), DeviceSettings::default());
+}
+
+// Request Body Tests
+
+#[test]
+fn test_body_tells_null_from_missing_window() {
+    let body = |json: &str| serde_json::from_str::<SettingsUpdate>(json).unwrap();
+
+    assert_eq!(body(r#"{"changed_by":"nurse-1"}"#).smoothing_window, None);
+    assert_eq!(
+        body(r#"{"changed_by":"nurse-1","smoothing_window":null}"#).smoothing_window,
+        Some(None)
+    );
+    assert_eq!(
+        body(r#"{"changed_by":"nurse-1","smoothing_window":15}"#).smoothing_window,
+        Some(Some(15))
+    );
+}
+
+#[tokio::test]
+async fn test_writes_are_serialized() {
+    let settings = Settings::default();
+    let first = settings.lock_writes().await;
+
+    // A second change waits until the first has set the live copy
+    let other = settings.clone();
+    let second = tokio::spawn(async move {
+        let _writing = other.lock_writes().await;
+        other.get("desk-3").alert_limit_sec
+    });
+    tokio::task::yield_now().await;
+    settings.set(
+        "desk-3",
+        DeviceSettings {
+            alert_limit_sec: 900,
+            ..DeviceSettings::default()
+        },
+    );
+    drop(first);
+
+    assert_eq!(second.await.unwrap(), 900);
+}
@@ -0,0 +1,62 @@
+use crate::handshake::Hello;
+use crate::models::RawReading;
+use std::io;
+
+/// Anything that yields raw sensor readings: a serial port, a network
+/// stream, a file of recorded lines or a test vector
+pub trait SensorSource: Send {
+    /// Short label for logs and status messages (e.g. the port name)
+    fn name(&self) -> &str;
+
+    /// Blocks until the next reading is available.
+    /// Ok(None) means nothing usable yet (read timeout, debug line),
+    /// Err means the source is gone and should be reopened or dropped.
+    fn next_reading(&mut self) -> io::Result<Option<RawReading>>;
+
+    /// Hello received since the last call, for sources that support the handshake
+    fn take_hello(&mut self) -> Option<Hello> {
+        None
+    }
+}
+
+/// Why a line isn't a reading
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineError {
+    Text,            // Not JSON (boot messages, debug prints)
+    Partial(String), // JSON cut off before the end (dropped bytes, buffer overrun)
+    Invalid(String), // Complete JSON that isn't a valid reading
+}
+
+impl LineError {
+    pub fn reason(&self) -> &str {
+        match self {
+            LineError::Text => "not a JSON reading",
+            LineError::Partial(e) | LineError::Invalid(e) => e,
+        }
+    }
+}
+
+/// Parses one line of Arduino output, saying why it isn't a reading
+pub fn check_line(line: &str) -> Result<RawReading, LineError> {
+    let clean_line = line.trim();
+    if !clean_line.starts_with('{') {
+        return Err(LineError::Text);
+    }
+    serde_json::from_str::<RawReading>(clean_line).map_err(|e| {
+        if e.is_eof() {
+            LineError::Partial(e.to_string())
+        } else {
+            LineError::Invalid(e.to_string())
+        }
+    })
+}
+
+/// Parses one line of Arduino output. Debug prints and anything that
+/// isn't a well-formed reading are skipped.
+pub fn parse_line(line: &str) -> Option<RawReading> {
+    check_line(line).ok()
+}
+
+#[cfg(test)]
+#[path = "source_tests.rs"]
+mod tests;
@@ -0,0 +1,44 @@
+use super::*;
+
+// parse_line Tests
+
+#[test]
+fn test_parse_line_valid_reading() {
+    let reading = parse_line("{\"ts\":\"12:34:56\",\"pir\":0,\"acc\":0.045}\r\n").unwrap();
+
+    assert_eq!(reading.ts, "12:34:56");
+    assert_eq!(reading.pir, 0);
+}
+
+#[test]
+fn test_parse_line_skips_debug_output() {
+    assert_eq!(parse_line("MPU6050 found at 0x69"), None);
+    assert_eq!(parse_line(""), None);
+}
+
+#[test]
+fn test_parse_line_skips_malformed_json() {
+    assert_eq!(parse_line("{\"ts\":\"12:34:56\",\"pir\":0"), None);
+    assert_eq!(parse_line("{\"ts\":\"12:34:56\"}"), None);
+}
+
+// check_line Tests
+
+#[test]
+fn test_check_line_reasons() {
+    assert_eq!(check_line("MPU6050 found at 0x69"), Err(LineError::Text));
+    assert!(matches!(
+        check_line("{\"ts\":\"12:34:56\",\"pir\":0"),
+        Err(LineError::Partial(_))
+    ));
+    assert!(matches!(
+        check_line("{\"ts\":\"12:34:56\"}"),
+        Err(LineError::Invalid(_))
+    ));
+    assert_eq!(
+        check_line("{\"ts\":\"12:34:56\",\"pir\":0}")
+            .unwrap_err()
+            .reason(),
+        "reading needs either acc or ax/ay/az"
+    );
+}
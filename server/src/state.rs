@@ -1,4 +1,8 @@
+use crate::commands::Commands;
+use crate::pipeline::{Pipeline, Sink};
 use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 
 #[derive(Clone)]
@@ -8,4 +12,10 @@ pub struct AppState {
     pub tx: broadcast::Sender<String>,
     // Redis client for caching and pub/sub
     pub redis: redis::Client,
+    // Per-device pipeline state for batch uploads (POST /api/ingest)
+    pub pipelines: Arc<Mutex<HashMap<String, Pipeline>>>,
+    // Fan-out shared with the listeners (Redis history, broadcast, raw capture)
+    pub sink: Sink,
+    // Outgoing device commands and their acks (POST /api/devices/:id/commands)
+    pub commands: Commands,
 }
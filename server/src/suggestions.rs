@@ -0,0 +1,250 @@
+use crate::settings::{self, SettingsResponse, SettingsUpdate};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 500;
+pub const STATUSES: &[&str] = &["pending", "applied", "dismissed"];
+
+/// Thresholds suggested by the nightly analysis (`cargo run -p analysis --bin nightly`)
+#[derive(Debug, Serialize)]
+pub struct ThresholdSuggestion {
+    pub id: i64,
+    pub device_id: String,
+    pub method: String, // kmeans or jenks
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub samples: i32,
+    pub sedentary_count: i32,
+    pub fidget_count: i32,
+    pub active_count: i32,
+    pub centers: Vec<f32>, // Cluster centers of the smoothed acceleration, lowest first
+    pub current_fidget: f32,
+    pub current_active: f32,
+    pub suggested_fidget: f32,
+    pub suggested_active: f32,
+    pub status: String, // pending, applied or dismissed
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuggestionParams {
+    pub device: Option<String>,
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+impl SuggestionParams {
+    fn check(&self) -> Result<i64, (StatusCode, String)> {
+        if let Some(status) = &self.status {
+            if !STATUSES.contains(&status.as_str()) {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    format!("status must be one of {}", STATUSES.join(", ")),
+                ));
+            }
+        }
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("limit must be 1-{}", MAX_LIMIT),
+            ));
+        }
+        Ok(limit)
+    }
+}
+
+/// Body of the apply and dismiss calls
+#[derive(Debug, Deserialize)]
+pub struct Review {
+    pub changed_by: String, // Who reviewed it, self-reported (the token is shared)
+}
+
+// What a review needs of a suggestion, locked until the review commits
+#[derive(Debug)]
+struct Pending {
+    device_id: String,
+    suggested_fidget: f32,
+    suggested_active: f32,
+    status: String,
+}
+
+impl Pending {
+    fn ensure_pending(&self, id: i64) -> Result<(), (StatusCode, String)> {
+        if self.status == "pending" {
+            Ok(())
+        } else {
+            Err((
+                StatusCode::CONFLICT,
+                format!("suggestion {} is already {}", id, self.status),
+            ))
+        }
+    }
+
+    // Only the thresholds change; the alert limit and smoothing stay as they are
+    fn update(&self, changed_by: &str) -> SettingsUpdate {
+        SettingsUpdate {
+            fidget: Some(self.suggested_fidget),
+            active: Some(self.suggested_active),
+            alert_limit_sec: None,
+            smoothing_window: None,
+            changed_by: changed_by.to_string(),
+        }
+    }
+}
+
+fn db_error(e: sqlx::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+// Locks a suggestion for review
+async fn lock(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+) -> Result<Pending, (StatusCode, String)> {
+    sqlx::query_as!(
+        Pending,
+        r#"
+        SELECT device_id, suggested_fidget, suggested_active, status
+        FROM threshold_suggestions
+        WHERE id = $1
+        FOR UPDATE
+        "#,
+        id
+    )
+    .fetch_optional(&mut **tx)
+    .await
+    .map_err(db_error)?
+    .ok_or((StatusCode::NOT_FOUND, format!("no suggestion {}", id)))
+}
+
+async fn mark(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+    status: &str,
+    changed_by: &str,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+        UPDATE threshold_suggestions
+        SET status = $2, reviewed_by = $3, reviewed_at = NOW()
+        WHERE id = $1
+        "#,
+        id,
+        status,
+        changed_by
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+// GET /api/suggestions?device=<id>&status=<status>&limit=<n>
+pub async fn list_suggestions(
+    State(state): State<AppState>,
+    Query(params): Query<SuggestionParams>,
+) -> Result<Json<Vec<ThresholdSuggestion>>, (StatusCode, String)> {
+    let limit = params.check()?;
+
+    let suggestions = sqlx::query_as!(
+        ThresholdSuggestion,
+        r#"
+        SELECT id, device_id, method, window_start, window_end, samples,
+               sedentary_count, fidget_count, active_count, centers,
+               current_fidget, current_active, suggested_fidget, suggested_active,
+               status, reviewed_by, reviewed_at, created_at
+        FROM threshold_suggestions
+        WHERE ($1::TEXT IS NULL OR device_id = $1)
+          AND ($2::TEXT IS NULL OR status = $2)
+        ORDER BY id DESC
+        LIMIT $3
+        "#,
+        params.device,
+        params.status,
+        limit
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(db_error)?;
+
+    Ok(Json(suggestions))
+}
+
+// POST /api/suggestions/:id/apply
+pub async fn apply_suggestion(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+    Json(review): Json<Review>,
+) -> Result<Json<SettingsResponse>, (StatusCode, String)> {
+    state.commands.authorize(&headers)?;
+    let changed_by = settings::changed_by(&review.changed_by)?;
+
+    // Same path as a manual change: validated, stored, audited, applied live
+    let live = state.sink.settings();
+    let _writing = live.lock_writes().await;
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    let suggestion = lock(&mut tx, id).await?;
+    suggestion.ensure_pending(id)?;
+
+    let device = suggestion.device_id.clone();
+    let old = settings::current(&mut tx, &device)
+        .await
+        .map_err(db_error)?;
+    let new = old
+        .updated(&suggestion.update(&changed_by))
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+    let updated_at = settings::store(&mut tx, &device, &old, &new, &changed_by)
+        .await
+        .map_err(db_error)?;
+    mark(&mut tx, id, "applied", &changed_by).await?;
+    tx.commit().await.map_err(db_error)?;
+
+    live.set(&device, new);
+    println!(
+        "Suggestion {} applied to {} by {}: fidget {}, active {}",
+        id, device, changed_by, new.thresholds.fidget, new.thresholds.active
+    );
+
+    Ok(Json(SettingsResponse {
+        device_id: device,
+        thresholds: new.thresholds,
+        alert_limit_sec: new.alert_limit_sec,
+        smoothing_window: new.smoothing_window,
+        updated_by: Some(changed_by),
+        updated_at: Some(updated_at),
+    }))
+}
+
+// POST /api/suggestions/:id/dismiss
+pub async fn dismiss_suggestion(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+    Json(review): Json<Review>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    state.commands.authorize(&headers)?;
+    let changed_by = settings::changed_by(&review.changed_by)?;
+
+    let mut tx = state.db.begin().await.map_err(db_error)?;
+    lock(&mut tx, id).await?.ensure_pending(id)?;
+    mark(&mut tx, id, "dismissed", &changed_by).await?;
+    tx.commit().await.map_err(db_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+#[path = "suggestions_tests.rs"]
+mod tests;
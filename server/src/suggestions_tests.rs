@@ -0,0 +1,64 @@
+use super::*;
+
+fn pending(status: &str) -> Pending {
+    Pending {
+        device_id: "desk-3".to_string(),
+        suggested_fidget: 0.018,
+        suggested_active: 0.045,
+        status: status.to_string(),
+    }
+}
+
+fn params(status: Option<&str>, limit: Option<i64>) -> SuggestionParams {
+    SuggestionParams {
+        device: None,
+        status: status.map(str::to_string),
+        limit,
+    }
+}
+
+// Review Tests
+
+#[test]
+fn test_only_pending_suggestions_can_be_reviewed() {
+    assert!(pending("pending").ensure_pending(7).is_ok());
+
+    let (status, message) = pending("applied").ensure_pending(7).unwrap_err();
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(message, "suggestion 7 is already applied");
+}
+
+#[test]
+fn test_applying_keeps_alert_limit() {
+    let update = pending("pending").update("nurse-1");
+
+    assert_eq!(update.fidget, Some(0.018));
+    assert_eq!(update.active, Some(0.045));
+    assert_eq!(update.alert_limit_sec, None);
+    assert_eq!(update.changed_by, "nurse-1");
+}
+
+#[test]
+fn test_invalid_suggestion_is_rejected_by_settings() {
+    let mut suggestion = pending("pending");
+    suggestion.suggested_active = 0.01;
+    let current = crate::settings::DeviceSettings::default();
+    assert!(current.updated(&suggestion.update("nurse-1")).is_err());
+}
+
+// Query Tests
+
+#[test]
+fn test_params_defaults_and_limits() {
+    assert_eq!(params(None, None).check(), Ok(DEFAULT_LIMIT));
+    assert_eq!(params(Some("dismissed"), Some(5)).check(), Ok(5));
+    assert!(params(None, Some(0)).check().is_err());
+    assert!(params(None, Some(MAX_LIMIT + 1)).check().is_err());
+}
+
+#[test]
+fn test_params_reject_unknown_status() {
+    let (status, message) = params(Some("approved"), None).check().unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(message, "status must be one of pending, applied, dismissed");
+}
@@ -0,0 +1,89 @@
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_LIMIT: i64 = 1000;
+const MAX_LIMIT: i64 = 10000;
+
+/// One stored record with its real-time label and the offline HMM label
+/// (`cargo run -p analysis --bin smooth`)
+#[derive(Debug, Serialize)]
+pub struct TimelineEntry {
+    pub id: i32,
+    pub at: DateTime<Utc>, // Measurement time, or arrival when the device clock was unknown
+    pub state: String,
+    pub smoothed_state: Option<String>, // None until a smoothing run covered the record
+    pub acceleration_val: Option<f32>,
+    pub hmm_run_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimelineParams {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+impl TimelineParams {
+    fn check(&self) -> Result<i64, (StatusCode, String)> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from >= to {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "from must be before to".to_string(),
+                ));
+            }
+        }
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT);
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("limit must be 1-{}", MAX_LIMIT),
+            ));
+        }
+        Ok(limit)
+    }
+}
+
+// GET /api/devices/:id/timeline?from=<rfc3339>&to=<rfc3339>&limit=<n>
+// The latest `limit` records of the range, oldest first
+pub async fn get_timeline(
+    State(state): State<AppState>,
+    Path(device_id): Path<String>,
+    Query(params): Query<TimelineParams>,
+) -> Result<Json<Vec<TimelineEntry>>, (StatusCode, String)> {
+    let limit = params.check()?;
+
+    let mut entries = sqlx::query_as!(
+        TimelineEntry,
+        r#"
+        SELECT id, COALESCE(measured_at, created_at) AS "at!", state, smoothed_state,
+               acceleration_val, hmm_run_id
+        FROM sedentary_log
+        WHERE device_id = $1
+          AND ($2::TIMESTAMPTZ IS NULL OR COALESCE(measured_at, created_at) >= $2)
+          AND ($3::TIMESTAMPTZ IS NULL OR COALESCE(measured_at, created_at) < $3)
+        ORDER BY 2 DESC, id DESC
+        LIMIT $4
+        "#,
+        device_id,
+        params.from,
+        params.to,
+        limit
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    entries.reverse();
+    Ok(Json(entries))
+}
+
+#[cfg(test)]
+#[path = "timeline_tests.rs"]
+mod tests;
@@ -0,0 +1,57 @@
+use super::*;
+
+fn params(from: Option<&str>, to: Option<&str>, limit: Option<i64>) -> TimelineParams {
+    TimelineParams {
+        from: from.map(|t| t.parse().unwrap()),
+        to: to.map(|t| t.parse().unwrap()),
+        limit,
+    }
+}
+
+// Query Tests
+
+#[test]
+fn test_params_defaults_and_limits() {
+    assert_eq!(params(None, None, None).check(), Ok(DEFAULT_LIMIT));
+    assert_eq!(params(None, None, Some(MAX_LIMIT)).check(), Ok(MAX_LIMIT));
+    assert!(params(None, None, Some(0)).check().is_err());
+    assert!(params(None, None, Some(MAX_LIMIT + 1)).check().is_err());
+}
+
+#[test]
+fn test_params_need_an_ordered_range() {
+    let range = params(
+        Some("2026-10-17T09:00:00Z"),
+        Some("2026-10-17T10:00:00Z"),
+        None,
+    );
+    assert!(range.check().is_ok());
+
+    let (status, message) = params(
+        Some("2026-10-17T10:00:00Z"),
+        Some("2026-10-17T10:00:00Z"),
+        None,
+    )
+    .check()
+    .unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(message, "from must be before to");
+}
+
+#[test]
+fn test_entry_format() {
+    let entry = TimelineEntry {
+        id: 7,
+        at: "2026-10-17T09:00:00Z".parse().unwrap(),
+        state: "FIDGET".to_string(),
+        smoothed_state: Some("SEDENTARY".to_string()),
+        acceleration_val: Some(0.021),
+        hmm_run_id: Some(3),
+    };
+    let json = serde_json::to_value(&entry).unwrap();
+
+    assert_eq!(json["at"], "2026-10-17T09:00:00Z");
+    assert_eq!(json["state"], "FIDGET");
+    assert_eq!(json["smoothed_state"], "SEDENTARY");
+    assert_eq!(json["hmm_run_id"], 3);
+}
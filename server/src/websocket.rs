@@ -1,35 +1,100 @@
+use crate::pipeline::{history_key, DEVICE_STATUS, HISTORY_DEVICES};
 use crate::state::AppState;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        Query, State,
     },
     response::IntoResponse,
 };
 use redis::AsyncCommands;
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
 
-pub async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+#[derive(Debug, Deserialize)]
+pub struct WsParams {
+    pub device: Option<String>, // Only this device's readings (events always pass)
 }
 
-async fn handle_socket(mut socket: WebSocket, state: AppState) {
+// GET /ws?device=<id>
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    Query(params): Query<WsParams>,
+) -> impl IntoResponse {
+    let device = params.device.filter(|d| !d.trim().is_empty());
+    ws.on_upgrade(|socket| handle_socket(socket, state, device))
+}
+
+// Just enough of a message to route it: readings and per-device events carry a device_id
+#[derive(Deserialize)]
+struct Tagged {
+    device_id: Option<String>,
+}
+
+/// Whether a broadcast message goes to a client watching `device`
+fn wanted(msg: &str, device: Option<&str>) -> bool {
+    let Some(device) = device else {
+        return true;
+    };
+    match serde_json::from_str::<Tagged>(msg) {
+        Ok(Tagged {
+            device_id: Some(id),
+        }) => id == device,
+        _ => true,
+    }
+}
+
+async fn handle_socket(mut socket: WebSocket, state: AppState, device: Option<String>) {
     // 1. RECONNECTION BACKUP (Fetch from Redis)
     // This fills the graph immediately upon connection
     if let Ok(mut con) = state.redis.get_multiplexed_async_connection().await {
-        // Fetch last 100 records
-        let history: Vec<String> = con.lrange("sensor_history", 0, 99).await.unwrap_or(vec![]);
-
-        // Send history to frontend (reversed because lpush stores newest first)
-        for msg in history.into_iter().rev() {
+        // Last known connection status of every port
+        let statuses: Vec<String> = con.hvals(DEVICE_STATUS).await.unwrap_or_default();
+        for msg in statuses {
             let _ = socket.send(Message::Text(msg)).await;
         }
+
+        // Fetch the last 100 records of the device (or of every device)
+        let devices: Vec<String> = match &device {
+            Some(device) => vec![device.clone()],
+            None => con.smembers(HISTORY_DEVICES).await.unwrap_or_default(),
+        };
+        for id in devices {
+            let history: Vec<String> = con
+                .lrange(history_key(&id), 0, 99)
+                .await
+                .unwrap_or_default();
+
+            // Send history to frontend (reversed because lpush stores newest first)
+            for msg in history.into_iter().rev() {
+                let _ = socket.send(Message::Text(msg)).await;
+            }
+        }
     }
 
     // 2. LIVE STREAM Zero Latency
     let mut rx = state.tx.subscribe();
-    while let Ok(msg) = rx.recv().await {
+    let metrics = state.sink.metrics();
+    loop {
+        let msg = match rx.recv().await {
+            Ok(msg) => msg,
+            // A slow browser skips what it missed instead of being disconnected
+            Err(RecvError::Lagged(missed)) => {
+                metrics.broadcast_lagged("WebSocket client", missed);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        if !wanted(&msg, device.as_deref()) {
+            continue;
+        }
         if socket.send(Message::Text(msg)).await.is_err() {
             break;
         }
     }
 }
+
+#[cfg(test)]
+#[path = "websocket_tests.rs"]
+mod tests;
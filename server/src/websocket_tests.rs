@@ -0,0 +1,27 @@
+use super::*;
+
+#[test]
+fn test_unfiltered_client_gets_everything() {
+    assert!(wanted(
+        r#"{"device_id":"desk-3","state":"SEDENTARY"}"#,
+        None
+    ));
+    assert!(wanted(r#"{"event":"device_status"}"#, None));
+}
+
+#[test]
+fn test_device_filter() {
+    let reading = r#"{"device_id":"desk-3","state":"SEDENTARY","timer":12}"#;
+
+    assert!(wanted(reading, Some("desk-3")));
+    assert!(!wanted(reading, Some("desk-4")));
+}
+
+#[test]
+fn test_device_filter_passes_events() {
+    assert!(wanted(
+        r#"{"event":"link_stats","port":"/dev/ttyACM0"}"#,
+        Some("desk-3")
+    ));
+    assert!(wanted("not json", Some("desk-3")));
+}
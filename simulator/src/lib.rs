@@ -0,0 +1,305 @@
+use crc::{CRC_16_IBM_3740, Crc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+// SIGNAL MODEL (acceleration delta magnitude, same unit as the Arduino's "acc")
+const FIDGET_MIN: f32 = 0.022; // Just above the server's THRESH_FIDGET (0.020)
+const FIDGET_MAX: f32 = 0.036; // Just below THRESH_ACTIVE (0.040)
+const WALK_BASE: f32 = 0.12; // Mean delta while walking
+const WALK_SWING: f32 = 0.08; // Extra delta at each footfall
+const STEP_HZ: f32 = 1.8; // Typical walking cadence (steps per second)
+
+// EVENT RATES (per second)
+const SEATED_FIDGET_RATE: f64 = 1.0 / 90.0; // A short fidget every ~1.5 minutes when seated
+const PIR_RETRIGGER_RATE: f64 = 0.5; // PIR re-fires while someone walks past it
+const PIR_HOLD_SEC: u32 = 3; // HC-SR501 output hold time
+
+// BINARY FRAMING (same layout the server decodes)
+const FRAME_READING: u8 = 0x01;
+const CRC16: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+
+/// What the simulated person is doing during a phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    Sedentary, // Sitting still, with occasional short fidgets
+    Fidget,    // Continuous small movements
+    Walk,      // Walking bout, triggers the PIR
+}
+
+impl Activity {
+    fn parse(word: &str) -> Option<Self> {
+        match word.to_ascii_lowercase().as_str() {
+            "sedentary" | "sit" | "sitting" | "still" => Some(Activity::Sedentary),
+            "fidget" | "fidgeting" => Some(Activity::Fidget),
+            "walk" | "walking" | "active" => Some(Activity::Walk),
+            _ => None,
+        }
+    }
+}
+
+/// One step of a scenario, e.g. "25 min sedentary"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phase {
+    pub activity: Activity,
+    pub duration_secs: u64,
+}
+
+/// Parses a scenario script: phases separated by ",", ";" or "then",
+/// each written as "<duration> <activity>".
+/// Durations accept s/sec, m/min and h/hr units, with or without a space:
+/// "25 min sedentary then 2 min walk", "30s fidget, 10m sit"
+pub fn parse_scenario(script: &str) -> Result<Vec<Phase>, String> {
+    let normalized = script
+        .replace(" then ", ",")
+        .replace(" THEN ", ",")
+        .replace(';', ",");
+
+    let phases: Vec<Phase> = normalized
+        .split(',')
+        .map(str::trim)
+        .filter(|step| !step.is_empty())
+        .map(parse_phase)
+        .collect::<Result<_, _>>()?;
+
+    if phases.is_empty() {
+        return Err("scenario has no phases".to_string());
+    }
+    Ok(phases)
+}
+
+fn parse_phase(step: &str) -> Result<Phase, String> {
+    let words: Vec<&str> = step.split_whitespace().collect();
+
+    // "25m sedentary" or "25 min sedentary"
+    let (amount, unit, activity) = match words.as_slice() {
+        [quantity, activity] => {
+            let split = quantity
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(quantity.len());
+            (&quantity[..split], &quantity[split..], *activity)
+        }
+        [amount, unit, activity] => (*amount, *unit, *activity),
+        _ => return Err(format!("cannot parse phase '{}'", step)),
+    };
+
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("invalid duration in '{}'", step))?;
+    let multiplier = match unit.to_ascii_lowercase().as_str() {
+        "" | "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3600,
+        other => return Err(format!("unknown unit '{}' in '{}'", other, step)),
+    };
+    let activity =
+        Activity::parse(activity).ok_or_else(|| format!("unknown activity in '{}'", step))?;
+
+    if amount == 0 {
+        return Err(format!("phase '{}' has zero duration", step));
+    }
+    Ok(Phase {
+        activity,
+        duration_secs: amount * multiplier,
+    })
+}
+
+/// Parses "HH:MM:SS" into seconds since midnight
+pub fn parse_clock(clock: &str) -> Result<u32, String> {
+    let parts: Vec<u32> = clock
+        .split(':')
+        .map(|p| p.parse::<u32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid time '{}', expected HH:MM:SS", clock))?;
+
+    match parts.as_slice() {
+        [h, m, s] if *h < 24 && *m < 60 && *s < 60 => Ok(h * 3600 + m * 60 + s),
+        _ => Err(format!("invalid time '{}', expected HH:MM:SS", clock)),
+    }
+}
+
+/// Same wire format as the Arduino sketch: {"ts":"12:34:56","pir":0,"acc":0.045}
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Reading {
+    pub ts: String,
+    pub pir: i32,
+    pub acc: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+}
+
+impl Reading {
+    /// COBS-encoded binary frame including the 0x00 delimiter:
+    /// [0x01][hour][minute][second][pir][acc: f32 LE][crc16 LE]
+    pub fn to_frame(&self) -> Vec<u8> {
+        let secs = parse_clock(&self.ts).unwrap_or(0);
+        let mut frame = vec![
+            FRAME_READING,
+            (secs / 3600) as u8,
+            ((secs % 3600) / 60) as u8,
+            (secs % 60) as u8,
+            self.pir as u8,
+        ];
+        frame.extend_from_slice(&self.acc.to_le_bytes());
+        let crc = CRC16.checksum(&frame);
+        frame.extend_from_slice(&crc.to_le_bytes());
+
+        let mut encoded = cobs::encode_vec(&frame);
+        encoded.push(0x00);
+        encoded
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    pub rate_hz: u32,           // Samples per second (the Arduino sends 10Hz)
+    pub start_secs: u32,        // RTC time of the first sample, seconds since midnight
+    pub noise: f32,             // Standard deviation of the MPU6050 noise floor
+    pub seed: u64,              // RNG seed, same seed gives the same stream
+    pub device: Option<String>, // Board id for network outputs
+    pub repeat: bool,           // Loop the scenario forever
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            rate_hz: 10,
+            start_secs: 9 * 3600,
+            noise: 0.004,
+            seed: 42,
+            device: None,
+            repeat: false,
+        }
+    }
+}
+
+/// Generates the reading stream for a scenario, one sample per iteration
+pub struct Simulator {
+    phases: Vec<Phase>,
+    config: SimConfig,
+    rng: StdRng,
+    phase_index: usize,
+    phase_sample: u64,
+    total_samples: u64,
+    fidget_left: u32,   // Samples left in a seated fidget burst
+    pir_hold_left: u32, // Samples the PIR output stays high
+}
+
+impl Simulator {
+    pub fn new(phases: Vec<Phase>, config: SimConfig) -> Self {
+        let rng = StdRng::seed_from_u64(config.seed);
+        Simulator {
+            phases,
+            config,
+            rng,
+            phase_index: 0,
+            phase_sample: 0,
+            total_samples: 0,
+            fidget_left: 0,
+            pir_hold_left: 0,
+        }
+    }
+
+    /// Total scenario length in samples (one pass)
+    pub fn samples_per_pass(&self) -> u64 {
+        self.phases.iter().map(|p| p.duration_secs).sum::<u64>() * self.config.rate_hz as u64
+    }
+
+    // Gaussian noise via Box-Muller
+    fn noise(&mut self) -> f32 {
+        let u1: f32 = self.rng.gen_range(f32::EPSILON..1.0);
+        let u2: f32 = self.rng.r#gen();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos() * self.config.noise
+    }
+
+    fn per_sample(&self, rate_per_sec: f64) -> f64 {
+        rate_per_sec / self.config.rate_hz as f64
+    }
+
+    fn sample(&mut self, activity: Activity) -> (i32, f32) {
+        let rate = self.config.rate_hz;
+        let noise = self.noise();
+
+        let acc = match activity {
+            Activity::Sedentary => {
+                if self.fidget_left == 0 && self.rng.gen_bool(self.per_sample(SEATED_FIDGET_RATE)) {
+                    self.fidget_left = self.rng.gen_range(1..=3) * rate;
+                }
+                if self.fidget_left > 0 {
+                    self.fidget_left -= 1;
+                    self.rng.gen_range(FIDGET_MIN..FIDGET_MAX) + noise
+                } else {
+                    noise
+                }
+            }
+            Activity::Fidget => self.rng.gen_range(FIDGET_MIN..FIDGET_MAX) + noise,
+            Activity::Walk => {
+                let t = self.phase_sample as f32 / rate as f32;
+                let footfall = (std::f32::consts::PI * STEP_HZ * t).sin().abs();
+                WALK_BASE + WALK_SWING * footfall + noise * 5.0
+            }
+        };
+
+        // PIR fires when walking starts and keeps re-triggering during the bout
+        if activity == Activity::Walk
+            && (self.phase_sample == 0 || self.rng.gen_bool(self.per_sample(PIR_RETRIGGER_RATE)))
+        {
+            self.pir_hold_left = PIR_HOLD_SEC * rate;
+        }
+        let pir = if self.pir_hold_left > 0 {
+            self.pir_hold_left -= 1;
+            1
+        } else {
+            0
+        };
+
+        // The Arduino sends a magnitude, so it is never negative
+        (pir, acc.abs())
+    }
+
+    fn timestamp(&self) -> String {
+        let elapsed = self.total_samples / self.config.rate_hz as u64;
+        let secs = (self.config.start_secs as u64 + elapsed) % 86_400;
+        format!(
+            "{:02}:{:02}:{:02}",
+            secs / 3600,
+            (secs % 3600) / 60,
+            secs % 60
+        )
+    }
+}
+
+impl Iterator for Simulator {
+    type Item = Reading;
+
+    fn next(&mut self) -> Option<Reading> {
+        // Advance to the phase this sample belongs to
+        loop {
+            let phase = self.phases.get(self.phase_index)?;
+            if self.phase_sample < phase.duration_secs * self.config.rate_hz as u64 {
+                break;
+            }
+            self.phase_index += 1;
+            self.phase_sample = 0;
+            if self.phase_index == self.phases.len() && self.config.repeat {
+                self.phase_index = 0;
+            }
+        }
+
+        let activity = self.phases[self.phase_index].activity;
+        let (pir, acc) = self.sample(activity);
+        let reading = Reading {
+            ts: self.timestamp(),
+            pir,
+            acc: (acc * 1000.0).round() / 1000.0, // Arduino prints 3 decimals
+            device: self.config.device.clone(),
+        };
+
+        self.phase_sample += 1;
+        self.total_samples += 1;
+        Some(reading)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,257 @@
+use clap::Parser;
+use simulator::{SimConfig, Simulator, parse_clock, parse_scenario};
+use std::io::{self, Write};
+use std::net::{TcpStream, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Generates Arduino-style sensor readings for demos and tests
+#[derive(Parser, Debug)]
+#[command(name = "simulate")]
+struct Args {
+    /// Scenario script, e.g. "25 min sedentary then 2 min walk"
+    #[arg(short, long, default_value = "25 min sedentary then 2 min walk")]
+    scenario: String,
+
+    /// Where to send readings: stdout, pty, tcp://host:port or udp://host:port
+    #[arg(short, long, default_value = "stdout")]
+    output: String,
+
+    /// Samples per second
+    #[arg(long, default_value_t = 10)]
+    rate: u32,
+
+    /// Playback speed multiplier (60 = one simulated minute per second)
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+
+    /// Emit as fast as possible, ignoring --speed
+    #[arg(long)]
+    fast: bool,
+
+    /// RTC time of the first reading (HH:MM:SS), defaults to the current local time
+    #[arg(long)]
+    start: Option<String>,
+
+    /// Board id added to each reading (used by the TCP/UDP ingestion)
+    #[arg(long)]
+    device: Option<String>,
+
+    /// Standard deviation of the accelerometer noise floor
+    #[arg(long, default_value_t = 0.004)]
+    noise: f32,
+
+    /// RNG seed, the same seed replays the same stream
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+
+    /// Loop the scenario forever
+    #[arg(long)]
+    repeat: bool,
+
+    /// Send COBS/CRC16 binary frames instead of JSON lines (stdout and pty only)
+    #[arg(long)]
+    binary: bool,
+
+    /// Announce the device with a hello line first (protocol v2 firmware)
+    #[arg(long)]
+    hello: bool,
+}
+
+/// Destination for the generated lines
+enum Output {
+    Stdout(io::Stdout),
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Pty(pty::Pty),
+}
+
+impl Output {
+    fn open(target: &str) -> Result<Self, String> {
+        if target == "stdout" || target == "-" {
+            Ok(Output::Stdout(io::stdout()))
+        } else if target == "pty" {
+            open_pty()
+        } else if let Some(addr) = target.strip_prefix("tcp://") {
+            let stream = TcpStream::connect(addr).map_err(|e| format!("{}: {}", target, e))?;
+            Ok(Output::Tcp(stream))
+        } else if let Some(addr) = target.strip_prefix("udp://") {
+            let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+            socket
+                .connect(addr)
+                .map_err(|e| format!("{}: {}", target, e))?;
+            Ok(Output::Udp(socket))
+        } else {
+            Err(format!(
+                "unknown output '{}', expected stdout, pty, tcp://host:port or udp://host:port",
+                target
+            ))
+        }
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => writeln!(out, "{}", line),
+            Output::Tcp(stream) => writeln!(stream, "{}", line),
+            Output::Udp(socket) => socket.send(format!("{}\n", line).as_bytes()).map(|_| ()),
+            #[cfg(unix)]
+            Output::Pty(pty) => pty.send(line),
+        }
+    }
+
+    fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.write_all(frame).and_then(|_| out.flush()),
+            #[cfg(unix)]
+            Output::Pty(pty) => pty.send_frame(frame),
+            _ => Err(io::Error::other(
+                "binary frames are only supported on serial",
+            )),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn open_pty() -> Result<Output, String> {
+    let pty = pty::Pty::open().map_err(|e| format!("pty: {}", e))?;
+    eprintln!("Pseudo-terminal ready: {}", pty.path);
+    eprintln!("Start the server with SERIAL_PORT={}", pty.path);
+    Ok(Output::Pty(pty))
+}
+
+#[cfg(not(unix))]
+fn open_pty() -> Result<Output, String> {
+    Err("pty output is only supported on unix".to_string())
+}
+
+#[cfg(unix)]
+mod pty {
+    use nix::pty::openpty;
+    use nix::sys::termios::{SetArg, cfmakeraw, tcgetattr, tcsetattr};
+    use nix::unistd::ttyname;
+    use std::fs::File;
+    use std::io::{self, Write};
+    use std::os::fd::OwnedFd;
+
+    /// Master side of a pseudo-terminal; the server opens the slave path
+    /// exactly like /dev/ttyACM0
+    pub struct Pty {
+        master: File,
+        // Held open so the pty doesn't hang up while the server reconnects
+        _slave: OwnedFd,
+        pub path: String,
+    }
+
+    impl Pty {
+        pub fn open() -> nix::Result<Self> {
+            let pair = openpty(None, None)?;
+
+            // Raw mode: no echo back to us, no newline translation
+            let mut termios = tcgetattr(&pair.slave)?;
+            cfmakeraw(&mut termios);
+            tcsetattr(&pair.slave, SetArg::TCSANOW, &termios)?;
+
+            let path = ttyname(&pair.slave)?.to_string_lossy().into_owned();
+            Ok(Pty {
+                master: File::from(pair.master),
+                _slave: pair.slave,
+                path,
+            })
+        }
+
+        pub fn send(&mut self, line: &str) -> io::Result<()> {
+            // Arduino Serial.println ends lines with CRLF
+            write!(self.master, "{}\r\n", line)
+        }
+
+        pub fn send_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+            self.master.write_all(frame)
+        }
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let phases = parse_scenario(&args.scenario).unwrap_or_else(|e| exit_with(&e));
+    if args.rate == 0 {
+        exit_with("--rate must be at least 1");
+    }
+    if !args.fast && args.speed <= 0.0 {
+        exit_with("--speed must be positive");
+    }
+
+    let start_secs = match &args.start {
+        Some(clock) => parse_clock(clock).unwrap_or_else(|e| exit_with(&e)),
+        None => {
+            let now = chrono::Local::now().time();
+            chrono::Timelike::num_seconds_from_midnight(&now)
+        }
+    };
+
+    if args.binary && args.output != "stdout" && args.output != "-" && args.output != "pty" {
+        exit_with("--binary is only supported with stdout and pty outputs");
+    }
+    let mut output = Output::open(&args.output).unwrap_or_else(|e| exit_with(&e));
+
+    let config = SimConfig {
+        rate_hz: args.rate,
+        start_secs,
+        noise: args.noise,
+        seed: args.seed,
+        device: args.device.clone(),
+        repeat: args.repeat,
+    };
+
+    if args.hello {
+        let hello = hello_line(args.device.as_deref().unwrap_or("simulator"), args.rate);
+        if let Err(e) = output.send(&hello) {
+            exit_with(&format!("write failed: {}", e));
+        }
+    }
+
+    eprintln!("Simulating {:?}", phases);
+    let simulator = Simulator::new(phases, config);
+
+    // Pace against the start instant so sleep jitter doesn't accumulate
+    let interval = Duration::from_secs_f64(1.0 / (args.rate as f64 * args.speed));
+    let started = Instant::now();
+
+    for (n, reading) in simulator.enumerate() {
+        if !args.fast {
+            let due = started + interval.mul_f64(n as f64);
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+        }
+
+        let sent = if args.binary {
+            output.send_frame(&reading.to_frame())
+        } else {
+            output.send(&serde_json::to_string(&reading).unwrap())
+        };
+        if let Err(e) = sent {
+            exit_with(&format!("write failed: {}", e));
+        }
+    }
+}
+
+fn hello_line(device: &str, rate_hz: u32) -> String {
+    serde_json::json!({
+        "hello": {
+            "device": device,
+            "firmware": concat!("sim-", env!("CARGO_PKG_VERSION")),
+            "protocol": 2,
+            "rate_hz": rate_hz,
+            "sensors": ["pir", "mpu6050"],
+        }
+    })
+    .to_string()
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("simulate: {}", message);
+    std::process::exit(1);
+}
@@ -0,0 +1,178 @@
+use super::*;
+
+fn config() -> SimConfig {
+    SimConfig::default()
+}
+
+fn mean_acc(readings: &[Reading]) -> f32 {
+    readings.iter().map(|r| r.acc).sum::<f32>() / readings.len() as f32
+}
+
+// Scenario Parsing Tests
+
+#[test]
+fn test_parse_scenario_with_then() {
+    let phases = parse_scenario("25 min sedentary then 2 min walk").unwrap();
+
+    assert_eq!(
+        phases,
+        vec![
+            Phase {
+                activity: Activity::Sedentary,
+                duration_secs: 1500
+            },
+            Phase {
+                activity: Activity::Walk,
+                duration_secs: 120
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_scenario_compact_units() {
+    let phases = parse_scenario("30s fidget, 10m sit; 1h still").unwrap();
+
+    assert_eq!(phases.len(), 3);
+    assert_eq!(phases[0].activity, Activity::Fidget);
+    assert_eq!(phases[0].duration_secs, 30);
+    assert_eq!(phases[1].duration_secs, 600);
+    assert_eq!(phases[2].duration_secs, 3600);
+}
+
+#[test]
+fn test_parse_scenario_bare_seconds() {
+    let phases = parse_scenario("45 walking").unwrap();
+    assert_eq!(phases[0].duration_secs, 45);
+}
+
+#[test]
+fn test_parse_scenario_errors() {
+    assert!(parse_scenario("").is_err());
+    assert!(parse_scenario("5 min dancing").is_err());
+    assert!(parse_scenario("5 fortnights sit").is_err());
+    assert!(parse_scenario("sit for a while").is_err());
+    assert!(parse_scenario("0 min sit").is_err());
+}
+
+#[test]
+fn test_parse_clock() {
+    assert_eq!(parse_clock("00:00:00"), Ok(0));
+    assert_eq!(parse_clock("09:30:15"), Ok(9 * 3600 + 30 * 60 + 15));
+    assert!(parse_clock("24:00:00").is_err());
+    assert!(parse_clock("9:30").is_err());
+}
+
+// Simulator Tests
+
+#[test]
+fn test_simulator_sample_count_and_timestamps() {
+    let phases = parse_scenario("2s sit, 1s walk").unwrap();
+    let readings: Vec<Reading> = Simulator::new(phases, config()).collect();
+
+    assert_eq!(readings.len(), 30);
+    assert_eq!(readings[0].ts, "09:00:00");
+    assert_eq!(readings[9].ts, "09:00:00");
+    assert_eq!(readings[10].ts, "09:00:01");
+    assert_eq!(readings[29].ts, "09:00:02");
+}
+
+#[test]
+fn test_simulator_timestamp_wraps_at_midnight() {
+    let phases = parse_scenario("2s sit").unwrap();
+    let config = SimConfig {
+        start_secs: 86_399,
+        ..config()
+    };
+    let readings: Vec<Reading> = Simulator::new(phases, config).collect();
+
+    assert_eq!(readings[0].ts, "23:59:59");
+    assert_eq!(readings[10].ts, "00:00:00");
+}
+
+#[test]
+fn test_simulator_activity_levels() {
+    let sit: Vec<Reading> =
+        Simulator::new(parse_scenario("60s fidget").unwrap(), config()).collect();
+    let walk: Vec<Reading> =
+        Simulator::new(parse_scenario("60s walk").unwrap(), config()).collect();
+
+    let fidget_mean = mean_acc(&sit);
+    assert!(fidget_mean > 0.020 && fidget_mean < 0.040);
+    assert!(mean_acc(&walk) > 0.040);
+}
+
+#[test]
+fn test_simulator_sedentary_stays_below_fidget_threshold() {
+    let readings: Vec<Reading> =
+        Simulator::new(parse_scenario("10 min sit").unwrap(), config()).collect();
+
+    assert!(mean_acc(&readings) < 0.020);
+    assert!(readings.iter().all(|r| r.pir == 0 && r.acc >= 0.0));
+}
+
+#[test]
+fn test_simulator_walk_triggers_pir() {
+    let readings: Vec<Reading> =
+        Simulator::new(parse_scenario("10s walk").unwrap(), config()).collect();
+
+    assert_eq!(readings[0].pir, 1);
+    assert!(readings.iter().filter(|r| r.pir == 1).count() >= 30);
+}
+
+#[test]
+fn test_simulator_same_seed_same_stream() {
+    let phases = parse_scenario("30s sit, 10s walk").unwrap();
+    let a: Vec<Reading> = Simulator::new(phases.clone(), config()).collect();
+    let b: Vec<Reading> = Simulator::new(phases, config()).collect();
+
+    assert_eq!(a, b);
+}
+
+#[test]
+fn test_simulator_repeat_loops_forever() {
+    let phases = parse_scenario("1s sit").unwrap();
+    let config = SimConfig {
+        repeat: true,
+        ..config()
+    };
+
+    assert_eq!(Simulator::new(phases, config).take(100).count(), 100);
+}
+
+#[test]
+fn test_reading_wire_format() {
+    let reading = Reading {
+        ts: "12:34:56".to_string(),
+        pir: 0,
+        acc: 0.045,
+        device: None,
+    };
+
+    assert_eq!(
+        serde_json::to_string(&reading).unwrap(),
+        r#"{"ts":"12:34:56","pir":0,"acc":0.045}"#
+    );
+}
+
+#[test]
+fn test_reading_binary_frame() {
+    let reading = Reading {
+        ts: "10:20:30".to_string(),
+        pir: 1,
+        acc: 0.045,
+        device: None,
+    };
+    let frame = reading.to_frame();
+
+    assert_eq!(frame.last(), Some(&0x00));
+    assert!(!frame[..frame.len() - 1].contains(&0x00));
+
+    let decoded = cobs::decode_vec(&frame[..frame.len() - 1]).unwrap();
+    assert_eq!(&decoded[..5], &[0x01, 10, 20, 30, 1]);
+    assert_eq!(decoded[5..9], 0.045f32.to_le_bytes());
+    assert_eq!(
+        u16::from_le_bytes([decoded[9], decoded[10]]),
+        CRC16.checksum(&decoded[..9])
+    );
+}
@@ -0,0 +1,69 @@
+// Integration tests for the simulator crate
+// These run whole scenarios the way the `simulate` binary does
+
+use simulator::{SimConfig, Simulator, parse_scenario};
+
+// Server-side classification, duplicated here to check the generated signal lands in the right band
+fn classify(pir: i32, smoothed: f32) -> &'static str {
+    if pir == 1 || smoothed > 0.040 {
+        "ACTIVE"
+    } else if smoothed > 0.020 {
+        "FIDGET"
+    } else {
+        "SEDENTARY"
+    }
+}
+
+#[test]
+fn test_alert_demo_scenario_reaches_alert_limit() {
+    // "25 min sedentary then 2 min walk" should accumulate > 20 minutes of sedentary seconds
+    let phases = parse_scenario("25 min sedentary then 2 min walk").unwrap();
+    let readings: Vec<_> = Simulator::new(phases, SimConfig::default()).collect();
+
+    let mut window: Vec<f32> = Vec::new();
+    let mut timer = 0u64;
+    let mut max_timer = 0u64;
+    let mut last_ts = String::new();
+
+    for reading in &readings {
+        window.push(reading.acc);
+        if window.len() > 10 {
+            window.remove(0);
+        }
+        let smoothed = window.iter().sum::<f32>() / window.len() as f32;
+        let state = classify(reading.pir, smoothed);
+
+        if reading.ts != last_ts {
+            last_ts = reading.ts.clone();
+            match state {
+                "ACTIVE" => timer = 0,
+                "SEDENTARY" => timer += 1,
+                _ => {}
+            }
+        }
+        max_timer = max_timer.max(timer);
+    }
+
+    assert!(max_timer >= 1200, "max sedentary timer was {}", max_timer);
+    // The walk at the end resets the timer
+    assert_eq!(timer, 0);
+}
+
+#[test]
+fn test_output_is_valid_arduino_json() {
+    let phases = parse_scenario("5s sit, 5s walk").unwrap();
+    let config = SimConfig {
+        device: Some("sim-1".to_string()),
+        ..SimConfig::default()
+    };
+
+    for reading in Simulator::new(phases, config) {
+        let line = serde_json::to_string(&reading).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+
+        assert!(value["ts"].is_string());
+        assert!(value["pir"].is_i64());
+        assert!(value["acc"].is_f64());
+        assert_eq!(value["device"], "sim-1");
+    }
+}